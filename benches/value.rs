@@ -0,0 +1,92 @@
+//! Compares the two layouts of `Value`, run once for each:
+//!
+//! ```text
+//! cargo bench --bench value
+//! cargo bench --bench value --features safe-value
+//! ```
+//!
+//! Each case is timed over a number of runs and the fastest one is reported, which is the least
+//! disturbed by the rest of the machine.
+
+use std::hint::black_box;
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+use yellowstone::value::{allocate_object, Value};
+use yellowstone::vm::VM;
+
+const RUNS: usize = 10;
+
+/// Runs `case` `RUNS` times and returns the fastest run.
+fn fastest(mut case: impl FnMut()) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            case();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!("{name:<28} {elapsed:>12.3?}");
+}
+
+/// A script spending its time pushing, popping and comparing numbers.
+fn arithmetic() -> Duration {
+    let source = "var i = 0; var total = 0; while (i < 2000) { total = total + i * 2; i = i + 1; }"
+        .to_string();
+    fastest(|| {
+        let mut vm = VM::default();
+        black_box(vm.interpret(&source).unwrap());
+        vm.free_objects();
+    })
+}
+
+/// A script creating a string object on every iteration.
+fn strings() -> Duration {
+    let source = "var i = 0; var text = \"\"; while (i < 2000) { text = text + \"a\"; i = i + 1; }"
+        .to_string();
+    fastest(|| {
+        let mut vm = VM::default();
+        black_box(vm.interpret(&source).unwrap());
+        vm.free_objects();
+    })
+}
+
+/// Host code cloning and comparing values of every kind.
+fn clone_and_compare() -> Duration {
+    let values = [
+        Value::nil_value(),
+        Value::bool_val(true),
+        Value::number_value(1.5),
+        allocate_object("yellowstone".to_string()),
+    ];
+    fastest(|| {
+        let mut equal = 0;
+        for _ in 0..100_000 {
+            for value in &values {
+                let copy = black_box(value.clone());
+                if copy == *value {
+                    equal += 1;
+                }
+            }
+        }
+        black_box(equal);
+    })
+}
+
+fn main() {
+    let layout = if cfg!(feature = "safe-value") {
+        "safe enum"
+    } else {
+        "tagged union"
+    };
+    println!(
+        "value layout: {layout}, {} bytes per value",
+        size_of::<Value>()
+    );
+    report("script arithmetic", arithmetic());
+    report("script strings", strings());
+    report("clone and compare", clone_and_compare());
+}
@@ -0,0 +1,317 @@
+//! Reads the listing written by `debug::disassemble` back into a chunk, so bytecode can be
+//! written by hand for tests and compiler output can be compared as text.
+//!
+//! Instruction offsets at the start of a line are only there to be read, they are recomputed, and
+//! so is the source line when it is left out. Jump targets are offsets into the code being
+//! assembled. The value shown after a constant index is checked against the constant. Blank lines
+//! and lines starting with `;` are skipped.
+
+use crate::chunk::Chunk;
+use crate::chunk::OpCode::{self, *};
+use crate::error::InterpretError;
+use crate::value::{allocate_object, Value};
+use anyhow::{Context, Result};
+
+/// Instructions without operands, by name.
+const SIMPLE: [OpCode; 28] = [
+    OP_NIL,
+    OP_TRUE,
+    OP_FALSE,
+    OP_EQUAL,
+    OP_GREATER,
+    OP_LESS,
+    OP_NOT_EQUAL,
+    OP_GREATER_EQUAL,
+    OP_LESS_EQUAL,
+    OP_RETURN,
+    OP_DEBUG,
+    OP_NEGATE,
+    OP_NOT,
+    OP_ADD,
+    OP_SUBTRACT,
+    OP_MULTIPLY,
+    OP_DIVIDE,
+    OP_MODULO,
+    OP_POWER,
+    OP_PRINT,
+    OP_POP,
+    OP_ASSERT_EQ,
+    OP_ASSERT_EQ_MESSAGE,
+    OP_JUMP_IF_FALSE,
+    OP_JUMP_IF_TRUE,
+    OP_JUMP,
+    OP_LOOP,
+    OP_PLACEHOLDER_JUMP_AMOUNT,
+];
+
+enum Section {
+    Constants,
+    Code,
+}
+
+/// A jump whose amount is filled in once every instruction is in place.
+struct Jump {
+    /// offset of the `OP_JUMP_AMOUNT` after the jump
+    operand: usize,
+    target: i64,
+    backwards: bool,
+    /// line of the listing, for errors
+    number: usize,
+}
+
+pub fn assemble(listing: &str) -> Result<Chunk> {
+    let mut chunk = Chunk::default();
+    let mut section = None;
+    let mut line = 1;
+    let mut jumps = Vec::new();
+
+    for (number, text) in listing.lines().enumerate() {
+        let number = number + 1;
+        let text = text.trim();
+        if text.is_empty() || text.starts_with(';') || text.starts_with("==") {
+            continue;
+        }
+        match text {
+            "-- constants --" => {
+                section = Some(Section::Constants);
+                continue;
+            }
+            "-- code --" => {
+                section = Some(Section::Code);
+                continue;
+            }
+            _ => {}
+        }
+
+        match section {
+            None => return fail(number, "expected `-- constants --` or `-- code --`"),
+            Some(Section::Constants) => {
+                let (index, rest) = text.split_once(' ').unwrap_or((text, ""));
+                if index.parse() != Ok(chunk.constants.len()) {
+                    return fail(
+                        number,
+                        format!("expected constant {:04}", chunk.constants.len()),
+                    );
+                }
+                let value = whole_value(rest, number)?;
+                chunk.add_constant(value);
+            }
+            Some(Section::Code) => {
+                let Some(start) = text.find("OP_") else {
+                    return fail(number, "expected an instruction");
+                };
+                match text[..start].split_whitespace().last() {
+                    None | Some("|") => {}
+                    Some(source_line) => match source_line.parse() {
+                        Ok(source_line) => line = source_line,
+                        Err(_) => return fail(number, format!("invalid line `{source_line}`")),
+                    },
+                }
+
+                let text = &text[start..];
+                let (name, operands) = text.split_once(' ').unwrap_or((text, ""));
+                let operands = operands.trim();
+                let op = match name {
+                    "OP_CONSTANT" => OP_CONSTANT(whole_value(operands, number)?),
+                    "OP_GET_LOCAL" | "OP_SET_LOCAL" | "OP_DEFINE_GLOBAL" | "OP_GET_GLOBAL"
+                    | "OP_SET_GLOBAL" | "OP_IMPORT" | "OP_GET_PROPERTY" => {
+                        let (index, value) = operands.split_once(' ').unwrap_or((operands, ""));
+                        let index = count(index, number)?;
+                        if !value.is_empty() {
+                            let value = whole_value(value, number)?;
+                            match chunk.constants.get(index) {
+                                Some(constant) if constant.repr() == value.repr() => {}
+                                _ => {
+                                    return fail(
+                                        number,
+                                        format!("constant {index} is not {}", value.repr()),
+                                    )
+                                }
+                            }
+                        }
+                        match name {
+                            "OP_GET_LOCAL" => OP_GET_LOCAL(index),
+                            "OP_SET_LOCAL" => OP_SET_LOCAL(index),
+                            "OP_DEFINE_GLOBAL" => OP_DEFINE_GLOBAL(index),
+                            "OP_GET_GLOBAL" => OP_GET_GLOBAL(index),
+                            "OP_SET_GLOBAL" => OP_SET_GLOBAL(index),
+                            "OP_IMPORT" => OP_IMPORT(index),
+                            _ => OP_GET_PROPERTY(index),
+                        }
+                    }
+                    "OP_CALL" => OP_CALL(count(operands, number)?),
+                    "OP_JUMP_AMOUNT" => OP_JUMP_AMOUNT(count(operands, number)?),
+                    _ => match SIMPLE.iter().find(|op| op.name() == name) {
+                        Some(op) => op.clone(),
+                        None => return fail(number, format!("unknown instruction `{name}`")),
+                    },
+                };
+
+                let is_jump = matches!(op, OP_JUMP | OP_JUMP_IF_FALSE | OP_JUMP_IF_TRUE | OP_LOOP);
+                let is_simple = SIMPLE.iter().any(|simple| simple.name() == name);
+                if is_simple && !is_jump && !operands.is_empty() {
+                    return fail(number, format!("`{name}` takes no operand"));
+                }
+                if is_jump && !operands.is_empty() {
+                    let Some(target) = operands.strip_prefix("->") else {
+                        return fail(number, "expected `-> target` after a jump");
+                    };
+                    let Ok(target) = target.trim().parse() else {
+                        return fail(number, format!("invalid jump target `{}`", target.trim()));
+                    };
+                    jumps.push(Jump {
+                        operand: chunk.code.len() + 1,
+                        target,
+                        backwards: matches!(op, OP_LOOP),
+                        number,
+                    });
+                    chunk.write_chunk(op, line);
+                    chunk.write_chunk(OP_JUMP_AMOUNT(0), line);
+                    continue;
+                }
+                chunk.write_chunk(op, line);
+            }
+        }
+    }
+
+    // Jumps are counted from the instruction after the amount.
+    for jump in jumps {
+        let from = jump.operand as i64 + 1;
+        let amount = if jump.backwards {
+            from - jump.target
+        } else {
+            jump.target - from
+        };
+        if amount < 0 {
+            return fail(
+                jump.number,
+                format!("cannot jump from {:04} to {:04}", from - 2, jump.target),
+            );
+        }
+        chunk.code[jump.operand] = OP_JUMP_AMOUNT(amount as usize);
+    }
+    Ok(chunk)
+}
+
+fn fail<T>(number: usize, message: impl ToString) -> Result<T> {
+    Err(InterpretError::COMPILE_ERROR).context(format!("[line {number}] {}", message.to_string()))
+}
+
+fn count(text: &str, number: usize) -> Result<usize> {
+    match text.trim().parse() {
+        Ok(count) => Ok(count),
+        Err(_) => fail(number, format!("expected a number, found `{text}`")),
+    }
+}
+
+/// The constant written in `text`, which must not be followed by anything else.
+fn whole_value(text: &str, number: usize) -> Result<Value> {
+    let text = text.trim();
+    if let Some(quoted) = text.strip_prefix('"') {
+        let mut string = String::new();
+        let mut chars = quoted.chars();
+        loop {
+            match chars.next() {
+                None => return fail(number, "unterminated string"),
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    _ => return fail(number, "invalid escape in string"),
+                },
+                Some(c) => string.push(c),
+            }
+        }
+        if !chars.as_str().trim().is_empty() {
+            return fail(number, format!("unexpected `{}`", chars.as_str().trim()));
+        }
+        return Ok(allocate_object(string));
+    }
+
+    match text {
+        "nil" => Ok(Value::nil_value()),
+        "true" => Ok(Value::bool_val(true)),
+        "false" => Ok(Value::bool_val(false)),
+        _ => match text.parse() {
+            Ok(number) => Ok(Value::number_value(number)),
+            Err(_) => fail(number, format!("expected a constant, found `{text}`")),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble;
+    use crate::compiler::compile;
+    use crate::debug::disassemble;
+    use crate::vm::VM;
+
+    #[test]
+    fn assemble_round_trip_test() {
+        let source = "
+            var total = 0;
+            for (var i = 0; i < 3; i = i + 1) { total = total + i; }
+            if (total >= 3 or false) { print \"big\ttotal\"; } else { print nil; }
+            while (!(total == 0)) total = total - 1;
+            assert_eq(sqrt(4), 2.5 - 0.5);
+        ";
+        let chunk = compile(&source.to_string()).unwrap();
+        let listing = disassemble(&chunk, "round trip");
+        let assembled = assemble(&listing).unwrap();
+
+        assert_eq!(listing, disassemble(&assembled, "round trip"));
+        assert_eq!(chunk.lines, assembled.lines);
+        assert_eq!(format!("{:?}", chunk.code), format!("{:?}", assembled.code));
+    }
+
+    #[test]
+    fn assemble_by_hand_test() {
+        // Counts down from 3 and leaves the last value on the stack.
+        let listing = "
+            -- constants --
+            0000 \"n\"
+            -- code --
+            OP_CONSTANT 3
+            OP_DEFINE_GLOBAL 0 \"n\"
+            ; loop start
+            2 OP_GET_GLOBAL 0
+            OP_CONSTANT 0
+            OP_GREATER
+            OP_JUMP_IF_FALSE -> 0015
+            OP_POP
+            OP_GET_GLOBAL 0
+            OP_CONSTANT 1
+            OP_SUBTRACT
+            OP_SET_GLOBAL 0
+            OP_POP
+            OP_LOOP -> 0002
+            OP_POP
+            OP_GET_GLOBAL 0
+            OP_RETURN
+        ";
+        let chunk = assemble(listing).unwrap();
+        assert_eq!("2_16_", chunk.lines);
+        let result = VM::default().interpret_chunk(chunk).unwrap();
+        assert_eq!(Some(0.), result.map(|value| value.as_number().unwrap()));
+    }
+
+    #[test]
+    fn assemble_errors_test() {
+        let error = |listing: &str| format!("{:#}", assemble(listing).err().unwrap());
+        assert!(error("OP_NIL").contains("[line 1] expected `-- constants --`"));
+        assert!(error("-- code --\nOP_PUSH").contains("[line 2] unknown instruction `OP_PUSH`"));
+        assert!(error("-- code --\nOP_NIL 1").contains("takes no operand"));
+        assert!(error("-- constants --\n0001 1").contains("expected constant 0000"));
+        assert!(
+            error("-- constants --\n0000 \"a\"\n-- code --\nOP_GET_GLOBAL 0 \"b\"")
+                .contains("constant 0 is not \"b\"")
+        );
+        assert!(error("-- code --\nOP_JUMP -> 0000").contains("cannot jump from 0000 to 0000"));
+        assert!(error("-- code --\nOP_CONSTANT \"open").contains("unterminated string"));
+        let root = assemble("-- code --\nOP_CALL x").err().unwrap();
+        assert_eq!("COMPILE_ERROR", root.root_cause().to_string());
+    }
+}
@@ -0,0 +1,199 @@
+//! Typed syntax tree of a yellowstone program. `parser` builds it from source code, `codegen` turns
+//! it into a chunk, and tools such as a formatter or linter can read it without running anything.
+
+use std::fmt::{Display, Formatter};
+
+/// Where a node came from in the source: the byte range it covers and the line it starts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize) -> Self {
+        Span { start, end, line }
+    }
+
+    /// The span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start, other.end.max(self.end), self.line)
+    }
+
+    /// The source text the span covers.
+    pub fn slice(self, source: &str) -> &str {
+        &source[self.start..self.end]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Number(f32),
+    /// The string without its quotes.
+    String(String),
+    Bool(bool),
+    Nil,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+}
+
+/// `and` and `or`, which only evaluate their right side when they need it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnaryOp::Negate => write!(f, "-"),
+            UnaryOp::Not => write!(f, "!"),
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Power => "**",
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+impl Display for LogicalOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogicalOp::And => write!(f, "and"),
+            LogicalOp::Or => write!(f, "or"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Literal(Literal),
+    Variable(String),
+    Assign {
+        name: String,
+        value: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Logical {
+        op: LogicalOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// An expression in parentheses, kept so tools can reproduce them.
+    Grouping(Box<Expr>),
+    Call {
+        callee: Box<Expr>,
+        arguments: Vec<Expr>,
+    },
+    /// Reading a member of a module, `module.name`.
+    Get {
+        object: Box<Expr>,
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    /// An expression used as a statement. Its value is left on the stack.
+    Expression(Expr),
+    Print(Expr),
+    Var {
+        name: String,
+        initializer: Option<Expr>,
+    },
+    Block(Vec<Stmt>),
+    If {
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+    },
+    While {
+        condition: Expr,
+        body: Box<Stmt>,
+    },
+    For {
+        initializer: Option<Box<Stmt>>,
+        condition: Option<Expr>,
+        increment: Option<Expr>,
+        body: Box<Stmt>,
+    },
+    /// `assert_eq(left, right)`, with a message to fail with in place of the default one when it
+    /// is given as a third argument.
+    AssertEq {
+        left: Expr,
+        right: Expr,
+        message: Option<Expr>,
+    },
+    /// `import "path.ys";` or `import alias from "path.ys";`. `name` is the global the module is
+    /// bound to, the alias if there is one and the file name otherwise.
+    Import {
+        alias: Option<String>,
+        path: String,
+        name: String,
+    },
+    /// `test "name" { ... }`, only run by `yellowstone test`. `body` is the block.
+    Test {
+        name: String,
+        body: Box<Stmt>,
+    },
+    /// A lone `;`.
+    Empty,
+}
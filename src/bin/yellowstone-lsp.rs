@@ -0,0 +1,10 @@
+//! The yellowstone language server. Editors start it and talk to it over stdin and stdout.
+
+use std::io::{stdin, stdout};
+
+fn main() {
+    if let Err(e) = yellowstone::lsp::run(stdin().lock(), stdout().lock()) {
+        eprintln!("{e:?}");
+        std::process::exit(1);
+    }
+}
@@ -12,6 +12,10 @@ pub enum OpCode {
     OP_EQUAL,
     OP_GREATER,
     OP_LESS,
+    // fused comparisons, only produced by the optimizer
+    OP_NOT_EQUAL,
+    OP_GREATER_EQUAL,
+    OP_LESS_EQUAL,
     OP_RETURN,
     OP_DEBUG,
     OP_NEGATE,
@@ -20,6 +24,8 @@ pub enum OpCode {
     OP_SUBTRACT,
     OP_MULTIPLY,
     OP_DIVIDE,
+    OP_MODULO,
+    OP_POWER,
     OP_PRINT,
     OP_POP,
     OP_GET_LOCAL(usize),
@@ -28,19 +34,86 @@ pub enum OpCode {
     OP_GET_GLOBAL(usize),
     OP_SET_GLOBAL(usize),
     OP_ASSERT_EQ,
+    /// `assert_eq` with a message, which is on the stack above the two values.
+    OP_ASSERT_EQ_MESSAGE,
+    OP_CALL(usize),
+    OP_IMPORT(usize),
+    OP_GET_PROPERTY(usize),
     // jumping
     OP_JUMP_IF_FALSE,
+    OP_JUMP_IF_TRUE,
     OP_JUMP,
     OP_PLACEHOLDER_JUMP_AMOUNT,
     OP_JUMP_AMOUNT(usize),
     OP_LOOP,
 }
 
+impl OpCode {
+    /// The instruction's name, without its operand.
+    pub fn name(&self) -> &'static str {
+        use OpCode::*;
+        match self {
+            OP_CONSTANT(_) => "OP_CONSTANT",
+            OP_NIL => "OP_NIL",
+            OP_TRUE => "OP_TRUE",
+            OP_FALSE => "OP_FALSE",
+            OP_EQUAL => "OP_EQUAL",
+            OP_GREATER => "OP_GREATER",
+            OP_LESS => "OP_LESS",
+            OP_NOT_EQUAL => "OP_NOT_EQUAL",
+            OP_GREATER_EQUAL => "OP_GREATER_EQUAL",
+            OP_LESS_EQUAL => "OP_LESS_EQUAL",
+            OP_RETURN => "OP_RETURN",
+            OP_DEBUG => "OP_DEBUG",
+            OP_NEGATE => "OP_NEGATE",
+            OP_NOT => "OP_NOT",
+            OP_ADD => "OP_ADD",
+            OP_SUBTRACT => "OP_SUBTRACT",
+            OP_MULTIPLY => "OP_MULTIPLY",
+            OP_DIVIDE => "OP_DIVIDE",
+            OP_MODULO => "OP_MODULO",
+            OP_POWER => "OP_POWER",
+            OP_PRINT => "OP_PRINT",
+            OP_POP => "OP_POP",
+            OP_GET_LOCAL(_) => "OP_GET_LOCAL",
+            OP_SET_LOCAL(_) => "OP_SET_LOCAL",
+            OP_DEFINE_GLOBAL(_) => "OP_DEFINE_GLOBAL",
+            OP_GET_GLOBAL(_) => "OP_GET_GLOBAL",
+            OP_SET_GLOBAL(_) => "OP_SET_GLOBAL",
+            OP_ASSERT_EQ => "OP_ASSERT_EQ",
+            OP_ASSERT_EQ_MESSAGE => "OP_ASSERT_EQ_MESSAGE",
+            OP_CALL(_) => "OP_CALL",
+            OP_IMPORT(_) => "OP_IMPORT",
+            OP_GET_PROPERTY(_) => "OP_GET_PROPERTY",
+            OP_JUMP_IF_FALSE => "OP_JUMP_IF_FALSE",
+            OP_JUMP_IF_TRUE => "OP_JUMP_IF_TRUE",
+            OP_JUMP => "OP_JUMP",
+            OP_PLACEHOLDER_JUMP_AMOUNT => "OP_PLACEHOLDER_JUMP_AMOUNT",
+            OP_JUMP_AMOUNT(_) => "OP_JUMP_AMOUNT",
+            OP_LOOP => "OP_LOOP",
+        }
+    }
+}
+
 /// Contains the bytecode instructions as well as constants created from parsing tokens.
 pub struct Chunk {
     pub code: Vec<OpCode>,
     pub constants: Vec<Value>,
     pub lines: String,
+    /// names of the locals, only known for chunks compiled from source, see `LocalInfo`
+    pub locals: Vec<LocalInfo>,
+}
+
+/// Where a local variable declared in a block keeps its value, so the debugger can show locals
+/// by name. Not written to `.ysc` files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalInfo {
+    pub name: String,
+    /// the constant slot read by `OP_GET_LOCAL`
+    pub slot: usize,
+    /// the source lines the local is in scope on, from its declaration to the end of its block
+    pub first_line: usize,
+    pub last_line: usize,
 }
 
 impl Default for Chunk {
@@ -49,6 +122,7 @@ impl Default for Chunk {
             code: Vec::default(),
             constants: Vec::default(),
             lines: "".to_string(),
+            locals: Vec::default(),
         }
     }
 }
@@ -74,63 +148,64 @@ impl Chunk {
             Err(())
         }
     }
+
+    /// The source line of the instruction at `offset`.
+    pub fn line(&self, offset: usize) -> usize {
+        let mut sum = 0;
+        let mut line = 0;
+        for count in self.lines.split_terminator('_') {
+            line += 1;
+            sum += count.parse::<usize>().unwrap_or(0);
+            if offset < sum {
+                return line;
+            }
+        }
+        line
+    }
+
+    /// The locals in scope on source `line`. When several have the same name only the innermost
+    /// one is returned, the one a reference on that line would resolve to.
+    pub fn locals_at(&self, line: usize) -> Vec<&LocalInfo> {
+        let mut visible: Vec<&LocalInfo> = Vec::new();
+        for local in &self.locals {
+            if local.first_line > line || line > local.last_line {
+                continue;
+            }
+            match visible.iter_mut().find(|other| other.name == local.name) {
+                Some(other) => *other = local,
+                None => visible.push(local),
+            }
+        }
+        visible
+    }
 }
 
 /// Each line number is separated by a '\_', the numbers in between the '\_' are the number of
-/// operations on that line.
+/// operations on that line. Lines without any operations are written as '0', so the n-th entry
+/// always belongs to source line n.
 /// Look at 'chunk_tests' module examples.
 fn encode(chunk: &mut Chunk, curr_line: usize) {
-    fn new_line(chunk: &mut Chunk, curr_line: usize) {
-        chunk.lines.push_str("1_");
-    }
-
     // Calculate the current line count.
-    let line_count = chunk
-        .lines
-        .chars()
-        .filter(|x| x == &'_')
-        .collect::<Vec<char>>()
-        .len();
-
-    if line_count == 0 {
-        new_line(chunk, curr_line);
-    } else {
-        let same_line: bool = line_count == curr_line;
-        if !same_line {
-            new_line(chunk, curr_line);
-        } else if same_line {
-            chunk.lines.pop();
-            let mut num = "".to_string();
-            println!("{}", num);
-            loop {
-                match chunk.lines.pop() {
-                    None => {
-                        break;
-                    }
-                    Some(c) => {
-                        if c == '_' {
-                            chunk.lines.push('_');
-                            break;
-                        }
-                        num.push(c)
-                    }
-                }
-            }
-            let mut num = num.parse::<usize>().unwrap();
-            num += 1;
-            let num = num.to_string();
-            chunk.lines.push_str(&num);
-            chunk.lines.push('_');
+    let line_count = chunk.lines.matches('_').count();
+
+    if line_count == 0 || curr_line > line_count {
+        for _ in line_count + 1..curr_line {
+            chunk.lines.push_str("0_");
         }
+        chunk.lines.push_str("1_");
+    } else {
+        // An operation for an earlier line than the last one written is counted with the last
+        // line, the entries can only grow at the end.
+        chunk.lines.pop();
+        let start = chunk.lines.rfind('_').map_or(0, |i| i + 1);
+        let num = chunk.lines.split_off(start).parse::<usize>().unwrap() + 1;
+        chunk.lines.push_str(&num.to_string());
+        chunk.lines.push('_');
     }
 }
 
 /// Gets the line of a given an instruction 'offset'.
 pub fn get_line(offset: &mut u32, lines: &String) -> String {
-    if *offset == 0 {
-        return "1".to_string();
-    }
-
     let line_numb = |offset: &mut u32| -> String {
         let mut split = lines.split('_').collect::<Vec<&str>>();
         split.pop();
@@ -151,6 +226,10 @@ pub fn get_line(offset: &mut u32, lines: &String) -> String {
         return split_len.to_string();
     };
 
+    if *offset == 0 {
+        return line_numb(offset);
+    }
+
     let before = line_numb(&mut (*offset - 1));
     let current = line_numb(offset);
 
@@ -187,6 +266,30 @@ mod chunk_tests {
         assert_eq!("2_2_", &chunk.lines);
     }
 
+    #[test]
+    fn encode_many_on_one_line_test() {
+        let chunk = &mut Chunk::default();
+        for _ in 0..12 {
+            write_chunk(chunk, OpCode::OP_NIL, 1);
+        }
+        write_chunk(chunk, OpCode::OP_RETURN, 2);
+        assert_eq!("12_1_", &chunk.lines);
+    }
+
+    #[test]
+    fn encode_skipped_lines_test() {
+        let chunk = &mut Chunk::default();
+        write_chunk(chunk, OpCode::OP_NIL, 2);
+        write_chunk(chunk, OpCode::OP_NIL, 5);
+        write_chunk(chunk, OpCode::OP_RETURN, 5);
+        assert_eq!("0_1_0_0_2_", &chunk.lines);
+        assert_eq!(2, chunk.line(0));
+        assert_eq!(5, chunk.line(1));
+        assert_eq!(5, chunk.line(2));
+        assert_eq!("2", get_line(&mut 0, &chunk.lines));
+        assert_eq!("5", get_line(&mut 1, &chunk.lines));
+    }
+
     #[test]
     fn get_line_test() {
         let chunk = &mut Chunk::default();
@@ -204,4 +307,27 @@ mod chunk_tests {
         assert_eq!("same", get_line(&mut 4, &chunk.lines));
         assert_eq!("3", get_line(&mut 5, &chunk.lines));
     }
+
+    #[test]
+    fn locals_at_test() {
+        let source = "var a = 1;\n{\n    var b = 2;\n    {\n        var b = 3;\n        print b;\n    }\n    print b;\n}\nprint a;";
+        let chunk = crate::compiler::compile(&source.to_string()).unwrap();
+        let names_at = |line| {
+            chunk
+                .locals_at(line)
+                .iter()
+                .map(|local| {
+                    (
+                        local.name.as_str(),
+                        chunk.constants[local.slot].as_number().ok(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert!(names_at(1).is_empty());
+        assert_eq!(vec![("b", Some(2.))], names_at(3));
+        assert_eq!(vec![("b", Some(3.))], names_at(6));
+        assert_eq!(vec![("b", Some(2.))], names_at(8));
+        assert!(names_at(10).is_empty());
+    }
 }
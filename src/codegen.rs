@@ -0,0 +1,411 @@
+//! Walks the syntax tree built by `parser` and emits the bytecode for it.
+
+use crate::ast::*;
+use crate::chunk::{Chunk, LocalInfo, OpCode};
+use crate::error::InterpretError;
+use crate::value::{allocate_object, Value};
+use anyhow::{Context, Result};
+
+/// Emits the chunk for a parsed program, ending with `OP_RETURN`.
+pub fn generate(program: &[Stmt]) -> Result<Chunk> {
+    let mut generator = Generator::default();
+    for statement in program {
+        generator.statement(statement)?;
+    }
+    generator.emit(OpCode::OP_RETURN);
+    Ok(generator.chunk)
+}
+
+/// Emits a chunk that stands in for another one at the point it is paused at. It starts out with
+/// that chunk's `constants`, so the `locals`, given as name and slot, are read and assigned where
+/// they live. Used to evaluate code in the debugger.
+pub fn generate_in_scope(
+    program: &[Stmt],
+    constants: Vec<Value>,
+    locals: &[(String, usize)],
+) -> Result<Chunk> {
+    let mut generator = Generator::default();
+    generator.chunk.constants = constants;
+    for (name, slot) in locals {
+        generator.locals.push(Local {
+            name: name.clone(),
+            depth: 0,
+            index: *slot,
+        });
+    }
+    for statement in program {
+        generator.statement(statement)?;
+    }
+    generator.emit(OpCode::OP_RETURN);
+    Ok(generator.chunk)
+}
+
+/// A variable declared inside a block. Its value lives in the stack slot `index`.
+struct Local {
+    name: String,
+    depth: usize,
+    index: usize,
+}
+
+struct Generator {
+    chunk: Chunk,
+    locals: Vec<Local>,
+    scope_depth: usize,
+    /// source line of the node being emitted, recorded for every instruction
+    line: usize,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Generator {
+            chunk: Chunk::default(),
+            locals: Vec::new(),
+            scope_depth: 0,
+            line: 1,
+        }
+    }
+}
+
+impl Generator {
+    const MAX_LOCALS: usize = 255;
+
+    fn statement(&mut self, statement: &Stmt) -> Result<()> {
+        self.line = statement.span.line;
+
+        match &statement.kind {
+            StmtKind::Expression(expression) => self.expression(expression),
+            StmtKind::Print(value) => {
+                self.expression(value);
+                self.emit(OpCode::OP_PRINT);
+            }
+            StmtKind::Var { name, initializer } => {
+                if self.scope_depth > 0 {
+                    self.declare_local(name)?;
+                    self.initializer(initializer);
+                } else {
+                    let index = self.identifier_constant(name);
+                    self.initializer(initializer);
+                    self.emit(OpCode::OP_DEFINE_GLOBAL(index));
+                }
+            }
+            StmtKind::Block(statements) => {
+                self.begin_scope();
+                for statement in statements {
+                    self.statement(statement)?;
+                }
+                self.end_scope();
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expression(condition);
+                let then_jump = self.emit_jump(OpCode::OP_JUMP_IF_FALSE);
+                self.emit(OpCode::OP_POP);
+                self.statement(then_branch)?;
+
+                let else_jump = self.emit_jump(OpCode::OP_JUMP);
+                self.patch_jump(then_jump);
+                self.emit(OpCode::OP_POP);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch)?;
+                }
+                self.patch_jump(else_jump);
+            }
+            StmtKind::While { condition, body } => {
+                let loop_start = self.chunk.code.len();
+                self.expression(condition);
+                let exit_jump = self.emit_jump(OpCode::OP_JUMP_IF_FALSE);
+                self.emit(OpCode::OP_POP);
+                self.statement(body)?;
+                self.emit_loop(loop_start);
+
+                self.patch_jump(exit_jump);
+                self.emit(OpCode::OP_POP);
+            }
+            StmtKind::For {
+                initializer,
+                condition,
+                increment,
+                body,
+            } => {
+                // The initializer's variable only lives as long as the loop.
+                self.begin_scope();
+                if let Some(initializer) = initializer {
+                    self.statement(initializer)?;
+                }
+
+                let mut loop_start = self.chunk.code.len();
+                let mut exit_jump = None;
+                if let Some(condition) = condition {
+                    self.expression(condition);
+                    exit_jump = Some(self.emit_jump(OpCode::OP_JUMP_IF_FALSE));
+                    self.emit(OpCode::OP_POP);
+                }
+
+                // The increment comes before the body in the code, so jump over it on the way in
+                // and loop back to it at the end of the body.
+                if let Some(increment) = increment {
+                    let body_jump = self.emit_jump(OpCode::OP_JUMP);
+                    let increment_start = self.chunk.code.len();
+                    self.expression(increment);
+                    self.emit(OpCode::OP_POP);
+                    self.emit_loop(loop_start);
+                    loop_start = increment_start;
+                    self.patch_jump(body_jump);
+                }
+
+                self.statement(body)?;
+                self.emit_loop(loop_start);
+
+                if let Some(exit_jump) = exit_jump {
+                    self.patch_jump(exit_jump);
+                    self.emit(OpCode::OP_POP);
+                }
+                self.end_scope();
+            }
+            StmtKind::AssertEq {
+                left,
+                right,
+                message,
+            } => {
+                self.expression(left);
+                self.expression(right);
+                match message {
+                    Some(message) => {
+                        self.expression(message);
+                        self.emit(OpCode::OP_ASSERT_EQ_MESSAGE);
+                    }
+                    None => self.emit(OpCode::OP_ASSERT_EQ),
+                }
+            }
+            StmtKind::Import { path, name, .. } => {
+                let path_index = self.chunk.add_constant(allocate_object(path.clone()));
+                self.emit(OpCode::OP_IMPORT(path_index));
+                let name_index = self.identifier_constant(name);
+                self.emit(OpCode::OP_DEFINE_GLOBAL(name_index));
+            }
+            // Tests only run under `yellowstone test`, which compiles each one as a block in
+            // place, see `test_runner`.
+            StmtKind::Test { .. } => {}
+            StmtKind::Empty => {}
+        }
+        Ok(())
+    }
+
+    fn initializer(&mut self, initializer: &Option<Expr>) {
+        match initializer {
+            Some(value) => self.expression(value),
+            None => self.emit(OpCode::OP_NIL),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expr) {
+        self.line = expression.span.line;
+
+        match &expression.kind {
+            ExprKind::Literal(Literal::Number(number)) => {
+                self.emit_constant(Value::number_value(*number))
+            }
+            ExprKind::Literal(Literal::String(string)) => {
+                self.emit_constant(allocate_object(string.clone()))
+            }
+            ExprKind::Literal(Literal::Bool(true)) => self.emit(OpCode::OP_TRUE),
+            ExprKind::Literal(Literal::Bool(false)) => self.emit(OpCode::OP_FALSE),
+            ExprKind::Literal(Literal::Nil) => self.emit(OpCode::OP_NIL),
+            ExprKind::Variable(name) => match self.resolve_local(name) {
+                Some(index) => self.emit(OpCode::OP_GET_LOCAL(index)),
+                None => {
+                    let index = self.identifier_constant(name);
+                    self.emit(OpCode::OP_GET_GLOBAL(index));
+                }
+            },
+            ExprKind::Assign { name, value } => match self.resolve_local(name) {
+                Some(index) => {
+                    self.expression(value);
+                    self.emit(OpCode::OP_SET_LOCAL(index));
+                }
+                None => {
+                    let index = self.identifier_constant(name);
+                    self.expression(value);
+                    self.emit(OpCode::OP_SET_GLOBAL(index));
+                }
+            },
+            ExprKind::Unary { op, operand } => {
+                self.expression(operand);
+                match op {
+                    UnaryOp::Negate => self.emit(OpCode::OP_NEGATE),
+                    UnaryOp::Not => self.emit(OpCode::OP_NOT),
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                self.expression(left);
+                self.expression(right);
+                match op {
+                    BinaryOp::Equal => self.emit(OpCode::OP_EQUAL),
+                    BinaryOp::NotEqual => {
+                        self.emit(OpCode::OP_EQUAL);
+                        self.emit(OpCode::OP_NOT);
+                    }
+                    BinaryOp::Greater => self.emit(OpCode::OP_GREATER),
+                    BinaryOp::GreaterEqual => {
+                        self.emit(OpCode::OP_LESS);
+                        self.emit(OpCode::OP_NOT);
+                    }
+                    BinaryOp::Less => self.emit(OpCode::OP_LESS),
+                    BinaryOp::LessEqual => {
+                        self.emit(OpCode::OP_GREATER);
+                        self.emit(OpCode::OP_NOT);
+                    }
+                    BinaryOp::Add => self.emit(OpCode::OP_ADD),
+                    BinaryOp::Subtract => self.emit(OpCode::OP_SUBTRACT),
+                    BinaryOp::Multiply => self.emit(OpCode::OP_MULTIPLY),
+                    BinaryOp::Divide => self.emit(OpCode::OP_DIVIDE),
+                    BinaryOp::Modulo => self.emit(OpCode::OP_MODULO),
+                    BinaryOp::Power => self.emit(OpCode::OP_POWER),
+                }
+            }
+            ExprKind::Logical {
+                op: LogicalOp::And,
+                left,
+                right,
+            } => {
+                self.expression(left);
+                let end_jump = self.emit_jump(OpCode::OP_JUMP_IF_FALSE);
+                self.emit(OpCode::OP_POP);
+                self.expression(right);
+                self.patch_jump(end_jump);
+            }
+            ExprKind::Logical {
+                op: LogicalOp::Or,
+                left,
+                right,
+            } => {
+                self.expression(left);
+                let else_jump = self.emit_jump(OpCode::OP_JUMP_IF_FALSE);
+                let end_jump = self.emit_jump(OpCode::OP_JUMP);
+                self.patch_jump(else_jump);
+                self.emit(OpCode::OP_POP);
+                self.expression(right);
+                self.patch_jump(end_jump);
+            }
+            ExprKind::Grouping(inner) => self.expression(inner),
+            ExprKind::Call { callee, arguments } => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+                self.emit(OpCode::OP_CALL(arguments.len()));
+            }
+            ExprKind::Get { object, name } => {
+                self.expression(object);
+                let index = self.identifier_constant(name);
+                self.emit(OpCode::OP_GET_PROPERTY(index));
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scope_depth += 1;
+    }
+
+    /// Leaves the innermost scope, popping the values of the locals declared in it.
+    fn end_scope(&mut self) {
+        self.scope_depth -= 1;
+        while self
+            .locals
+            .last()
+            .is_some_and(|local| local.depth > self.scope_depth)
+        {
+            let local = self.locals.pop().unwrap();
+            self.end_local(local.index);
+            self.emit(OpCode::OP_POP);
+        }
+    }
+
+    /// Records the current line as the last one the local in `slot` is in scope on.
+    fn end_local(&mut self, slot: usize) {
+        if let Some(info) = self
+            .chunk
+            .locals
+            .iter_mut()
+            .rev()
+            .find(|info| info.slot == slot)
+        {
+            info.last_line = self.line;
+        }
+    }
+
+    /// Adds a local for `name` to the current scope. A local with the same name declared in the
+    /// same scope is shadowed and can never be referenced again, so it is forgotten.
+    fn declare_local(&mut self, name: &str) -> Result<()> {
+        let shadowed = self
+            .locals
+            .iter()
+            .rposition(|local| local.depth == self.scope_depth && local.name == name);
+        if let Some(shadowed) = shadowed {
+            let local = self.locals.remove(shadowed);
+            self.end_local(local.index);
+        }
+
+        if self.locals.len() >= Generator::MAX_LOCALS {
+            return Err(InterpretError::COMPILE_ERROR)
+                .context(format!("[line {}] Too many local variables.", self.line));
+        }
+        let index = self.chunk.constants.len();
+        self.locals.push(Local {
+            name: name.to_string(),
+            depth: self.scope_depth,
+            index,
+        });
+        self.chunk.locals.push(LocalInfo {
+            name: name.to_string(),
+            slot: index,
+            first_line: self.line,
+            last_line: self.line,
+        });
+        Ok(())
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.locals
+            .iter()
+            .rev()
+            .find(|local| local.name == name)
+            .map(|local| local.index)
+    }
+
+    fn identifier_constant(&mut self, name: &str) -> usize {
+        self.chunk.add_constant(allocate_object(name.to_string()))
+    }
+
+    fn emit(&mut self, op: OpCode) {
+        self.chunk.write_chunk(op, self.line);
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        self.chunk.add_constant(value.clone());
+        self.emit(OpCode::OP_CONSTANT(value));
+    }
+
+    /// Emits `op` with a placeholder jump amount and returns the placeholder's offset, to be
+    /// filled in by `patch_jump` once the target is known.
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit(op);
+        self.emit(OpCode::OP_PLACEHOLDER_JUMP_AMOUNT);
+        self.chunk.code.len() - 1
+    }
+
+    /// Points the jump whose placeholder is at `offset` to the next instruction.
+    fn patch_jump(&mut self, offset: usize) {
+        let jump = self.chunk.code.len() - offset - 1;
+        self.chunk.code[offset] = OpCode::OP_JUMP_AMOUNT(jump);
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) {
+        self.emit(OpCode::OP_LOOP);
+        let offset = self.chunk.code.len() - loop_start + 1;
+        self.emit(OpCode::OP_JUMP_AMOUNT(offset));
+    }
+}
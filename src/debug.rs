@@ -1,129 +1,131 @@
-use crate::chunk::get_line;
+//! Turns chunks into text: the listing written by `disassemble`, which `assembler::assemble` reads
+//! back, and the trace of a running VM.
+
 use crate::chunk::OpCode::*;
 use crate::chunk::*;
+use crate::vm::{Hook, VM};
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::io::Write;
 
+/// Prints the listing of `chunk`, see `disassemble`.
 pub fn disassemble_chunk(chunk: &Chunk, name: &str) {
-    println!("== {name} ==");
-
-    let mut offset: u32 = 0;
-    for instruction in chunk.code.iter() {
-        disassemble_instruction(instruction, &mut offset, &chunk.lines, chunk);
-    }
-}
-
-fn simple_instruction(name: &str, offset: &mut u32) {
-    println!("{name}");
-    *offset += 1;
+    print!("{}", disassemble(chunk, name));
 }
 
-fn constant_instruction(instruction: &OpCode, offset: &mut u32) {
-    if let OP_CONSTANT(const_val) = instruction {
-        println!("{}", format_args!("OP_CONSTANT {const_val:?}"));
-        *offset += 1;
-    } else {
-        panic!("The instruction at offset {offset} is not a constant instruction.");
+/// The listing of a chunk: its constants, then one instruction per line with its offset and the
+/// source line it came from (`|` when it is the line above's). The operand of a jump is not an
+/// instruction of its own, the jump shows the offset it goes to instead.
+///
+/// ```text
+/// == script ==
+/// -- constants --
+/// 0000 1
+/// 0001 "a"
+/// -- code --
+/// 0000    1 OP_CONSTANT 1
+/// 0001    | OP_DEFINE_GLOBAL 1 "a"
+/// 0002    2 OP_JUMP -> 0005
+/// 0004    | OP_NIL
+/// 0005    | OP_RETURN
+/// ```
+pub fn disassemble(chunk: &Chunk, name: &str) -> String {
+    let mut listing = format!("== {name} ==\n-- constants --\n");
+    for (index, constant) in chunk.constants.iter().enumerate() {
+        let _ = writeln!(listing, "{index:04} {}", constant.repr());
     }
-}
 
-fn global_instruction(instruction: &OpCode, offset: &mut u32, chunk: &Chunk) {
-    match instruction {
-        OP_SET_GLOBAL(index) => {
-            println!(
-                "OP_SET_GLOBAL {:?} {:?}",
-                chunk.get_constant_name(index),
-                chunk.constants.get(*index).unwrap()
-            );
-            *offset += 1;
-        }
-        OP_GET_GLOBAL(index) => {
-            println!("OP_GET_GLOBAL {:?}", chunk.constants.get(*index).unwrap());
-            *offset += 1;
-        }
-        OP_DEFINE_GLOBAL(index) => {
-            println!(
-                "OP_DEFINE_GLOBAL {:?}",
-                chunk.constants.get(*index).unwrap()
-            );
-            *offset += 1;
-        }
-        _ => {
-            panic!("The instruction at offset {offset} is not a constant instruction.");
+    listing.push_str("-- code --\n");
+    let mut previous_line = None;
+    for offset in 0..chunk.code.len() {
+        if offset > 0 && is_jump_operand(chunk, offset) {
+            continue;
         }
+        let line = chunk.line(offset);
+        let _ = if previous_line.replace(line) == Some(line) {
+            writeln!(listing, "{offset:04}    | {}", instruction(chunk, offset))
+        } else {
+            writeln!(
+                listing,
+                "{offset:04} {line:>4} {}",
+                instruction(chunk, offset)
+            )
+        };
     }
+    listing
 }
 
-/// The slot number of the local variable. b/c the local variable's name never leaves the
-/// compiler to make it into the chunk at all.
-fn local_instruction(instruction: &OpCode, offset: &mut u32, chunk: &Chunk) {
-    match instruction {
-        OP_SET_LOCAL(i) => {
-            println!(
-                "OP_SET_LOCAL {:?} {:?}",
-                chunk.get_constant_name(i),
-                chunk.constants.get(*i).unwrap()
-            );
-            *offset += 1;
-        }
-        OP_GET_LOCAL(i) => {
-            println!("OP_GET_LOCAL {:?}", chunk.constants.get(*i).unwrap());
-            *offset += 1;
-        }
-        _ => {
-            panic!("The instruction at offset {offset} is not a local instruction.")
+/// The instruction at `offset` as it is written in a listing. Instructions that refer to a
+/// constant show its value after the index, jumps show the offset they go to.
+pub fn instruction(chunk: &Chunk, offset: usize) -> String {
+    let op = &chunk.code[offset];
+    match op {
+        OP_CONSTANT(value) => format!("OP_CONSTANT {}", value.repr()),
+        OP_DEFINE_GLOBAL(index)
+        | OP_GET_GLOBAL(index)
+        | OP_SET_GLOBAL(index)
+        | OP_GET_LOCAL(index)
+        | OP_SET_LOCAL(index)
+        | OP_IMPORT(index)
+        | OP_GET_PROPERTY(index) => match chunk.constants.get(*index) {
+            Some(constant) => format!("{} {index} {}", op.name(), constant.repr()),
+            None => format!("{} {index}", op.name()),
+        },
+        OP_CALL(count) | OP_JUMP_AMOUNT(count) => format!("{} {count}", op.name()),
+        OP_JUMP | OP_JUMP_IF_FALSE | OP_JUMP_IF_TRUE | OP_LOOP => {
+            match chunk.code.get(offset + 1) {
+                Some(OP_JUMP_AMOUNT(jump)) => {
+                    let (from, jump) = (offset as i64 + 2, *jump as i64);
+                    let target = if let OP_LOOP = op {
+                        from - jump
+                    } else {
+                        from + jump
+                    };
+                    format!("{} -> {target:04}", op.name())
+                }
+                _ => op.name().to_string(),
+            }
         }
+        _ => op.name().to_string(),
     }
 }
 
-fn jump_instruction(name: &str, sign: i32, chunk: &Chunk, offset: &mut u32) {
-    if let OP_JUMP_AMOUNT(jump) = chunk.code.get((*offset + 1) as usize).unwrap() {
-        println!(
-            "{name} {offset} {}",
-            (*offset as i32 + 2 + sign as i32 * (*jump as i32))
-        );
-    }
-    *offset += 1;
+/// Whether the instruction at `offset` is the amount of the jump before it.
+fn is_jump_operand(chunk: &Chunk, offset: usize) -> bool {
+    matches!(chunk.code[offset], OP_JUMP_AMOUNT(_))
+        && matches!(
+            chunk.code[offset - 1],
+            OP_JUMP | OP_JUMP_IF_FALSE | OP_JUMP_IF_TRUE | OP_LOOP
+        )
 }
 
-fn disassemble_instruction(instruction: &OpCode, offset: &mut u32, lines: &String, chunk: &Chunk) {
-    print!("{offset:04}");
-    let line = get_line(offset, lines);
-    if line == *"same" {
-        print!("   | ");
-    } else {
-        print!("{line:>4} ");
+/// Writes each instruction the VM runs to a sink: its offset, source line, the instruction as it
+/// is written in a listing and the stack before it runs, bottom first.
+///
+/// ```text
+/// 0004    2 OP_ADD                       [1, 2]
+/// ```
+pub struct Tracer<W: Write> {
+    sink: W,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(sink: W) -> Self {
+        Tracer { sink }
     }
+}
 
-    match instruction {
-        OP_CONSTANT(_) => constant_instruction(instruction, offset),
-        OP_DEFINE_GLOBAL(_) => global_instruction(instruction, offset, chunk),
-        OP_GET_GLOBAL(_) => global_instruction(instruction, offset, chunk),
-        OP_SET_GLOBAL(_) => global_instruction(instruction, offset, chunk),
-        OP_SET_LOCAL(_) => local_instruction(instruction, offset, chunk),
-        OP_GET_LOCAL(_) => local_instruction(instruction, offset, chunk),
-        OP_TRUE => simple_instruction("OP_TRUE", offset),
-        OP_NIL => simple_instruction("OP_NIL", offset),
-        OP_FALSE => simple_instruction("OP_FALSE", offset),
-        OP_EQUAL => simple_instruction("OP_EQUAL", offset),
-        OP_GREATER => simple_instruction("OP_GREATER", offset),
-        OP_LESS => simple_instruction("OP_LESS", offset),
-        OP_RETURN => simple_instruction("OP_RETURN", offset),
-        OP_NEGATE => simple_instruction("OP_NEGATE", offset),
-        OP_NOT => simple_instruction("OP_NOT", offset),
-        OP_ADD => simple_instruction("OP_ADD", offset),
-        OP_SUBTRACT => simple_instruction("OP_SUBTRACT", offset),
-        OP_MULTIPLY => simple_instruction("OP_MULTIPLY", offset),
-        OP_DIVIDE => simple_instruction("OP_DIVIDE", offset),
-        OP_PRINT => simple_instruction("OP_PRINT", offset),
-        OP_POP => simple_instruction("OP_POP", offset),
-        OP_ASSERT_EQ => simple_instruction("OP_ASSERT_EQ", offset),
-        OP_DEBUG => {
-            todo!()
-        }
-        // jump
-        OP_JUMP_IF_FALSE => jump_instruction("OP_JUMP_IF_FALSE", 1, chunk, offset),
-        OP_JUMP => jump_instruction("OP_JUMP", 1, chunk, offset),
-        OP_LOOP => jump_instruction("OP_LOOP", -1, chunk, offset),
-        OP_PLACEHOLDER_JUMP_AMOUNT => simple_instruction("OP_PLACEHOLDER_JUMP_AMOUNT", offset),
-        OP_JUMP_AMOUNT(_) => simple_instruction("", offset),
+impl<W: Write> Hook for Tracer<W> {
+    fn before_instruction(&mut self, vm: &mut VM) -> Result<()> {
+        let stack: Vec<String> = vm.stack.iter().map(|value| format!("{value:?}")).collect();
+        writeln!(
+            self.sink,
+            "{:04} {:>4} {:<28} [{}]",
+            vm.ip,
+            vm.chunk.line(vm.ip),
+            instruction(&vm.chunk, vm.ip),
+            stack.join(", ")
+        )?;
+        Ok(())
     }
 }
@@ -0,0 +1,262 @@
+//! An interactive debugger, run as a `VM::hook`. It pauses before the first statement, at
+//! breakpoints and after steps, and reads commands until told to carry on.
+//!
+//! Statements are tracked by source line. Modules are the only code that runs "inside" another
+//! statement, so stepping into an `import` enters the module's file and stepping out of it
+//! returns to the importing one.
+
+use crate::chunk::Chunk;
+use crate::error::InterpretError;
+use crate::vm::{Hook, VM};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+const HELP: &str = "\
+break [file:]line   pause when the line is reached, without a line lists the breakpoints
+delete [file:]line  remove a breakpoint
+step                run to the next line, entering imported modules
+next                run to the next line in this file, running imports without pausing
+out                 run until the current module returns to the file that imported it
+continue            run until a breakpoint
+stack               show the value stack, bottom first
+locals              show the locals in scope
+globals             show the global variables
+print <code>        run code where the program is paused and show its value
+list                show the lines around the current one
+quit                stop the program";
+
+/// A line to pause at. Without a file it is in the script being debugged, otherwise in the
+/// imported module whose path ends with `file`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub file: Option<String>,
+    pub line: usize,
+}
+
+impl Breakpoint {
+    /// Reads `line` or `file:line`.
+    pub fn parse(text: &str) -> Option<Breakpoint> {
+        let (file, line) = match text.rsplit_once(':') {
+            Some((file, line)) => (Some(file.to_string()), line),
+            None => (None, text),
+        };
+        let line = line.trim().parse().ok()?;
+        Some(Breakpoint { file, line })
+    }
+
+    fn matches(&self, vm: &VM, line: usize) -> bool {
+        if self.line != line {
+            return false;
+        }
+        match &self.file {
+            None => vm.import_stack.len() <= 1,
+            Some(file) => vm.import_stack.len() > 1 && current_file(vm).ends_with(file),
+        }
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{file}:{}", self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
+}
+
+/// How far to run before pausing again. Depths are the length of `VM::import_stack`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Step,
+    Next(usize),
+    Out(usize),
+    Continue,
+}
+
+pub struct Debugger<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    pub breakpoints: Vec<Breakpoint>,
+    mode: Mode,
+    /// depth, line and offset of the instruction run last, to tell when a new line starts
+    last: Option<(usize, usize, usize)>,
+    /// lines of the files shown so far
+    sources: HashMap<PathBuf, Vec<String>>,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    /// A debugger reading commands from `input` and answering on `output`. When `input` runs out
+    /// the program runs to the end without pausing.
+    pub fn new(input: R, output: W) -> Self {
+        Debugger {
+            input,
+            output,
+            breakpoints: Vec::new(),
+            mode: Mode::Step,
+            last: None,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Shows where the program is paused and handles commands until one resumes it.
+    fn pause(&mut self, vm: &mut VM, line: usize) -> Result<()> {
+        let text = self.source_line(vm, line).unwrap_or_default();
+        writeln!(self.output, "{}  {}", location(vm, line), text.trim())?;
+
+        loop {
+            write!(self.output, "(ysdb) ")?;
+            self.output.flush()?;
+            let mut command = String::new();
+            if self.input.read_line(&mut command)? == 0 {
+                writeln!(self.output)?;
+                self.breakpoints.clear();
+                self.mode = Mode::Continue;
+                return Ok(());
+            }
+
+            let command = command.trim();
+            let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+            let argument = argument.trim();
+            let depth = vm.import_stack.len();
+            match name {
+                "" => {}
+                "s" | "step" => return self.resume(Mode::Step),
+                "n" | "next" => return self.resume(Mode::Next(depth)),
+                "o" | "out" => return self.resume(Mode::Out(depth)),
+                "c" | "continue" => return self.resume(Mode::Continue),
+                "q" | "quit" => {
+                    return Err(InterpretError::RUNTIME_ERROR).context("stopped by the debugger")
+                }
+                "b" | "break" if argument.is_empty() => {
+                    for breakpoint in &self.breakpoints {
+                        writeln!(self.output, "breakpoint at {breakpoint}")?;
+                    }
+                }
+                "b" | "break" => match Breakpoint::parse(argument) {
+                    Some(breakpoint) => {
+                        writeln!(self.output, "breakpoint at {breakpoint}")?;
+                        if !self.breakpoints.contains(&breakpoint) {
+                            self.breakpoints.push(breakpoint);
+                        }
+                    }
+                    None => writeln!(self.output, "expected a line number, like `break 12`")?,
+                },
+                "d" | "delete" => match Breakpoint::parse(argument) {
+                    Some(breakpoint) if self.breakpoints.contains(&breakpoint) => {
+                        self.breakpoints.retain(|other| *other != breakpoint);
+                        writeln!(self.output, "deleted breakpoint at {breakpoint}")?;
+                    }
+                    _ => writeln!(self.output, "no breakpoint at {argument}")?,
+                },
+                "stack" => {
+                    for (i, value) in vm.stack.iter().enumerate() {
+                        writeln!(self.output, "[{i}] {value:?}")?;
+                    }
+                }
+                "locals" => {
+                    for local in vm.chunk.locals_at(line) {
+                        let value = &vm.chunk.constants[local.slot];
+                        writeln!(self.output, "{} = {value:?}", local.name)?;
+                    }
+                }
+                "globals" => {
+                    for (name, value) in vm.globals() {
+                        writeln!(self.output, "{name} = {value:?}")?;
+                    }
+                }
+                "p" | "print" => match vm.evaluate(&argument.to_string()) {
+                    Ok(Some(value)) => writeln!(self.output, "{value:?}")?,
+                    Ok(None) => {}
+                    Err(e) => writeln!(self.output, "error: {e:#}")?,
+                },
+                "l" | "list" => {
+                    for number in line.saturating_sub(3).max(1)..=line + 3 {
+                        if let Some(text) = self.source_line(vm, number) {
+                            let marker = if number == line { ">" } else { " " };
+                            writeln!(self.output, "{marker}{number:>4}  {text}")?;
+                        }
+                    }
+                }
+                "h" | "help" => writeln!(self.output, "{HELP}")?,
+                _ => writeln!(self.output, "unknown command `{name}`, try `help`")?,
+            }
+        }
+    }
+
+    fn resume(&mut self, mode: Mode) -> Result<()> {
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// Line `number` of the file running now, if it is a source file.
+    fn source_line(&mut self, vm: &VM, number: usize) -> Option<String> {
+        let path = vm.import_stack.last()?;
+        if !self.sources.contains_key(path) {
+            let source = fs::read_to_string(path).ok()?;
+            let lines = source.lines().map(str::to_string).collect();
+            self.sources.insert(path.clone(), lines);
+        }
+        self.sources[path].get(number.checked_sub(1)?).cloned()
+    }
+}
+
+impl<R: BufRead, W: Write> Hook for Debugger<R, W> {
+    fn before_instruction(&mut self, vm: &mut VM) -> Result<()> {
+        let depth = vm.import_stack.len();
+        let line = vm.chunk.line(vm.ip);
+
+        let last = self.last.replace((depth, line, vm.ip));
+
+        // Back in the importing file, in the middle of the `import` line. Only stepping out
+        // pauses there, anything else waits for the next line.
+        if last.is_some_and(|(last_depth, ..)| depth < last_depth) {
+            return match self.mode {
+                Mode::Out(from) if depth < from => self.pause(vm, line),
+                _ => Ok(()),
+            };
+        }
+
+        // A new line starts when the line changes, or when a loop jumps back to the start of one.
+        let new_line = match last {
+            Some((last_depth, last_line, last_ip)) if last_depth == depth && last_line == line => {
+                vm.ip <= last_ip && is_line_start(&vm.chunk, vm.ip)
+            }
+            _ => true,
+        };
+        if !new_line {
+            return Ok(());
+        }
+
+        let stepped = match self.mode {
+            Mode::Step => true,
+            Mode::Next(from) => depth <= from,
+            Mode::Out(from) => depth < from,
+            Mode::Continue => false,
+        };
+        if stepped || self.breakpoints.iter().any(|b| b.matches(vm, line)) {
+            self.pause(vm, line)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_line_start(chunk: &Chunk, offset: usize) -> bool {
+    offset == 0 || chunk.line(offset) != chunk.line(offset - 1)
+}
+
+fn current_file(vm: &VM) -> &Path {
+    vm.import_stack
+        .last()
+        .map_or(Path::new(""), |path| path.as_path())
+}
+
+/// `file:line`, or just the line for code that was not read from a file.
+fn location(vm: &VM, line: usize) -> String {
+    match current_file(vm).file_name() {
+        Some(name) => format!("{}:{line}", name.to_string_lossy()),
+        None => format!("line {line}"),
+    }
+}
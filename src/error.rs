@@ -4,20 +4,36 @@ use std::{error::Error, fmt::Display};
 #[derive(PartialEq, Debug, Clone)]
 pub enum InterpretError {
     COMPILE_ERROR,
+    VERIFY_ERROR,
     RUNTIME_ERROR,
     RUNTIME_UNRECOGNIZED_VARIABLE_ERROR,
     RUNTIME_ASSERT_ERROR,
+    RUNTIME_IMPORT_ERROR,
+    RUNTIME_FUEL_ERROR,
+    RUNTIME_STACK_OVERFLOW_ERROR,
+    RUNTIME_MEMORY_ERROR,
+    RUNTIME_TIMEOUT_ERROR,
+    RUNTIME_SANDBOX_ERROR,
+    RUNTIME_INTERRUPT_ERROR,
 }
 
 impl Display for InterpretError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let message = match self {
             InterpretError::COMPILE_ERROR => "COMPILE_ERROR",
+            InterpretError::VERIFY_ERROR => "VERIFY_ERROR",
             InterpretError::RUNTIME_ERROR => "RUNTIME_ERROR",
             InterpretError::RUNTIME_UNRECOGNIZED_VARIABLE_ERROR => {
                 "RUNTIME_UNRECOGNIZED_VARIABLE_ERROR"
             }
             InterpretError::RUNTIME_ASSERT_ERROR => "RUNTIME_ASSERT_ERROR",
+            InterpretError::RUNTIME_IMPORT_ERROR => "RUNTIME_IMPORT_ERROR",
+            InterpretError::RUNTIME_FUEL_ERROR => "RUNTIME_FUEL_ERROR",
+            InterpretError::RUNTIME_STACK_OVERFLOW_ERROR => "RUNTIME_STACK_OVERFLOW_ERROR",
+            InterpretError::RUNTIME_MEMORY_ERROR => "RUNTIME_MEMORY_ERROR",
+            InterpretError::RUNTIME_TIMEOUT_ERROR => "RUNTIME_TIMEOUT_ERROR",
+            InterpretError::RUNTIME_SANDBOX_ERROR => "RUNTIME_SANDBOX_ERROR",
+            InterpretError::RUNTIME_INTERRUPT_ERROR => "RUNTIME_INTERRUPT_ERROR",
         };
 
         write!(f, "{message}")
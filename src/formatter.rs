@@ -0,0 +1,463 @@
+//! Prints a program back out in the one canonical style: four space indentation, one statement
+//! per line, spaces around binary operators, a semicolon after every statement and `} else {` on
+//! one line. Comments are kept, and so is a single blank line wherever the source had blank lines.
+
+use crate::ast::*;
+use crate::parser::{parse, ParseError};
+
+const INDENT: &str = "    ";
+
+/// Formats `source`, which must parse.
+pub fn format(source: &String) -> Result<String, Vec<ParseError>> {
+    let program = parse(source)?;
+    let mut formatter = Formatter::new(source);
+    formatter.statements(&program, source.len());
+    Ok(formatter.output)
+}
+
+/// A `//` comment, from the slashes to the end of its line.
+struct Comment {
+    start: usize,
+    end: usize,
+    written: bool,
+}
+
+/// Finds the comments in `source`, skipping `//` inside strings.
+fn comments(source: &str) -> Vec<Comment> {
+    let bytes = source.as_bytes();
+    let mut comments = Vec::new();
+    let mut in_string = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => in_string = !in_string,
+            b'/' if !in_string && bytes.get(i + 1) == Some(&b'/') => {
+                let end = source[i..]
+                    .find('\n')
+                    .map_or(source.len(), |length| i + length);
+                comments.push(Comment {
+                    start: i,
+                    end: end.min(source[..end].trim_end().len()),
+                    written: false,
+                });
+                i = end;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    comments
+}
+
+struct Formatter<'source> {
+    source: &'source str,
+    comments: Vec<Comment>,
+    output: String,
+    depth: usize,
+    /// end of the last statement or comment written, in the source
+    last_end: usize,
+}
+
+impl<'source> Formatter<'source> {
+    fn new(source: &'source str) -> Self {
+        Formatter {
+            source,
+            comments: comments(source),
+            output: String::new(),
+            depth: 0,
+            last_end: 0,
+        }
+    }
+
+    /// Appends `text`, indenting it if it starts a line.
+    fn write(&mut self, text: &str) {
+        if self.output.ends_with('\n') {
+            for _ in 0..self.depth {
+                self.output.push_str(INDENT);
+            }
+        }
+        self.output.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.output.push('\n');
+    }
+
+    /// Keeps one blank line before the item at `start` if the source had any, except at the start
+    /// of the file or of a block.
+    fn gap(&mut self, start: usize) {
+        if start <= self.last_end {
+            return;
+        }
+        let blank = self.source[self.last_end..start].matches('\n').count() >= 2;
+        if blank && !self.output.is_empty() && !self.output.ends_with("{\n") {
+            self.newline();
+        }
+    }
+
+    /// The comments not written yet that start in `start..end`, in order.
+    fn pending(&self, start: usize, end: usize) -> Vec<usize> {
+        (0..self.comments.len())
+            .filter(|&index| {
+                let comment = &self.comments[index];
+                !comment.written && start <= comment.start && comment.start < end
+            })
+            .collect()
+    }
+
+    /// Marks the comment written and returns its text.
+    fn take(&mut self, index: usize) -> &'source str {
+        let comment = &mut self.comments[index];
+        comment.written = true;
+        &self.source[comment.start..comment.end]
+    }
+
+    /// Writes the comments that start before `offset`, each on its own line.
+    fn comments_before(&mut self, offset: usize) {
+        for index in self.pending(0, offset) {
+            let (start, end) = (self.comments[index].start, self.comments[index].end);
+            self.gap(start);
+            let text = self.take(index);
+            self.write(text);
+            self.newline();
+            self.last_end = self.last_end.max(end);
+        }
+    }
+
+    /// Writes the comments inside the statement that its blocks do not write, such as one in the
+    /// middle of an expression, on their own lines before it. Expressions are written on one line,
+    /// where a comment would hide the rest of them.
+    fn comments_within(&mut self, statement: &Stmt) {
+        let mut nested = Vec::new();
+        nested_ranges(statement, &mut nested);
+        for index in self.pending(statement.span.start, statement.span.end) {
+            let start = self.comments[index].start;
+            if nested.iter().any(|range| range.contains(&start)) {
+                continue;
+            }
+            let text = self.take(index);
+            self.write(text);
+            self.newline();
+        }
+    }
+
+    /// Writes a comment that follows the statement ending at `end` on the same line, if it starts
+    /// before `limit`, the end of the enclosing block.
+    fn trailing_comment(&mut self, end: usize, limit: usize) {
+        if let Some(&index) = self.pending(end, limit).first() {
+            let (start, comment_end) = (self.comments[index].start, self.comments[index].end);
+            if !self.source[end..start].contains('\n') {
+                self.write(" ");
+                let text = self.take(index);
+                self.write(text);
+                self.last_end = comment_end;
+            }
+        }
+    }
+
+    /// Writes a list of statements one per line, then the comments before `end`.
+    fn statements(&mut self, statements: &[Stmt], end: usize) {
+        for statement in statements {
+            self.comments_before(statement.span.start);
+            if let StmtKind::Empty = statement.kind {
+                continue;
+            }
+            self.gap(statement.span.start);
+            self.comments_within(statement);
+            self.statement(statement);
+            self.last_end = statement.span.end;
+            self.trailing_comment(statement.span.end, end);
+            self.newline();
+        }
+        self.comments_before(end);
+    }
+
+    /// Writes a statement, leaving the line open for a trailing comment.
+    fn statement(&mut self, statement: &Stmt) {
+        match &statement.kind {
+            StmtKind::Expression(expression) => {
+                let expression = self.expression(expression);
+                self.write(&format!("{expression};"));
+            }
+            StmtKind::Print(value) => {
+                let value = self.expression(value);
+                self.write(&format!("print {value};"));
+            }
+            StmtKind::Var { name, initializer } => match initializer {
+                Some(initializer) => {
+                    let initializer = self.expression(initializer);
+                    self.write(&format!("var {name} = {initializer};"));
+                }
+                None => self.write(&format!("var {name};")),
+            },
+            StmtKind::Block(statements) => {
+                // The block's span ends after its closing brace.
+                let close = statement.span.end - 1;
+                let has_comments = !self.pending(statement.span.start, close).is_empty();
+                if statements.is_empty() && !has_comments {
+                    self.write("{}");
+                    return;
+                }
+
+                self.write("{");
+                self.newline();
+                self.depth += 1;
+                self.last_end = statement.span.start + 1;
+                self.statements(statements, close);
+                self.depth -= 1;
+                self.write("}");
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = self.expression(condition);
+                self.write(&format!("if ({condition}) "));
+                self.statement(then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    // A comment between the branches stays after the then branch.
+                    let between = self.pending(then_branch.span.end, else_branch.span.start);
+                    for (position, index) in between.iter().enumerate() {
+                        let start = self.comments[*index].start;
+                        let same_line = !self.source[then_branch.span.end..start].contains('\n');
+                        if position == 0 && same_line {
+                            self.write(" ");
+                        } else {
+                            self.newline();
+                        }
+                        let text = self.take(*index);
+                        self.write(text);
+                    }
+                    match then_branch.kind {
+                        StmtKind::Block(_) if between.is_empty() => self.write(" "),
+                        _ => self.newline(),
+                    }
+                    self.write("else ");
+                    self.statement(else_branch);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                let condition = self.expression(condition);
+                self.write(&format!("while ({condition}) "));
+                self.statement(body);
+            }
+            StmtKind::For {
+                initializer,
+                condition,
+                increment,
+                body,
+            } => {
+                self.write("for (");
+                match initializer {
+                    Some(initializer) => self.statement(initializer),
+                    None => self.write(";"),
+                }
+                match condition {
+                    Some(condition) => {
+                        let condition = self.expression(condition);
+                        self.write(&format!(" {condition};"));
+                    }
+                    None => self.write(";"),
+                }
+                if let Some(increment) = increment {
+                    let increment = self.expression(increment);
+                    self.write(&format!(" {increment}"));
+                }
+                self.write(") ");
+                self.statement(body);
+            }
+            StmtKind::AssertEq {
+                left,
+                right,
+                message,
+            } => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                match message {
+                    Some(message) => {
+                        let message = self.expression(message);
+                        self.write(&format!("assert_eq({left}, {right}, {message});"));
+                    }
+                    None => self.write(&format!("assert_eq({left}, {right});")),
+                }
+            }
+            StmtKind::Import { alias, path, .. } => match alias {
+                Some(alias) => self.write(&format!("import {alias} from \"{path}\";")),
+                None => self.write(&format!("import \"{path}\";")),
+            },
+            StmtKind::Test { name, body } => {
+                self.write(&format!("test \"{name}\" "));
+                self.statement(body);
+            }
+            StmtKind::Empty => self.write(";"),
+        }
+    }
+
+    fn expression(&self, expression: &Expr) -> String {
+        match &expression.kind {
+            // Numbers are written the way the source wrote them, `1.0` stays `1.0`.
+            ExprKind::Literal(Literal::Number(_)) => expression.span.slice(self.source).to_string(),
+            ExprKind::Literal(Literal::String(string)) => format!("\"{string}\""),
+            ExprKind::Literal(Literal::Bool(bool)) => bool.to_string(),
+            ExprKind::Literal(Literal::Nil) => "nil".to_string(),
+            ExprKind::Variable(name) => name.clone(),
+            ExprKind::Assign { name, value } => format!("{name} = {}", self.expression(value)),
+            ExprKind::Unary { op, operand } => format!("{op}{}", self.expression(operand)),
+            ExprKind::Binary { op, left, right } => {
+                format!("{} {op} {}", self.expression(left), self.expression(right))
+            }
+            ExprKind::Logical { op, left, right } => {
+                format!("{} {op} {}", self.expression(left), self.expression(right))
+            }
+            ExprKind::Grouping(inner) => format!("({})", self.expression(inner)),
+            ExprKind::Call { callee, arguments } => {
+                let arguments: Vec<String> = arguments
+                    .iter()
+                    .map(|argument| self.expression(argument))
+                    .collect();
+                format!("{}({})", self.expression(callee), arguments.join(", "))
+            }
+            ExprKind::Get { object, name } => format!("{}.{name}", self.expression(object)),
+        }
+    }
+}
+
+/// Collects the parts of the statement whose comments are written by the statement itself: its
+/// blocks, and the space between the branches of an `if`.
+fn nested_ranges(statement: &Stmt, ranges: &mut Vec<std::ops::Range<usize>>) {
+    match &statement.kind {
+        StmtKind::Block(_) => ranges.push(statement.span.start..statement.span.end),
+        StmtKind::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            nested_ranges(then_branch, ranges);
+            if let Some(else_branch) = else_branch {
+                ranges.push(then_branch.span.end..else_branch.span.start);
+                nested_ranges(else_branch, ranges);
+            }
+        }
+        StmtKind::While { body, .. } | StmtKind::For { body, .. } | StmtKind::Test { body, .. } => {
+            nested_ranges(body, ranges)
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::format;
+
+    fn formatted(source: &str) -> String {
+        format(&source.to_string()).unwrap()
+    }
+
+    #[test]
+    fn format_statements_test() {
+        let source =
+            "var num=1;\nif (false and true){\nnum=2\n}\nelse { num = -num }\nassert_eq(1,num);";
+        let expected = "\
+var num = 1;
+if (false and true) {
+    num = 2;
+} else {
+    num = -num;
+}
+assert_eq(1, num);
+";
+        assert_eq!(expected, formatted(source));
+    }
+
+    #[test]
+    fn format_loops_and_imports_test() {
+        let source = "import  m from \"lib/m.ys\" ;\nimport \"g.ys\";\nwhile (m.x<3) { print m.f(1,2.50) ; }\nfor (var i=0;i<2;i=i+1) print i;\nfor (;;) {}";
+        let expected = "\
+import m from \"lib/m.ys\";
+import \"g.ys\";
+while (m.x < 3) {
+    print m.f(1, 2.50);
+}
+for (var i = 0; i < 2; i = i + 1) print i;
+for (;;) {}
+";
+        assert_eq!(expected, formatted(source));
+    }
+
+    #[test]
+    fn format_tests_test() {
+        let source = "var test=1;\ntest   \"sums\"{assert_eq(test+1,2);}\ntest \"empty\" {}";
+        let expected = "\
+var test = 1;
+test \"sums\" {
+    assert_eq(test + 1, 2);
+}
+test \"empty\" {}
+";
+        assert_eq!(expected, formatted(source));
+    }
+
+    #[test]
+    fn format_keeps_comments_test() {
+        let source = "// header\n\n\n\nvar a = 1;   // one\n{\n  // inside\n  print \"// not a comment\";\n  // last\n}\n// end\n";
+        let expected = "\
+// header
+
+var a = 1; // one
+{
+    // inside
+    print \"// not a comment\";
+    // last
+}
+// end
+";
+        assert_eq!(expected, formatted(source));
+    }
+
+    #[test]
+    fn format_is_idempotent_test() {
+        for source in [
+            "var x = 2;\nif (x == 1) {\n print \"one\";\n}\nelse if (x == 2) { print \"two\"; } else print \"many\";",
+            "{ var a = (1 + 2) * 3; a = a ** 2; }\n\n// done",
+            "print !(1 <= 2) or nil;",
+            "assert_eq( 1,1 ,\"same\"  );",
+        ] {
+            let once = formatted(source);
+            assert_eq!(once, formatted(&once), "formatting changed again: {once}");
+        }
+    }
+
+    #[test]
+    fn format_comment_inside_expression_test() {
+        let source = "var a = 1 + // note\n  2;\nprint a;\n";
+        let expected = "\
+// note
+var a = 1 + 2;
+print a;
+";
+        assert_eq!(expected, formatted(source));
+        assert_eq!(expected, formatted(expected));
+    }
+
+    #[test]
+    fn format_comment_after_then_branch_test() {
+        let source = "if (c) { print 1; } // after\nelse { print 2; }\n";
+        let expected = "\
+if (c) {
+    print 1;
+} // after
+else {
+    print 2;
+}
+";
+        assert_eq!(expected, formatted(source));
+        assert_eq!(expected, formatted(expected));
+    }
+
+    #[test]
+    fn format_parse_error_test() {
+        assert!(format(&"var = 1;".to_string()).is_err());
+    }
+}
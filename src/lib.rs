@@ -1,12 +1,28 @@
 extern crate core;
 
+pub mod assembler;
+pub mod ast;
 pub mod chunk;
+pub mod codegen;
 pub mod compiler;
 pub mod debug;
+pub mod debugger;
 pub mod error;
+pub mod formatter;
+pub mod limits;
+pub mod linter;
+pub mod lsp;
+pub mod optimizer;
+pub mod parser;
+pub mod profiler;
+pub mod repl;
 pub mod scanner;
+pub mod serialize;
+pub mod stdlib;
 pub mod table;
 pub mod test_macros;
+pub mod test_runner;
 pub mod util;
 pub mod value;
+pub mod verifier;
 pub mod vm;
@@ -0,0 +1,121 @@
+//! Bounds on what a script may do, for running code that is not trusted. Set `VM::limits` before
+//! running; a script that goes over a limit stops with an error of its own and the VM can run the
+//! next script.
+
+use crate::error::InterpretError::*;
+use anyhow::{Context, Result};
+use std::time::{Duration, Instant};
+
+/// `None` means unlimited. The counts start over each time the VM interprets a script, imported
+/// modules count towards the script that imported them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// instructions the script may run, `RUNTIME_FUEL_ERROR` after that
+    pub fuel: Option<u64>,
+    /// values the stack may hold, `RUNTIME_STACK_OVERFLOW_ERROR` past that
+    pub max_stack: Option<usize>,
+    /// bytes the script may allocate while running, e.g. by concatenating strings,
+    /// `RUNTIME_MEMORY_ERROR` past that
+    pub max_heap: Option<usize>,
+    /// how long the script may run, `RUNTIME_TIMEOUT_ERROR` after that
+    pub timeout: Option<Duration>,
+    /// whether `print` may write output, `RUNTIME_SANDBOX_ERROR` when it may not
+    pub allow_print: bool,
+    /// whether `import` may read files, `RUNTIME_SANDBOX_ERROR` when it may not
+    pub allow_imports: bool,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            fuel: None,
+            max_stack: None,
+            max_heap: None,
+            timeout: None,
+            allow_print: true,
+            allow_imports: true,
+        }
+    }
+}
+
+impl Limits {
+    /// Whether any of the counted limits is set. Without one the VM skips counting.
+    pub fn is_bounded(&self) -> bool {
+        self.fuel.is_some()
+            || self.max_stack.is_some()
+            || self.max_heap.is_some()
+            || self.timeout.is_some()
+    }
+}
+
+/// What the running script has used so far.
+#[derive(Debug, Clone)]
+pub struct Usage {
+    /// instructions run, only counted while a limit is set
+    pub instructions: u64,
+    /// bytes allocated while running
+    pub heap: usize,
+    pub started: Instant,
+    /// when the clock was stopped, while the script is suspended or its hook runs
+    pub stopped: Option<Instant>,
+}
+
+impl Default for Usage {
+    fn default() -> Self {
+        Usage {
+            instructions: 0,
+            heap: 0,
+            started: Instant::now(),
+            stopped: None,
+        }
+    }
+}
+
+impl Usage {
+    /// The clock is only read every this many instructions.
+    const CLOCK_INTERVAL: u64 = 1024;
+
+    /// Stops the clock `timeout` is measured with, for time the script is not running.
+    pub fn stop_clock(&mut self) {
+        self.stopped.get_or_insert_with(Instant::now);
+    }
+
+    /// Starts the clock again, leaving out the time it was stopped.
+    pub fn start_clock(&mut self) {
+        if let Some(stopped) = self.stopped.take() {
+            self.started += stopped.elapsed();
+        }
+    }
+
+    /// Counts the instruction about to run and fails if the script has gone over a limit.
+    pub fn check(&mut self, limits: &Limits, stack: usize) -> Result<()> {
+        self.instructions += 1;
+        if let Some(fuel) = limits.fuel {
+            if self.instructions > fuel {
+                return Err(RUNTIME_FUEL_ERROR)
+                    .context(format!("ran out of fuel after {fuel} instructions"));
+            }
+        }
+        if let Some(max_stack) = limits.max_stack {
+            if stack > max_stack {
+                return Err(RUNTIME_STACK_OVERFLOW_ERROR)
+                    .context(format!("stack overflow, more than {max_stack} values"));
+            }
+        }
+        if let Some(max_heap) = limits.max_heap {
+            if self.heap > max_heap {
+                return Err(RUNTIME_MEMORY_ERROR)
+                    .context(format!("allocated more than {max_heap} bytes"));
+            }
+        }
+        if let Some(timeout) = limits.timeout {
+            if self.instructions.is_multiple_of(Usage::CLOCK_INTERVAL)
+                && self.started.elapsed() > timeout
+            {
+                return Err(RUNTIME_TIMEOUT_ERROR)
+                    .context(format!("ran for longer than {timeout:?}"));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,510 @@
+//! Finds mistakes in a program that compile fine but are probably not what was meant, like
+//! assigning to a global that was never declared, which only fails once `OP_SET_GLOBAL` runs.
+
+use crate::ast::*;
+use crate::parser::{parse, ParseError};
+use crate::table::Table;
+use crate::vm::VM;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    /// a local variable that is never read
+    UnusedLocal,
+    /// an assignment to a global that no `var` or `import` above it declared
+    UndeclaredGlobal,
+    /// a statement after a loop that never ends. The language has no `return` or `break` yet, so
+    /// an infinite loop is the only thing code can be unreachable after.
+    UnreachableCode,
+    /// a local declared with the name of a variable that is already in scope
+    Shadowing,
+    /// a comparison of an expression with itself
+    SelfComparison,
+    /// an `if`, `while` or `for` condition that does not depend on any variable
+    ConstantCondition,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [
+        Lint::UnusedLocal,
+        Lint::UndeclaredGlobal,
+        Lint::UnreachableCode,
+        Lint::Shadowing,
+        Lint::SelfComparison,
+        Lint::ConstantCondition,
+    ];
+
+    /// The name used to turn the lint off, e.g. `--allow unused_local`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedLocal => "unused_local",
+            Lint::UndeclaredGlobal => "undeclared_global",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::Shadowing => "shadowing",
+            Lint::SelfComparison => "self_comparison",
+            Lint::ConstantCondition => "constant_condition",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+/// Which lints run. All of them do by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintOptions {
+    pub allowed: Vec<Lint>,
+}
+
+impl LintOptions {
+    pub fn allow(&mut self, lint: Lint) {
+        if !self.allowed.contains(&lint) {
+            self.allowed.push(lint);
+        }
+    }
+
+    pub fn is_enabled(&self, lint: Lint) -> bool {
+        !self.allowed.contains(&lint)
+    }
+}
+
+/// A problem found by a lint, at the code it is about.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub lint: Lint,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[line {}] Warning: {} ({})",
+            self.span.line,
+            self.message,
+            self.lint.name()
+        )
+    }
+}
+
+/// Runs the enabled lints over `source`, which must parse. Diagnostics are in source order.
+pub fn lint(source: &String, options: &LintOptions) -> Result<Vec<Diagnostic>, Vec<ParseError>> {
+    let program = parse(source)?;
+    let mut linter = Linter::new(source, options);
+    linter.statements(&program);
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    Ok(diagnostics)
+}
+
+struct Local {
+    name: String,
+    span: Span,
+    used: bool,
+}
+
+struct Linter<'a> {
+    source: &'a str,
+    options: &'a LintOptions,
+    /// globals every program starts with, like `sqrt` and `pi`
+    predefined: Table,
+    globals: HashSet<String>,
+    /// the locals of each block the linter is inside, innermost last
+    scopes: Vec<Vec<Local>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    fn new(source: &'a str, options: &'a LintOptions) -> Self {
+        Linter {
+            source,
+            options,
+            predefined: VM::default().table,
+            globals: HashSet::new(),
+            scopes: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, lint: Lint, span: Span, message: String) {
+        if self.options.is_enabled(lint) {
+            self.diagnostics.push(Diagnostic {
+                lint,
+                message,
+                span,
+            });
+        }
+    }
+
+    /// Lints a list of statements, reporting the first one after a statement that never finishes.
+    fn statements(&mut self, statements: &[Stmt]) {
+        let mut diverged = false;
+        for statement in statements {
+            if diverged {
+                let message = "Unreachable statement, the loop above never ends.".to_string();
+                self.report(Lint::UnreachableCode, statement.span, message);
+                diverged = false;
+            } else if diverges(statement) {
+                diverged = true;
+            }
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Stmt) {
+        match &statement.kind {
+            StmtKind::Expression(expression) | StmtKind::Print(expression) => {
+                self.expression(expression)
+            }
+            StmtKind::Var { name, initializer } => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                self.declare(name, statement.span);
+            }
+            StmtKind::Block(statements) => {
+                self.scopes.push(Vec::new());
+                self.statements(statements);
+                self.end_scope();
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.condition(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                self.condition(condition);
+                self.statement(body);
+            }
+            StmtKind::For {
+                initializer,
+                condition,
+                increment,
+                body,
+            } => {
+                self.scopes.push(Vec::new());
+                if let Some(initializer) = initializer {
+                    self.statement(initializer);
+                }
+                if let Some(condition) = condition {
+                    self.condition(condition);
+                }
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                }
+                self.statement(body);
+                self.end_scope();
+            }
+            StmtKind::AssertEq {
+                left,
+                right,
+                message,
+            } => {
+                self.expression(left);
+                self.expression(right);
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+            }
+            StmtKind::Import { name, .. } => {
+                self.globals.insert(name.clone());
+            }
+            StmtKind::Test { body, .. } => self.statement(body),
+            StmtKind::Empty => {}
+        }
+    }
+
+    fn condition(&mut self, condition: &Expr) {
+        if is_constant(condition) {
+            let message = format!(
+                "Condition '{}' is always the same.",
+                condition.span.slice(self.source)
+            );
+            self.report(Lint::ConstantCondition, condition.span, message);
+        }
+        self.expression(condition);
+    }
+
+    fn expression(&mut self, expression: &Expr) {
+        match &expression.kind {
+            ExprKind::Literal(_) => {}
+            ExprKind::Variable(name) => {
+                if let Some(local) = self.resolve(name) {
+                    local.used = true;
+                }
+            }
+            ExprKind::Assign { name, value } => {
+                self.expression(value);
+                let declared = self.resolve(name).is_some()
+                    || self.globals.contains(name)
+                    || self.predefined.get(name).is_some();
+                if !declared {
+                    let message = format!("Assignment to undeclared global '{name}'.");
+                    self.report(Lint::UndeclaredGlobal, expression.span, message);
+                }
+            }
+            ExprKind::Unary { operand, .. } => self.expression(operand),
+            ExprKind::Binary { op, left, right } => {
+                let comparison = !matches!(
+                    op,
+                    BinaryOp::Add
+                        | BinaryOp::Subtract
+                        | BinaryOp::Multiply
+                        | BinaryOp::Divide
+                        | BinaryOp::Modulo
+                        | BinaryOp::Power
+                );
+                if comparison && same_expression(left, right) {
+                    let message = format!(
+                        "'{}' is compared with itself.",
+                        left.span.slice(self.source)
+                    );
+                    self.report(Lint::SelfComparison, expression.span, message);
+                }
+                self.expression(left);
+                self.expression(right);
+            }
+            ExprKind::Logical { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExprKind::Grouping(inner) => self.expression(inner),
+            ExprKind::Call { callee, arguments } => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            ExprKind::Get { object, .. } => self.expression(object),
+        }
+    }
+
+    /// Declares a variable in the current scope, a global outside of any block.
+    fn declare(&mut self, name: &str, span: Span) {
+        if self.scopes.is_empty() {
+            self.globals.insert(name.to_string());
+            return;
+        }
+
+        let redeclared = self
+            .scopes
+            .last()
+            .unwrap()
+            .iter()
+            .rposition(|local| local.name == name);
+        if let Some(index) = redeclared {
+            let message = format!("'{name}' is declared again in the same scope.");
+            self.report(Lint::Shadowing, span, message);
+            let local = self.scopes.last_mut().unwrap().remove(index);
+            self.check_used(local);
+        } else if self.resolve(name).is_some() || self.globals.contains(name) {
+            let message = format!("'{name}' shadows a variable from an outer scope.");
+            self.report(Lint::Shadowing, span, message);
+        }
+
+        self.scopes.last_mut().unwrap().push(Local {
+            name: name.to_string(),
+            span,
+            used: false,
+        });
+    }
+
+    fn resolve(&mut self, name: &str) -> Option<&mut Local> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|local| local.name == name)
+    }
+
+    fn end_scope(&mut self) {
+        for local in self.scopes.pop().unwrap_or_default() {
+            self.check_used(local);
+        }
+    }
+
+    /// Locals whose name starts with `_` are allowed to be unused.
+    fn check_used(&mut self, local: Local) {
+        if !local.used && !local.name.starts_with('_') {
+            let message = format!("Local variable '{}' is never read.", local.name);
+            self.report(Lint::UnusedLocal, local.span, message);
+        }
+    }
+}
+
+/// Whether the statement runs forever once it starts.
+fn diverges(statement: &Stmt) -> bool {
+    match &statement.kind {
+        StmtKind::While { condition, .. } => is_true(condition),
+        StmtKind::For { condition, .. } => condition.as_ref().is_none_or(is_true),
+        StmtKind::Block(statements) => statements.iter().any(diverges),
+        StmtKind::If {
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } => diverges(then_branch) && diverges(else_branch),
+        _ => false,
+    }
+}
+
+fn is_true(expression: &Expr) -> bool {
+    match &expression.kind {
+        ExprKind::Literal(Literal::Bool(true)) => true,
+        ExprKind::Grouping(inner) => is_true(inner),
+        _ => false,
+    }
+}
+
+/// Whether the expression is made of literals only, so it has the same value every time.
+fn is_constant(expression: &Expr) -> bool {
+    match &expression.kind {
+        ExprKind::Literal(_) => true,
+        ExprKind::Grouping(inner) => is_constant(inner),
+        ExprKind::Unary { operand, .. } => is_constant(operand),
+        ExprKind::Binary { left, right, .. } | ExprKind::Logical { left, right, .. } => {
+            is_constant(left) && is_constant(right)
+        }
+        _ => false,
+    }
+}
+
+/// Whether two expressions are written the same, ignoring where they are. Calls are never the
+/// same, since they may return something different each time.
+fn same_expression(a: &Expr, b: &Expr) -> bool {
+    match (&a.kind, &b.kind) {
+        (ExprKind::Literal(a), ExprKind::Literal(b)) => a == b,
+        (ExprKind::Variable(a), ExprKind::Variable(b)) => a == b,
+        (ExprKind::Grouping(a), ExprKind::Grouping(b)) => same_expression(a, b),
+        (
+            ExprKind::Unary {
+                op: op_a,
+                operand: a,
+            },
+            ExprKind::Unary {
+                op: op_b,
+                operand: b,
+            },
+        ) => op_a == op_b && same_expression(a, b),
+        (
+            ExprKind::Binary {
+                op: op_a,
+                left: left_a,
+                right: right_a,
+            },
+            ExprKind::Binary {
+                op: op_b,
+                left: left_b,
+                right: right_b,
+            },
+        ) => op_a == op_b && same_expression(left_a, left_b) && same_expression(right_a, right_b),
+        (
+            ExprKind::Get {
+                object: a,
+                name: name_a,
+            },
+            ExprKind::Get {
+                object: b,
+                name: name_b,
+            },
+        ) => name_a == name_b && same_expression(a, b),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::linter::{lint, Lint, LintOptions};
+
+    /// The lints reported for `source` with every lint enabled, and the lines they are on.
+    fn lints(source: &str) -> Vec<(Lint, usize)> {
+        lint(&source.to_string(), &LintOptions::default())
+            .unwrap()
+            .iter()
+            .map(|diagnostic| (diagnostic.lint, diagnostic.span.line))
+            .collect()
+    }
+
+    #[test]
+    fn lint_clean_program_test() {
+        let source = "
+            import geometry from \"geometry.ys\";
+            var total = 0;
+            for (var i = 0; i < 3; i = i + 1) {
+                var _ignored = 1;
+                total = total + sqrt(i);
+            }
+            pi = 3;
+            print total + geometry.sides;
+        ";
+        assert_eq!(Vec::<(Lint, usize)>::new(), lints(source));
+    }
+
+    #[test]
+    fn lint_unused_local_test() {
+        let source = "{\n var a = 1;\n var b = 2;\n a = 3;\n print b;\n}";
+        assert_eq!(vec![(Lint::UnusedLocal, 2)], lints(source));
+    }
+
+    #[test]
+    fn lint_undeclared_global_test() {
+        let source = "count = 1;\nvar count = 0;\ncount = 2;";
+        assert_eq!(vec![(Lint::UndeclaredGlobal, 1)], lints(source));
+    }
+
+    #[test]
+    fn lint_unreachable_code_test() {
+        let source =
+            "var a = 0;\nwhile (a < 1) { a = a + 1; }\n{ for (;;) {} }\nprint a;\nprint a;";
+        assert_eq!(vec![(Lint::UnreachableCode, 4)], lints(source));
+    }
+
+    #[test]
+    fn lint_shadowing_test() {
+        let source = "var a = 1;\n{\n var a = 2;\n var a = 3;\n print a;\n}";
+        assert_eq!(
+            vec![
+                (Lint::Shadowing, 3),
+                (Lint::UnusedLocal, 3),
+                (Lint::Shadowing, 4)
+            ],
+            lints(source)
+        );
+    }
+
+    #[test]
+    fn lint_self_comparison_and_constant_condition_test() {
+        let source =
+            "var a = 1;\nprint a == a;\nprint a + a;\nif (1 < 2) print a;\nwhile (!(a <= a)) {}";
+        assert_eq!(
+            vec![
+                (Lint::SelfComparison, 2),
+                (Lint::ConstantCondition, 4),
+                (Lint::SelfComparison, 5),
+            ],
+            lints(source)
+        );
+    }
+
+    #[test]
+    fn lint_options_test() {
+        let mut options = LintOptions::default();
+        options.allow(Lint::UndeclaredGlobal);
+        let diagnostics = lint(&"a = 1;\n{ var b = a; }".to_string(), &options).unwrap();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            "[line 2] Warning: Local variable 'b' is never read. (unused_local)",
+            diagnostics[0].to_string()
+        );
+        assert_eq!(Some(Lint::Shadowing), Lint::from_name("shadowing"));
+    }
+}
@@ -0,0 +1,349 @@
+//! Resolves every variable in a program to where it was declared, following the same scope rules
+//! as `codegen`: locals live until the end of their block, anything else is a global.
+
+use crate::ast::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Global,
+    Local,
+    Module,
+}
+
+/// A declared variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// where the name is written in the declaration
+    pub name_span: Span,
+    /// the whole declaration
+    pub span: Span,
+    /// what the initializer evaluates to, as far as can be told without running it
+    pub inferred: &'static str,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub symbols: Vec<Symbol>,
+    /// every read or assignment of a declared variable, as the name's span and the symbol index
+    pub references: Vec<(Span, usize)>,
+    /// names used without a declaration in the program, like the standard library's
+    pub unresolved: Vec<(String, Span)>,
+}
+
+impl Analysis {
+    /// The symbol declared or referenced at `offset`.
+    pub fn symbol_at(&self, offset: usize) -> Option<usize> {
+        let contains = |span: &Span| span.start <= offset && offset <= span.end;
+        self.symbols
+            .iter()
+            .position(|symbol| contains(&symbol.name_span))
+            .or_else(|| {
+                self.references
+                    .iter()
+                    .find(|(span, _)| contains(span))
+                    .map(|(_, symbol)| *symbol)
+            })
+    }
+
+    /// The undeclared name used at `offset`.
+    pub fn unresolved_at(&self, offset: usize) -> Option<&str> {
+        self.unresolved
+            .iter()
+            .find(|(_, span)| span.start <= offset && offset <= span.end)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn references_to(&self, symbol: usize) -> impl Iterator<Item = Span> + '_ {
+        self.references
+            .iter()
+            .filter(move |(_, referenced)| *referenced == symbol)
+            .map(|(span, _)| *span)
+    }
+}
+
+pub fn analyze(program: &[Stmt], source: &str) -> Analysis {
+    let mut analyzer = Analyzer {
+        source,
+        analysis: Analysis::default(),
+        globals: HashMap::new(),
+        scopes: Vec::new(),
+        pending: Vec::new(),
+    };
+    for statement in program {
+        analyzer.statement(statement);
+    }
+
+    // A global can be used in a block above its declaration.
+    for (name, span) in analyzer.pending {
+        match analyzer.globals.get(&name) {
+            Some(symbol) => analyzer.analysis.references.push((span, *symbol)),
+            None => analyzer.analysis.unresolved.push((name, span)),
+        }
+    }
+    analyzer.analysis
+}
+
+struct Analyzer<'source> {
+    source: &'source str,
+    analysis: Analysis,
+    /// the latest declaration of each global
+    globals: HashMap<String, usize>,
+    /// the locals of each block the analyzer is inside, innermost last
+    scopes: Vec<Vec<(String, usize)>>,
+    /// names that were not declared yet where they were used
+    pending: Vec<(String, Span)>,
+}
+
+impl Analyzer<'_> {
+    fn statement(&mut self, statement: &Stmt) {
+        match &statement.kind {
+            StmtKind::Expression(expression) | StmtKind::Print(expression) => {
+                self.expression(expression)
+            }
+            StmtKind::Var { name, initializer } => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                let inferred = initializer
+                    .as_ref()
+                    .map_or("nil", |initializer| self.infer(initializer));
+                let kind = if self.scopes.is_empty() {
+                    SymbolKind::Global
+                } else {
+                    SymbolKind::Local
+                };
+                // The name is the first thing after `var`.
+                let name_span = self.find_name(statement.span, 3, name);
+                self.declare(name, kind, name_span, statement.span, inferred);
+            }
+            StmtKind::Block(statements) => {
+                self.scopes.push(Vec::new());
+                for statement in statements {
+                    self.statement(statement);
+                }
+                self.scopes.pop();
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expression(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            StmtKind::For {
+                initializer,
+                condition,
+                increment,
+                body,
+            } => {
+                self.scopes.push(Vec::new());
+                if let Some(initializer) = initializer {
+                    self.statement(initializer);
+                }
+                for expression in condition.iter().chain(increment) {
+                    self.expression(expression);
+                }
+                self.statement(body);
+                self.scopes.pop();
+            }
+            StmtKind::AssertEq {
+                left,
+                right,
+                message,
+            } => {
+                self.expression(left);
+                self.expression(right);
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+            }
+            StmtKind::Import { name, .. } => {
+                // Either the alias after `import`, or the file name inside the path.
+                let name_span = self.find_name(statement.span, 6, name);
+                self.declare(
+                    name,
+                    SymbolKind::Module,
+                    name_span,
+                    statement.span,
+                    "module",
+                );
+            }
+            StmtKind::Test { body, .. } => self.statement(body),
+            StmtKind::Empty => {}
+        }
+    }
+
+    fn expression(&mut self, expression: &Expr) {
+        match &expression.kind {
+            ExprKind::Literal(_) => {}
+            ExprKind::Variable(name) => self.reference(name, expression.span),
+            ExprKind::Assign { name, value } => {
+                self.expression(value);
+                let span = expression.span;
+                self.reference(
+                    name,
+                    Span::new(span.start, span.start + name.len(), span.line),
+                );
+            }
+            ExprKind::Unary { operand, .. } => self.expression(operand),
+            ExprKind::Binary { left, right, .. } | ExprKind::Logical { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExprKind::Grouping(inner) => self.expression(inner),
+            ExprKind::Call { callee, arguments } => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            ExprKind::Get { object, .. } => self.expression(object),
+        }
+    }
+
+    /// The span of `name` in the declaration `span`, searching after the keyword.
+    fn find_name(&self, span: Span, keyword_length: usize, name: &str) -> Span {
+        let start = span
+            .slice(self.source)
+            .get(keyword_length..)
+            .and_then(|rest| rest.find(name))
+            .map_or(span.start, |index| span.start + keyword_length + index);
+        Span::new(start, start + name.len(), span.line)
+    }
+
+    fn declare(
+        &mut self,
+        name: &str,
+        kind: SymbolKind,
+        name_span: Span,
+        span: Span,
+        inferred: &'static str,
+    ) {
+        let symbol = self.analysis.symbols.len();
+        self.analysis.symbols.push(Symbol {
+            name: name.to_string(),
+            kind,
+            name_span,
+            span,
+            inferred,
+        });
+        match self.scopes.last_mut() {
+            Some(scope) => scope.push((name.to_string(), symbol)),
+            None => {
+                self.globals.insert(name.to_string(), symbol);
+            }
+        }
+    }
+
+    fn resolve(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(local, _)| local == name)
+            .map(|(_, symbol)| *symbol)
+            .or_else(|| self.globals.get(name).copied())
+    }
+
+    fn reference(&mut self, name: &str, span: Span) {
+        match self.resolve(name) {
+            Some(symbol) => self.analysis.references.push((span, symbol)),
+            None => self.pending.push((name.to_string(), span)),
+        }
+    }
+
+    /// The kind of value an expression evaluates to, `"unknown"` when it depends on a call or
+    /// on which side of an `and` or `or` is picked.
+    fn infer(&self, expression: &Expr) -> &'static str {
+        match &expression.kind {
+            ExprKind::Literal(Literal::Number(_)) => "number",
+            ExprKind::Literal(Literal::String(_)) => "string",
+            ExprKind::Literal(Literal::Bool(_)) => "bool",
+            ExprKind::Literal(Literal::Nil) => "nil",
+            ExprKind::Variable(name) => self
+                .resolve(name)
+                .map_or("unknown", |symbol| self.analysis.symbols[symbol].inferred),
+            ExprKind::Assign { value, .. } | ExprKind::Grouping(value) => self.infer(value),
+            ExprKind::Unary {
+                op: UnaryOp::Negate,
+                ..
+            } => "number",
+            ExprKind::Unary {
+                op: UnaryOp::Not, ..
+            } => "bool",
+            ExprKind::Binary { op, left, right } => match op {
+                BinaryOp::Add if self.infer(left) == "string" || self.infer(right) == "string" => {
+                    "string"
+                }
+                BinaryOp::Add
+                | BinaryOp::Subtract
+                | BinaryOp::Multiply
+                | BinaryOp::Divide
+                | BinaryOp::Modulo
+                | BinaryOp::Power => "number",
+                _ => "bool",
+            },
+            ExprKind::Logical { left, right, .. } => {
+                let kind = self.infer(left);
+                if kind == self.infer(right) {
+                    kind
+                } else {
+                    "unknown"
+                }
+            }
+            ExprKind::Call { .. } | ExprKind::Get { .. } => "unknown",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lsp::analysis::{analyze, SymbolKind};
+    use crate::parser::parse;
+
+    #[test]
+    fn analyze_scopes_test() {
+        let source = "var a = 1;\n{\n var a = \"s\";\n print a;\n}\na = a + 1;";
+        let analysis = analyze(&parse(&source.to_string()).unwrap(), source);
+        assert_eq!(2, analysis.symbols.len());
+        assert_eq!(SymbolKind::Global, analysis.symbols[0].kind);
+        assert_eq!("number", analysis.symbols[0].inferred);
+        assert_eq!(SymbolKind::Local, analysis.symbols[1].kind);
+        assert_eq!("string", analysis.symbols[1].inferred);
+        assert_eq!("a", analysis.symbols[1].name_span.slice(source));
+
+        let print_a = source.find("print a").unwrap() + 6;
+        assert_eq!(Some(1), analysis.symbol_at(print_a));
+        let global_uses: Vec<&str> = analysis
+            .references_to(0)
+            .map(|span| span.slice(source))
+            .collect();
+        assert_eq!(vec!["a", "a"], global_uses);
+    }
+
+    #[test]
+    fn analyze_imports_and_natives_test() {
+        let source =
+            "{ print later + sqrt(2); }\nimport \"lib/geometry.ys\";\nvar later = geometry.sides;";
+        let analysis = analyze(&parse(&source.to_string()).unwrap(), source);
+        assert_eq!(SymbolKind::Module, analysis.symbols[0].kind);
+        assert_eq!("geometry", analysis.symbols[0].name_span.slice(source));
+        assert_eq!("unknown", analysis.symbols[1].inferred);
+        assert_eq!(Some(1), analysis.symbol_at(source.find("later").unwrap()));
+        assert_eq!(
+            Some("sqrt"),
+            analysis.unresolved_at(source.find("sqrt").unwrap())
+        );
+    }
+}
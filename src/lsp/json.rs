@@ -0,0 +1,346 @@
+//! Just enough JSON for the language server's messages.
+
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in the order they were written.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Builds an object from `(key, value)` pairs.
+    pub fn object<const N: usize>(members: [(&str, Json); N]) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// The member `key` of an object, `None` for anything else.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Follows a chain of object members, e.g. `["params", "textDocument", "uri"]`.
+    pub fn path(&self, keys: &[&str]) -> Option<&Json> {
+        keys.iter().try_fold(self, |json, key| json.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(bool) => Some(*bool),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = JsonParser { text, position: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position < text.len() {
+            return Err(format!("unexpected text at {}", parser.position));
+        }
+        Ok(value)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(string: &str) -> Self {
+        Json::String(string.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(string: String) -> Self {
+        Json::String(string)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(bool: bool) -> Self {
+        Json::Bool(bool)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(number: usize) -> Self {
+        Json::Number(number as f64)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(array: Vec<Json>) -> Self {
+        Json::Array(array)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(bool) => write!(f, "{bool}"),
+            Json::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+                write!(f, "{}", *number as i64)
+            }
+            Json::Number(number) if number.is_finite() => write!(f, "{number}"),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(string) => write_string(f, string),
+            Json::Array(array) => {
+                f.write_char('[')?;
+                for (i, value) in array.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(members) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut Formatter<'_>, string: &str) -> std::fmt::Result {
+    f.write_char('"')?;
+    for c in string.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+struct JsonParser<'text> {
+    text: &'text str,
+    position: usize,
+}
+
+impl JsonParser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(format!(
+                "expected '{}' at {}",
+                expected as char, self.position
+            ));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        if !self.text[self.position..].starts_with(word) {
+            return Err(format!("unexpected text at {}", self.position));
+        }
+        self.position += word.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[') => {
+                self.position += 1;
+                let mut array = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.position += 1;
+                    return Ok(Json::Array(array));
+                }
+                loop {
+                    array.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b']')?;
+                Ok(Json::Array(array))
+            }
+            Some(b'{') => {
+                self.position += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b'}') {
+                    self.position += 1;
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    members.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b'}')?;
+                Ok(Json::Object(members))
+            }
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.position;
+                while matches!(
+                    self.peek(),
+                    Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+                ) {
+                    self.position += 1;
+                }
+                self.text[start..self.position]
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| format!("invalid number at {start}"))
+            }
+            _ => Err(format!("unexpected text at {}", self.position)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut string = String::new();
+        loop {
+            let rest = &self.text[self.position..];
+            let c = rest.chars().next().ok_or("unterminated string")?;
+            self.position += c.len_utf8();
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escape = self.peek().ok_or("unterminated string")?;
+                    self.position += 1;
+                    match escape {
+                        b'"' => string.push('"'),
+                        b'\\' => string.push('\\'),
+                        b'/' => string.push('/'),
+                        b'b' => string.push('\u{8}'),
+                        b'f' => string.push('\u{c}'),
+                        b'n' => string.push('\n'),
+                        b'r' => string.push('\r'),
+                        b't' => string.push('\t'),
+                        b'u' => {
+                            let mut code = self.hex()?;
+                            // A character outside the basic plane is written as a surrogate pair.
+                            if (0xd800..0xdc00).contains(&code)
+                                && self.text[self.position..].starts_with("\\u")
+                            {
+                                self.position += 2;
+                                let low = self.hex()?;
+                                code =
+                                    0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00));
+                            }
+                            string.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(format!("invalid escape at {}", self.position)),
+                    }
+                }
+                c => string.push(c),
+            }
+        }
+    }
+
+    fn hex(&mut self) -> Result<u32, String> {
+        let digits = self
+            .text
+            .get(self.position..self.position + 4)
+            .ok_or("unterminated escape")?;
+        self.position += 4;
+        u32::from_str_radix(digits, 16).map_err(|_| format!("invalid escape at {}", self.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lsp::json::Json;
+
+    #[test]
+    fn json_round_trip_test() {
+        let text = r#"{"id":1,"ok":true,"none":null,"list":[1.5,-2,"a\"b\\c\n"],"empty":{},"nested":{"x":[]}}"#;
+        let json = Json::parse(text).unwrap();
+        assert_eq!(Some(1.0), json.get("id").and_then(Json::as_f64));
+        assert_eq!(
+            Some("a\"b\\c\n"),
+            json.get("list").unwrap().as_array().unwrap()[2].as_str()
+        );
+        assert_eq!(text, json.to_string());
+    }
+
+    #[test]
+    fn json_escapes_test() {
+        let json = Json::parse(r#" [ "\u00e9\ud83e\udd2c\t" , "\u00FC" ] "#).unwrap();
+        assert_eq!(Some("é🤬\t"), json.as_array().unwrap()[0].as_str());
+        assert_eq!(r#"["é🤬\t","ü"]"#, json.to_string());
+    }
+
+    #[test]
+    fn json_errors_test() {
+        for text in ["", "{", "[1,]", "{\"a\" 1}", "\"open", "tru", "1 2"] {
+            assert!(Json::parse(text).is_err(), "{text}");
+        }
+    }
+}
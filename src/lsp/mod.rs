@@ -0,0 +1,532 @@
+//! A language server for `.ys` files, speaking JSON-RPC over stdin and stdout. It publishes
+//! syntax errors and lints as diagnostics, and answers go-to-definition, find-references, hover,
+//! document symbol and completion requests. Documents are synced in full on every change.
+
+pub mod analysis;
+pub mod json;
+
+use crate::ast::Span;
+use crate::linter::{lint, LintOptions};
+use crate::lsp::analysis::{analyze, Analysis, SymbolKind};
+use crate::lsp::json::Json;
+use crate::parser::parse;
+use crate::scanner::TokenKind;
+use crate::scanner::TokenKind::*;
+use crate::table::Table;
+use crate::value::Value;
+use crate::vm::VM;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+
+const METHOD_NOT_FOUND: f64 = -32601.0;
+const PARSE_ERROR: f64 = -32700.0;
+
+/// The largest message body the server accepts, so a bad `Content-Length` cannot make it allocate
+/// without bound.
+pub const MAX_MESSAGE_LENGTH: usize = 64 * 1024 * 1024;
+
+/// Serves requests from `input` until it is closed or the client sends `exit`.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> Result<()> {
+    let mut server = Server::default();
+    while let Some(body) = read_message(&mut input)? {
+        let replies = match Json::parse(&body) {
+            Ok(message) => server.handle(&message),
+            Err(e) => vec![error_response(Json::Null, PARSE_ERROR, &e)],
+        };
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exited {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the body of one `Content-Length` framed message, `None` at the end of the input.
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .context("invalid Content-Length")?,
+            );
+        }
+    }
+
+    let Some(length) = length else {
+        bail!("message without a Content-Length header");
+    };
+    if length > MAX_MESSAGE_LENGTH {
+        bail!("message of {length} bytes is larger than the limit of {MAX_MESSAGE_LENGTH}");
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8(body)?))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()?;
+    Ok(())
+}
+
+fn response(id: Json, result: Json) -> Json {
+    Json::object([("jsonrpc", "2.0".into()), ("id", id), ("result", result)])
+}
+
+fn error_response(id: Json, code: f64, message: &str) -> Json {
+    let error = Json::object([("code", Json::Number(code)), ("message", message.into())]);
+    Json::object([("jsonrpc", "2.0".into()), ("id", id), ("error", error)])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+/// An open document and what is known about it.
+struct Document {
+    source: String,
+    lines: LineIndex,
+    /// `None` while the document does not parse
+    analysis: Option<Analysis>,
+}
+
+impl Document {
+    fn new(source: String) -> Self {
+        let lines = LineIndex::new(&source);
+        let analysis = parse(&source)
+            .ok()
+            .map(|program| analyze(&program, &source));
+        Document {
+            source,
+            lines,
+            analysis,
+        }
+    }
+
+    /// A document that could not be analyzed, which only answers with nothing.
+    fn unanalyzed(source: String) -> Self {
+        let lines = LineIndex::new(&source);
+        Document {
+            source,
+            lines,
+            analysis: None,
+        }
+    }
+
+    fn range(&self, span: Span) -> Json {
+        Json::object([
+            ("start", self.lines.position(&self.source, span.start)),
+            ("end", self.lines.position(&self.source, span.end)),
+        ])
+    }
+
+    fn location(&self, uri: &str, span: Span) -> Json {
+        Json::object([("uri", uri.into()), ("range", self.range(span))])
+    }
+}
+
+/// Converts byte offsets to the line and UTF-16 column positions of the protocol, and back.
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(source: &str) -> Self {
+        let newlines = source.match_indices('\n').map(|(i, _)| i + 1);
+        LineIndex {
+            starts: std::iter::once(0).chain(newlines).collect(),
+        }
+    }
+
+    fn position(&self, source: &str, offset: usize) -> Json {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let character = source[self.starts[line]..offset].encode_utf16().count();
+        Json::object([("line", line.into()), ("character", character.into())])
+    }
+
+    fn offset(&self, source: &str, position: &Json) -> Option<usize> {
+        let line = position.get("line")?.as_f64()? as usize;
+        let character = position.get("character")?.as_f64()? as usize;
+        let start = *self.starts.get(line)?;
+        let end = self
+            .starts
+            .get(line + 1)
+            .map_or(source.len(), |end| end - 1);
+
+        let mut units = 0;
+        for (i, c) in source[start..end].char_indices() {
+            if units >= character {
+                return Some(start + i);
+            }
+            units += c.len_utf16();
+        }
+        Some(end)
+    }
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, Document>,
+    /// set once the client sends `exit`
+    exited: bool,
+}
+
+impl Server {
+    /// Handles one message, returning the response and any notifications to send.
+    fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let id = message.get("id").cloned();
+
+        let result = match method {
+            "initialize" => Some(initialize_result()),
+            "shutdown" => Some(Json::Null),
+            "exit" => {
+                self.exited = true;
+                None
+            }
+            "textDocument/didOpen" => {
+                let uri = params.path(&["textDocument", "uri"]).and_then(Json::as_str);
+                let text = params
+                    .path(&["textDocument", "text"])
+                    .and_then(Json::as_str);
+                if let (Some(uri), Some(text)) = (uri, text) {
+                    return self.update(uri, text.to_string());
+                }
+                None
+            }
+            "textDocument/didChange" => {
+                let uri = params.path(&["textDocument", "uri"]).and_then(Json::as_str);
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                if let (Some(uri), Some(text)) = (uri, text) {
+                    return self.update(uri, text.to_string());
+                }
+                None
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = params.path(&["textDocument", "uri"]).and_then(Json::as_str) {
+                    self.documents.remove(uri);
+                    return vec![publish_diagnostics(uri, Vec::new())];
+                }
+                None
+            }
+            "textDocument/definition" => Some(self.definition(params)),
+            "textDocument/references" => Some(self.references(params)),
+            "textDocument/hover" => Some(self.hover(params)),
+            "textDocument/documentSymbol" => Some(self.document_symbols(params)),
+            "textDocument/completion" => Some(self.completion(params)),
+            _ => {
+                return match id {
+                    Some(id) => vec![error_response(
+                        id,
+                        METHOD_NOT_FOUND,
+                        &format!("Method not found: {method}"),
+                    )],
+                    None => Vec::new(),
+                };
+            }
+        };
+
+        match (id, result) {
+            (Some(id), Some(result)) => vec![response(id, result)],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Stores the new text of a document and publishes its diagnostics. A bug in the parser or a
+    /// lint must not take the server down with it, so a panic while analyzing is reported on the
+    /// document instead.
+    fn update(&mut self, uri: &str, source: String) -> Vec<Json> {
+        let analyzed = panic::catch_unwind(AssertUnwindSafe(|| {
+            let document = Document::new(source.clone());
+            let diagnostics = diagnostics(&document);
+            (document, diagnostics)
+        }));
+        let (document, diagnostics) = match analyzed {
+            Ok(analyzed) => analyzed,
+            Err(panic) => {
+                let reason = panic
+                    .downcast_ref::<&str>()
+                    .map(|reason| reason.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                let document = Document::unanalyzed(source);
+                let diagnostic = Json::object([
+                    ("range", document.range(Span::default())),
+                    ("severity", 1usize.into()),
+                    ("source", "yellowstone".into()),
+                    (
+                        "message",
+                        format!("internal error analyzing the document: {reason}").into(),
+                    ),
+                ]);
+                (document, vec![diagnostic])
+            }
+        };
+        self.documents.insert(uri.to_string(), document);
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    /// The document the request is about, and the byte offset of its position.
+    fn target<'a>(&'a self, params: &'a Json) -> Option<(&'a str, &'a Document, usize)> {
+        let uri = params.path(&["textDocument", "uri"])?.as_str()?;
+        let document = self.documents.get(uri)?;
+        let offset = document
+            .lines
+            .offset(&document.source, params.get("position")?)?;
+        Some((uri, document, offset))
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let Some((uri, document, offset)) = self.target(params) else {
+            return Json::Null;
+        };
+        let Some(analysis) = &document.analysis else {
+            return Json::Null;
+        };
+        match analysis.symbol_at(offset) {
+            Some(symbol) => document.location(uri, analysis.symbols[symbol].name_span),
+            None => Json::Null,
+        }
+    }
+
+    fn references(&self, params: &Json) -> Json {
+        let Some((uri, document, offset)) = self.target(params) else {
+            return Json::Null;
+        };
+        let Some(analysis) = &document.analysis else {
+            return Json::Null;
+        };
+        let Some(symbol) = analysis.symbol_at(offset) else {
+            return Json::Array(Vec::new());
+        };
+
+        let include_declaration = params
+            .path(&["context", "includeDeclaration"])
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        let mut spans: Vec<Span> = analysis.references_to(symbol).collect();
+        if include_declaration {
+            spans.push(analysis.symbols[symbol].name_span);
+        }
+        spans.sort_by_key(|span| span.start);
+
+        let locations = spans
+            .into_iter()
+            .map(|span| document.location(uri, span))
+            .collect();
+        Json::Array(locations)
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let Some((_, document, offset)) = self.target(params) else {
+            return Json::Null;
+        };
+        let Some(analysis) = &document.analysis else {
+            return Json::Null;
+        };
+
+        let text = match analysis.symbol_at(offset) {
+            Some(symbol) => {
+                let symbol = &analysis.symbols[symbol];
+                match symbol.kind {
+                    SymbolKind::Global => {
+                        format!("(global) var {}: {}", symbol.name, symbol.inferred)
+                    }
+                    SymbolKind::Local => {
+                        format!("(local) var {}: {}", symbol.name, symbol.inferred)
+                    }
+                    SymbolKind::Module => format!("(module) {}", symbol.name),
+                }
+            }
+            None => {
+                let Some(name) = analysis.unresolved_at(offset) else {
+                    return Json::Null;
+                };
+                match predefined().get(name) {
+                    Some(value) if Value::is_native(value) => format!("(native) fn {name}"),
+                    Some(value) if value.is_number() => format!("(native) var {name}: number"),
+                    Some(_) => format!("(native) {name}"),
+                    None => format!("{name}: undeclared"),
+                }
+            }
+        };
+
+        let contents = Json::object([
+            ("kind", "markdown".into()),
+            ("value", format!("```yellowstone\n{text}\n```").into()),
+        ]);
+        Json::object([("contents", contents)])
+    }
+
+    fn document_symbols(&self, params: &Json) -> Json {
+        let document = params
+            .path(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .and_then(|uri| self.documents.get(uri));
+        let Some(analysis) = document.and_then(|document| document.analysis.as_ref()) else {
+            return Json::Array(Vec::new());
+        };
+        let document = document.unwrap();
+
+        let symbols = analysis
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind != SymbolKind::Local)
+            .map(|symbol| {
+                // The protocol's SymbolKind, 2 is a module and 13 a variable.
+                let kind: usize = match symbol.kind {
+                    SymbolKind::Module => 2,
+                    _ => 13,
+                };
+                Json::object([
+                    ("name", symbol.name.clone().into()),
+                    ("detail", symbol.inferred.into()),
+                    ("kind", kind.into()),
+                    ("range", document.range(symbol.span)),
+                    ("selectionRange", document.range(symbol.name_span)),
+                ])
+            })
+            .collect();
+        Json::Array(symbols)
+    }
+
+    /// Offers the keywords, the document's globals and the standard library.
+    fn completion(&self, params: &Json) -> Json {
+        // The protocol's CompletionItemKind, 14 is a keyword, 6 a variable and 3 a function.
+        let mut items: Vec<Json> = TokenKind::KEYWORDS
+            .into_iter()
+            // Reserved, but not part of the grammar yet.
+            .filter(|kind| {
+                !matches!(
+                    kind,
+                    TOKEN_CLASS | TOKEN_FUN | TOKEN_RETURN | TOKEN_SUPER | TOKEN_THIS
+                )
+            })
+            .filter_map(TokenKind::keyword)
+            .map(|keyword| completion_item(keyword, 14, "keyword"))
+            .collect();
+
+        let analysis = params
+            .path(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .and_then(|uri| self.documents.get(uri))
+            .and_then(|document| document.analysis.as_ref());
+        if let Some(analysis) = analysis {
+            let mut seen = Vec::new();
+            for symbol in &analysis.symbols {
+                if symbol.kind != SymbolKind::Local && !seen.contains(&&symbol.name) {
+                    seen.push(&symbol.name);
+                    items.push(completion_item(&symbol.name, 6, symbol.inferred));
+                }
+            }
+        }
+
+        let predefined = predefined();
+        let mut natives: Vec<_> = predefined
+            .entries
+            .iter()
+            .flatten()
+            .filter(|entry| !entry.is_tombstone)
+            .collect();
+        natives.sort_by(|a, b| a.key.cmp(&b.key));
+        for entry in natives {
+            if Value::is_native(&entry.value) {
+                items.push(completion_item(&entry.key, 3, "native function"));
+            } else {
+                items.push(completion_item(&entry.key, 6, "number"));
+            }
+        }
+        Json::Array(items)
+    }
+}
+
+/// The globals every program starts with.
+fn predefined() -> Table {
+    VM::default().table
+}
+
+fn completion_item(label: &str, kind: usize, detail: &str) -> Json {
+    Json::object([
+        ("label", label.into()),
+        ("kind", kind.into()),
+        ("detail", detail.into()),
+    ])
+}
+
+fn initialize_result() -> Json {
+    let capabilities = Json::object([
+        // Full document sync.
+        ("textDocumentSync", 1usize.into()),
+        ("definitionProvider", true.into()),
+        ("referencesProvider", true.into()),
+        ("hoverProvider", true.into()),
+        ("documentSymbolProvider", true.into()),
+        ("completionProvider", Json::object([])),
+    ]);
+    Json::object([
+        ("capabilities", capabilities),
+        ("serverInfo", Json::object([("name", "yellowstone".into())])),
+    ])
+}
+
+/// Syntax errors, or the lints when the document parses.
+fn diagnostics(document: &Document) -> Vec<Json> {
+    let diagnostic = |span: Span, severity: usize, message: String, code: Option<&str>| {
+        let mut members = vec![
+            ("range".to_string(), document.range(span)),
+            ("severity".to_string(), severity.into()),
+            ("source".to_string(), "yellowstone".into()),
+            ("message".to_string(), message.into()),
+        ];
+        if let Some(code) = code {
+            members.push(("code".to_string(), code.into()));
+        }
+        Json::Object(members)
+    };
+
+    match parse(&document.source) {
+        // The protocol's severity, 1 is an error and 2 a warning.
+        Err(errors) => errors
+            .into_iter()
+            .map(|error| diagnostic(error.span, 1, error.message, None))
+            .collect(),
+        Ok(_) => lint(&document.source, &LintOptions::default())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|lint| diagnostic(lint.span, 2, lint.message, Some(lint.lint.name())))
+            .collect(),
+    }
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    notification(
+        "textDocument/publishDiagnostics",
+        Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]),
+    )
+}
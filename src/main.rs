@@ -2,74 +2,405 @@
 extern crate core;
 
 use crate::chunk::Chunk;
-use crate::debug::disassemble_chunk;
-use crate::vm::VM;
+use crate::compiler::{compile_with_options, CompileOptions};
+use crate::debug::disassemble;
+use crate::debugger::Debugger;
+use crate::error::InterpretError;
+use crate::linter::{Lint, LintOptions};
+use crate::profiler::Profile;
+use crate::repl::repl;
+use crate::test_runner::TestOptions;
+use crate::verifier::verify;
+use crate::vm::{load_chunk, VM};
 use std::env;
 use std::fs;
-use std::io::Write;
-use std::io::stdout;
+use std::io::{stderr, stdin, stdout, Read};
+use std::path::{Path, PathBuf};
+use std::process::exit;
 
+mod assembler;
+mod ast;
 mod chunk;
+mod codegen;
 mod compiler;
 mod debug;
+mod debugger;
 mod error;
+mod formatter;
+mod limits;
+mod linter;
+mod lsp;
+mod optimizer;
+mod parser;
+mod profiler;
+mod repl;
 mod scanner;
+mod serialize;
+mod stdlib;
 mod table;
+mod test_runner;
 mod util;
 mod value;
+mod verifier;
 mod vm;
 
+const USAGE: &str = "\
+Usage: yellowstone [options] [run] <path> [arguments]...   run a script, `-` reads it from stdin
+       yellowstone [options] -e <code> [arguments]...      run code given on the command line
+       yellowstone [options] repl                          start the interactive prompt
+       yellowstone [options] check <path>...               compile scripts without running them
+       yellowstone [options] disasm <path>                 show the bytecode of a script
+       yellowstone [options] compile <path> [output]       write a script's bytecode to a .ysc file
+       yellowstone [options] test [--filter text] [path]...  run the tests in scripts and directories
+       yellowstone fmt [--check] <path>...                 format scripts
+       yellowstone lint [--allow lint]... <path>...        lint scripts
+       yellowstone debug <path>                            run a script under the debugger
+
+Options:
+  --no-optimize              run the code exactly as the compiler emits it
+  --print-code               print the compiled code
+  --trace                    print every instruction as it runs
+  --profile                  print where the time went when the script ends
+  --profile-stacks <output>  also write the profile as collapsed stacks for flamegraphs
+
+Arguments after the script are read by it with `arg_count()` and `arg(index)`.";
+
+// Exit codes follow the BSD `sysexits.h` convention.
+/// the command line was wrong
+const EXIT_USAGE: i32 = 64;
+/// the script did not compile or its bytecode is malformed
+const EXIT_COMPILE_ERROR: i32 = 65;
+/// the script or another input file could not be read
+const EXIT_NO_INPUT: i32 = 66;
+/// the script stopped with a runtime error
+const EXIT_RUNTIME_ERROR: i32 = 70;
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let vm = VM::default();
+    let args: Vec<String> = env::args().skip(1).collect();
+    exit(run(args));
+}
+
+/// Runs the command in `args`, the command line without the program name, and returns the exit
+/// code.
+fn run(mut args: Vec<String>) -> i32 {
+    match args.first().map(String::as_str) {
+        Some("fmt") => {
+            let check = args.iter().any(|arg| arg == "--check");
+            let paths: Vec<&String> = args[1..].iter().filter(|arg| *arg != "--check").collect();
+            return if format_files(&paths, check) { 0 } else { 1 };
+        }
+        Some("lint") => return if lint_files(&args[1..]) { 0 } else { 1 },
+        Some("debug") if args.len() == 2 => return debug_file(VM::default(), &args[1]),
+        Some("help" | "--help" | "-h") => {
+            println!("{USAGE}");
+            return 0;
+        }
+        _ => {}
+    }
+
+    // Options come before the command, anything after the script belongs to the script.
+    let mut vm = VM::default();
+    let mut profile_stacks = None;
+    while let Some(option) = args.first().filter(|arg| arg.starts_with("--")) {
+        match option.as_str() {
+            "--no-optimize" => vm.compile_options.optimize = false,
+            "--print-code" => vm.compile_options.print_code = true,
+            "--trace" => vm.trace(stderr()),
+            "--profile" => vm.profile = Some(Profile::default()),
+            "--profile-stacks" if args.len() > 1 => {
+                vm.profile = Some(Profile::default());
+                profile_stacks = Some(args.remove(1));
+            }
+            _ => return usage_error(&format!("unknown option {option}")),
+        }
+        args.remove(0);
+    }
 
-    match args.len() {
-        1 => repl(vm),
-        2 => run_file(vm, &args[1]),
+    let Some(command) = args.first().cloned() else {
+        repl(vm);
+        return 0;
+    };
+    let rest = args.split_off(1);
+    match command.as_str() {
+        "repl" if rest.is_empty() => {
+            repl(vm);
+            0
+        }
+        "check" if !rest.is_empty() => check_files(&rest, vm.compile_options),
+        "disasm" if rest.len() == 1 => disassemble_file(&rest[0], vm.compile_options),
+        "compile" if matches!(rest.len(), 1 | 2) => {
+            compile_file(&rest[0], rest.get(1), vm.compile_options)
+        }
+        "test" => test_files(rest, vm.compile_options),
+        "-e" => match rest.split_first() {
+            Some((code, arguments)) => {
+                vm.args = arguments.to_vec();
+                let result = vm.interpret(code);
+                finish(vm, result, profile_stacks.as_ref())
+            }
+            None => usage_error("-e needs the code to run"),
+        },
+        "run" => match rest.split_first() {
+            Some((path, arguments)) => {
+                vm.args = arguments.to_vec();
+                run_file(vm, path, profile_stacks.as_ref())
+            }
+            None => usage_error("run needs a script"),
+        },
+        "repl" | "check" | "disasm" | "compile" => {
+            usage_error(&format!("wrong arguments for {command}"))
+        }
         _ => {
-            println!("Usage: clox [path]")
+            vm.args = rest;
+            run_file(vm, &command, profile_stacks.as_ref())
         }
     }
 }
 
-fn run_file(mut vm: VM, path: &String) {
-    let source =
-        fs::read_to_string(path).expect(format!("The file at {path} does not exist").as_str());
+fn usage_error(message: &str) -> i32 {
+    eprintln!("error: {message}\n\n{USAGE}");
+    EXIT_USAGE
+}
+
+/// The exit code for a script that stopped with `error`. Bytecode that cannot be decoded is a
+/// compile error like bytecode that fails verification, only I/O errors mean the input is missing.
+fn exit_code(error: &anyhow::Error) -> i32 {
+    let cause = error.root_cause();
+    match cause.downcast_ref::<InterpretError>() {
+        Some(InterpretError::COMPILE_ERROR | InterpretError::VERIFY_ERROR) => EXIT_COMPILE_ERROR,
+        Some(_) => EXIT_RUNTIME_ERROR,
+        None if cause.is::<serialize::FormatError>() => EXIT_COMPILE_ERROR,
+        None => EXIT_NO_INPUT,
+    }
+}
 
-    println!("{}", source);
-    let result = vm.interpret(&source);
+/// Runs the script at `path`, or the one read from stdin when it is `-`.
+fn run_file(mut vm: VM, path: &String, profile_stacks: Option<&String>) -> i32 {
+    let result = if path == "-" {
+        let mut source = String::new();
+        match stdin().read_to_string(&mut source) {
+            Ok(_) => vm.interpret(&source),
+            Err(e) => {
+                eprintln!("error: cannot read stdin: {e}");
+                return EXIT_NO_INPUT;
+            }
+        }
+    } else {
+        vm.interpret_file(Path::new(path))
+    };
+    finish(vm, result, profile_stacks)
+}
 
-    match result {
+/// Reports how a script ended and returns the exit code. When the VM is profiling, the report is
+/// printed to stderr and the collapsed stacks are written to `profile_stacks` if it is given.
+fn finish(
+    mut vm: VM,
+    result: anyhow::Result<Option<value::Value>>,
+    profile_stacks: Option<&String>,
+) -> i32 {
+    let code = match &result {
+        Ok(_) => 0,
         Err(e) => {
-            println!("{:?}", e)
+            eprintln!("{e:?}");
+            exit_code(e)
         }
-        Ok(_) => {}
-    }
+    };
 
+    if let Some(profile) = vm.profile.take() {
+        eprint!("{}", profile.report(10));
+        if let Some(output) = profile_stacks {
+            if let Err(e) = fs::write(output, profile.collapsed()) {
+                eprintln!("error: cannot write {output}: {e}");
+            }
+        }
+    }
     vm.free_objects();
+    code
 }
 
-fn repl(mut vm: VM) {
-    println!("[yellowstone repl]");
-    println!("(type `exit` or `quit` to stop session)");
+/// Runs the script at `path` under the debugger, which takes its commands from stdin.
+fn debug_file(mut vm: VM, path: &String) -> i32 {
+    println!("[yellowstone debugger]");
+    println!("(type `help` for the commands)");
+    vm.hook = Some(Box::new(Debugger::new(stdin().lock(), stdout())));
 
-    let mut line = String::new();
-    loop {
-        print!(">> ");
-        let _ = stdout().flush(); // Flushed the input buffering. Used for formatting.
+    let result = vm.interpret_file(Path::new(path));
+    finish(vm, result, None)
+}
 
-        // request std input stream and print result
-        match std::io::stdin().read_line(&mut line) {
-            Ok(_) => match line.as_str() {
-                "exit" | "Exit" | "Quit" | "quit" | "q" => break,
-                _ => {}
-            },
-            Err(error) => println!("> error: {error}"),
+/// Compiles and verifies each script without running it, reporting the ones that fail.
+fn check_files(paths: &[String], options: CompileOptions) -> i32 {
+    let mut code = 0;
+    for path in paths {
+        if let Err(e) = load_chunk(Path::new(path), options).and_then(|chunk| verify(&chunk)) {
+            eprintln!("{path}: {e:#}");
+            code = code.max(exit_code(&e));
         }
+    }
+    code
+}
 
-        let result = &vm.interpret(&line);
-        line.clear(); // clear buffer for next repl
+/// Prints the listing of the script at `path`, source code or a `.ysc` file.
+fn disassemble_file(path: &String, options: CompileOptions) -> i32 {
+    match load_chunk(Path::new(path), options) {
+        Ok(chunk) => {
+            print!("{}", disassemble(&chunk, path));
+            0
+        }
+        Err(e) => {
+            eprintln!("{e:?}");
+            exit_code(&e)
+        }
     }
+}
 
-    vm.free_objects();
+/// Compiles the script at `path` to a `.ysc` file, next to it unless `output` is given.
+fn compile_file(path: &String, output: Option<&String>, options: CompileOptions) -> i32 {
+    let output = match output {
+        Some(output) => PathBuf::from(output),
+        None => Path::new(path).with_extension(serialize::EXTENSION),
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error: cannot read {path}: {e}");
+            return EXIT_NO_INPUT;
+        }
+    };
+    match compile_with_options(&source, options).and_then(|chunk| chunk.serialize()) {
+        Err(e) => {
+            eprintln!("{e:?}");
+            exit_code(&e)
+        }
+        Ok(bytes) => match fs::write(&output, bytes) {
+            Err(e) => {
+                eprintln!("error: cannot write {}: {e}", output.display());
+                EXIT_NO_INPUT
+            }
+            Ok(_) => {
+                println!("compiled {path} to {}", output.display());
+                0
+            }
+        },
+    }
+}
+
+/// Runs the tests in `args`, files and directories to search for `*_test.ys` files, the working
+/// directory if there are none. `--filter text` only runs the tests whose name contains `text`.
+fn test_files(mut args: Vec<String>, compile_options: CompileOptions) -> i32 {
+    let mut options = TestOptions {
+        filter: None,
+        compile_options,
+    };
+    if let Some(index) = args.iter().position(|arg| arg == "--filter") {
+        if index + 1 == args.len() {
+            return usage_error("--filter needs the text to look for");
+        }
+        options.filter = Some(args.remove(index + 1));
+        args.remove(index);
+    }
+    let mut paths: Vec<PathBuf> = args.iter().map(PathBuf::from).collect();
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+
+    match test_runner::run(&paths, &options, &mut stdout()) {
+        Ok(summary) if summary.is_ok() => 0,
+        Ok(_) => 1,
+        Err(e) => {
+            eprintln!("error: cannot find the tests: {e}");
+            EXIT_NO_INPUT
+        }
+    }
+}
+
+/// Rewrites each file in the canonical style. With `check` the files are left alone and the ones
+/// that are not formatted are listed. Returns false if any file failed to parse or, when checking,
+/// was not formatted.
+fn format_files(paths: &[&String], check: bool) -> bool {
+    let mut ok = true;
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                println!("> error: cannot read {path}: {e}");
+                ok = false;
+                continue;
+            }
+        };
+
+        let formatted = match formatter::format(&source) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{path}: {error}");
+                }
+                ok = false;
+                continue;
+            }
+        };
+
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{path} is not formatted");
+            ok = false;
+        } else if let Err(e) = fs::write(path, formatted) {
+            println!("> error: cannot write {path}: {e}");
+            ok = false;
+        }
+    }
+    ok
+}
+
+/// Lints each file, `--allow name` turns a lint off. Returns false if any file failed to parse or
+/// had a diagnostic.
+fn lint_files(args: &[String]) -> bool {
+    let mut options = LintOptions::default();
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg != "--allow" {
+            paths.push(arg);
+            continue;
+        }
+        match args.next().and_then(|name| Lint::from_name(name)) {
+            Some(lint) => options.allow(lint),
+            None => {
+                let names: Vec<&str> = Lint::ALL.iter().map(|lint| lint.name()).collect();
+                println!("> error: --allow expects one of {}", names.join(", "));
+                return false;
+            }
+        }
+    }
+
+    let mut ok = true;
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                println!("> error: cannot read {path}: {e}");
+                ok = false;
+                continue;
+            }
+        };
+
+        match linter::lint(&source, &options) {
+            Ok(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    println!("{path}: {diagnostic}");
+                }
+                ok &= diagnostics.is_empty();
+            }
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{path}: {error}");
+                }
+                ok = false;
+            }
+        }
+    }
+    ok
 }
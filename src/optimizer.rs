@@ -0,0 +1,469 @@
+//! Rewrites a compiled chunk into a shorter one that does the same thing. The compiler emits code
+//! as it reads tokens, so `1 + 2` becomes two constants and an `OP_ADD`, and `a != b` becomes
+//! `OP_EQUAL, OP_NOT`. The passes here work on a list of instructions whose jumps point at other
+//! instructions instead of carrying an amount, so removing an instruction never breaks a jump.
+
+use crate::chunk::Chunk;
+use crate::chunk::OpCode::{self, *};
+use crate::value::Value;
+use crate::verifier::verify;
+use std::cmp::Ordering;
+
+/// One instruction of the chunk being optimized. Jumps keep the index of the instruction they
+/// land on in `target`, their `OP_JUMP_AMOUNT` is rebuilt when the chunk is written back.
+struct Instruction {
+    op: OpCode,
+    line: usize,
+    target: Option<usize>,
+}
+
+/// Optimizes `chunk` in place, running every pass until none of them finds anything left to do.
+/// The constant table is not touched because locals live in it. Chunks that fail verification
+/// are left alone, the VM refuses to run them anyway.
+pub fn optimize(chunk: &mut Chunk) {
+    if verify(chunk).is_err() {
+        return;
+    }
+
+    let mut code = decode(chunk);
+    loop {
+        let changed = fold_constants(&mut code)
+            | resolve_constant_jumps(&mut code)
+            | invert_jumps(&mut code)
+            | fuse_comparisons(&mut code)
+            | remove_dead_code(&mut code);
+        if !changed {
+            break;
+        }
+    }
+    encode(chunk, code);
+}
+
+fn is_jump(op: &OpCode) -> bool {
+    matches!(op, OP_JUMP | OP_JUMP_IF_FALSE | OP_JUMP_IF_TRUE | OP_LOOP)
+}
+
+fn decode(chunk: &Chunk) -> Vec<Instruction> {
+    let code = &chunk.code;
+    let mut offsets = Vec::new();
+    let mut index_at = vec![0; code.len()];
+    let mut offset = 0;
+    while offset < code.len() {
+        index_at[offset] = offsets.len();
+        offsets.push(offset);
+        offset += if is_jump(&code[offset]) { 2 } else { 1 };
+    }
+
+    offsets
+        .into_iter()
+        .map(|offset| {
+            let op = code[offset].clone();
+            let target = match (&op, code.get(offset + 1)) {
+                (OP_LOOP, Some(OP_JUMP_AMOUNT(amount))) => Some(index_at[offset + 2 - amount]),
+                (op, Some(OP_JUMP_AMOUNT(amount))) if is_jump(op) => {
+                    Some(index_at[offset + 2 + amount])
+                }
+                _ => None,
+            };
+            Instruction {
+                op,
+                line: chunk.line(offset),
+                target,
+            }
+        })
+        .collect()
+}
+
+fn encode(chunk: &mut Chunk, code: Vec<Instruction>) {
+    let mut offsets = Vec::with_capacity(code.len());
+    let mut offset = 0;
+    for instruction in &code {
+        offsets.push(offset);
+        offset += if instruction.target.is_some() { 2 } else { 1 };
+    }
+
+    let mut optimized = Chunk::default();
+    for (index, instruction) in code.into_iter().enumerate() {
+        let next = offsets[index] + 2;
+        let amount = instruction.target.map(|target| match instruction.op {
+            OP_LOOP => next - offsets[target],
+            _ => offsets[target] - next,
+        });
+        optimized.write_chunk(instruction.op, instruction.line);
+        if let Some(amount) = amount {
+            optimized.write_chunk(OP_JUMP_AMOUNT(amount), instruction.line);
+        }
+    }
+    chunk.code = optimized.code;
+    chunk.lines = optimized.lines;
+}
+
+/// Which instructions some jump lands on. Patterns may start on such an instruction but must not
+/// span one, since code arriving from the jump skips the start of the pattern.
+fn jump_targets(code: &[Instruction]) -> Vec<bool> {
+    let mut targets = vec![false; code.len()];
+    for target in code.iter().filter_map(|instruction| instruction.target) {
+        targets[target] = true;
+    }
+    targets
+}
+
+/// Drops the instructions not marked in `keep`. Jumps to a dropped instruction land on the next
+/// kept one instead.
+fn retain(code: &mut Vec<Instruction>, keep: &[bool]) {
+    let mut new_index = Vec::with_capacity(keep.len());
+    let mut kept = 0;
+    for &kept_here in keep {
+        new_index.push(kept);
+        kept += kept_here as usize;
+    }
+
+    let old = std::mem::take(code);
+    *code = old
+        .into_iter()
+        .zip(keep)
+        .filter(|(_, &kept_here)| kept_here)
+        .map(|(mut instruction, _)| {
+            instruction.target = instruction.target.map(|target| new_index[target]);
+            instruction
+        })
+        .collect();
+}
+
+/// The value pushed by an instruction that always pushes the same thing. Strings are left out,
+/// the VM allocates and tracks them at runtime.
+fn literal(op: &OpCode) -> Option<Value> {
+    match op {
+        OP_CONSTANT(value) if value.is_number() => Some(value.clone()),
+        OP_TRUE => Some(Value::bool_val(true)),
+        OP_FALSE => Some(Value::bool_val(false)),
+        OP_NIL => Some(Value::nil_value()),
+        _ => None,
+    }
+}
+
+fn push_literal(value: Value) -> OpCode {
+    if value.is_nil() {
+        OP_NIL
+    } else if let Ok(b) = value.as_bool() {
+        if b {
+            OP_TRUE
+        } else {
+            OP_FALSE
+        }
+    } else {
+        OP_CONSTANT(value)
+    }
+}
+
+// nil and false are falsey and every other value behaves like true, same as the VM
+fn is_falsey(value: &Value) -> bool {
+    value.is_nil() || value.as_bool() == Ok(false)
+}
+
+/// What `a op b` evaluates to, if it cannot fail at runtime.
+fn fold_binary(a: &OpCode, b: &OpCode, op: &OpCode) -> Option<OpCode> {
+    let (a, b) = (literal(a)?, literal(b)?);
+    let value = match op {
+        OP_EQUAL => Value::bool_val(Value::values_equal(a, b)),
+        OP_NOT_EQUAL => Value::bool_val(!Value::values_equal(a, b)),
+        _ => {
+            let (a, b) = (a.as_number().ok()?, b.as_number().ok()?);
+            match op {
+                OP_ADD => Value::number_value(a + b),
+                OP_SUBTRACT => Value::number_value(a - b),
+                OP_MULTIPLY => Value::number_value(a * b),
+                OP_DIVIDE => Value::number_value(a / b),
+                OP_MODULO => Value::number_value(a % b),
+                OP_POWER => Value::number_value(a.powf(b)),
+                OP_GREATER => Value::bool_val(a > b),
+                OP_LESS => Value::bool_val(a < b),
+                OP_GREATER_EQUAL => Value::bool_val(a.partial_cmp(&b) != Some(Ordering::Less)),
+                OP_LESS_EQUAL => Value::bool_val(a.partial_cmp(&b) != Some(Ordering::Greater)),
+                _ => return None,
+            }
+        }
+    };
+    Some(push_literal(value))
+}
+
+/// What `op a` evaluates to, if it cannot fail at runtime.
+fn fold_unary(a: &OpCode, op: &OpCode) -> Option<OpCode> {
+    let a = literal(a)?;
+    match op {
+        OP_NEGATE => Some(OP_CONSTANT(Value::number_value(-a.as_number().ok()?))),
+        // OP_NOT fails on numbers.
+        OP_NOT if !a.is_number() => Some(push_literal(Value::bool_val(is_falsey(&a)))),
+        _ => None,
+    }
+}
+
+/// Evaluates operators whose operands are all literals, and drops literals that are popped right
+/// after being pushed. Operations that would fail at runtime are kept so the error still happens.
+fn fold_constants(code: &mut Vec<Instruction>) -> bool {
+    let targets = jump_targets(code);
+    let mut keep = vec![true; code.len()];
+    let mut changed = false;
+
+    let mut i = 0;
+    while i < code.len() {
+        if i + 2 < code.len() && !targets[i + 1] && !targets[i + 2] {
+            if let Some(op) = fold_binary(&code[i].op, &code[i + 1].op, &code[i + 2].op) {
+                code[i].op = op;
+                keep[i + 1] = false;
+                keep[i + 2] = false;
+                changed = true;
+                i += 3;
+                continue;
+            }
+        }
+        if i + 1 < code.len() && !targets[i + 1] {
+            if matches!(code[i + 1].op, OP_POP) && literal(&code[i].op).is_some() {
+                keep[i] = false;
+                keep[i + 1] = false;
+                changed = true;
+                i += 2;
+                continue;
+            }
+            if let Some(op) = fold_unary(&code[i].op, &code[i + 1].op) {
+                code[i].op = op;
+                keep[i + 1] = false;
+                changed = true;
+                i += 2;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    retain(code, &keep);
+    changed
+}
+
+/// A conditional jump right after a literal always goes the same way: it becomes an `OP_JUMP` or
+/// is dropped, and whatever it skipped over is left for `remove_dead_code`.
+fn resolve_constant_jumps(code: &mut Vec<Instruction>) -> bool {
+    let targets = jump_targets(code);
+    let mut keep = vec![true; code.len()];
+    let mut changed = false;
+
+    for i in 1..code.len() {
+        let jumps_if_falsey = match code[i].op {
+            OP_JUMP_IF_FALSE => true,
+            OP_JUMP_IF_TRUE => false,
+            _ => continue,
+        };
+        let Some(condition) = literal(&code[i - 1].op) else {
+            continue;
+        };
+        if targets[i] {
+            continue;
+        }
+        if is_falsey(&condition) == jumps_if_falsey {
+            code[i].op = OP_JUMP;
+        } else {
+            keep[i] = false;
+        }
+        changed = true;
+    }
+
+    retain(code, &keep);
+    changed
+}
+
+/// `OP_NOT, OP_JUMP_IF_FALSE` becomes `OP_JUMP_IF_TRUE` when both ways out of the jump pop the
+/// condition, so nothing can tell it was never negated. The operand of the `OP_NOT` has to be a
+/// boolean, dropping an `OP_NOT` on a number would hide the error it raises.
+fn invert_jumps(code: &mut Vec<Instruction>) -> bool {
+    let targets = jump_targets(code);
+    let mut keep = vec![true; code.len()];
+    let mut changed = false;
+
+    for i in 1..code.len().saturating_sub(2) {
+        let pushes_bool = matches!(
+            code[i - 1].op,
+            OP_TRUE
+                | OP_FALSE
+                | OP_NOT
+                | OP_EQUAL
+                | OP_NOT_EQUAL
+                | OP_GREATER
+                | OP_GREATER_EQUAL
+                | OP_LESS
+                | OP_LESS_EQUAL
+        );
+        let (OP_NOT, OP_JUMP_IF_FALSE, Some(target)) =
+            (&code[i].op, &code[i + 1].op, code[i + 1].target)
+        else {
+            continue;
+        };
+        let condition_popped =
+            matches!(code[i + 2].op, OP_POP) && matches!(code[target].op, OP_POP);
+        if pushes_bool && condition_popped && !targets[i] && !targets[i + 1] {
+            code[i + 1].op = OP_JUMP_IF_TRUE;
+            keep[i] = false;
+            changed = true;
+        }
+    }
+
+    retain(code, &keep);
+    changed
+}
+
+/// Replaces a comparison followed by `OP_NOT` with the single opcode computing both.
+fn fuse_comparisons(code: &mut Vec<Instruction>) -> bool {
+    let targets = jump_targets(code);
+    let mut keep = vec![true; code.len()];
+    let mut changed = false;
+
+    for i in 0..code.len().saturating_sub(1) {
+        if !matches!(code[i + 1].op, OP_NOT) || targets[i + 1] {
+            continue;
+        }
+        let fused = match code[i].op {
+            OP_EQUAL => OP_NOT_EQUAL,
+            OP_LESS => OP_GREATER_EQUAL,
+            OP_GREATER => OP_LESS_EQUAL,
+            _ => continue,
+        };
+        code[i].op = fused;
+        keep[i + 1] = false;
+        changed = true;
+    }
+
+    retain(code, &keep);
+    changed
+}
+
+/// Drops instructions no path through the chunk reaches, such as the ones an unconditional jump
+/// skips, and jumps to the instruction right after them. The final `OP_RETURN` always stays.
+fn remove_dead_code(code: &mut Vec<Instruction>) -> bool {
+    let mut reachable = vec![false; code.len()];
+    let mut pending = vec![0];
+    while let Some(i) = pending.pop() {
+        if i >= code.len() || reachable[i] {
+            continue;
+        }
+        reachable[i] = true;
+        match code[i].op {
+            OP_RETURN => {}
+            OP_JUMP | OP_LOOP => pending.extend(code[i].target),
+            OP_JUMP_IF_FALSE | OP_JUMP_IF_TRUE => {
+                pending.extend(code[i].target);
+                pending.push(i + 1);
+            }
+            _ => pending.push(i + 1),
+        }
+    }
+
+    let last = code.len() - 1;
+    let keep: Vec<bool> = (0..code.len())
+        .map(|i| {
+            let jumps_to_next = matches!(code[i].op, OP_JUMP) && code[i].target == Some(i + 1);
+            i == last || (reachable[i] && !jumps_to_next)
+        })
+        .collect();
+
+    let changed = keep.contains(&false);
+    retain(code, &keep);
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk::OpCode::{self, *};
+    use crate::compiler::{compile, compile_with_options, CompileOptions};
+    use crate::verifier::verify;
+
+    fn optimized(source: &str) -> Chunk {
+        let chunk = compile(&source.to_string()).unwrap();
+        verify(&chunk).unwrap();
+        chunk
+    }
+
+    fn has(chunk: &Chunk, matcher: fn(&OpCode) -> bool) -> bool {
+        chunk.code.iter().any(matcher)
+    }
+
+    #[test]
+    fn optimizer_folds_arithmetic_test() {
+        let chunk = optimized("1 + 2 * 3 - -4");
+        assert_eq!(2, chunk.code.len());
+        match &chunk.code[0] {
+            OP_CONSTANT(value) => assert_eq!(Ok(11.), value.as_number()),
+            op => panic!("expected a constant, found {op:?}"),
+        }
+    }
+
+    #[test]
+    fn optimizer_folds_comparisons_test() {
+        let chunk = optimized("2 >= 3");
+        assert!(matches!(chunk.code[..], [OP_FALSE, OP_RETURN]));
+
+        let chunk = optimized("nil == false");
+        assert!(matches!(chunk.code[..], [OP_FALSE, OP_RETURN]));
+
+        let chunk = optimized("!nil");
+        assert!(matches!(chunk.code[..], [OP_TRUE, OP_RETURN]));
+    }
+
+    #[test]
+    fn optimizer_keeps_failing_operations_test() {
+        assert!(has(&optimized("1 + true"), |op| matches!(op, OP_ADD)));
+        assert!(has(&optimized("!5"), |op| matches!(op, OP_NOT)));
+        assert!(has(&optimized("-\"yellow\""), |op| matches!(op, OP_NEGATE)));
+    }
+
+    #[test]
+    fn optimizer_fuses_comparisons_test() {
+        let chunk = optimized("var a = 1; a != 2; a <= 2; a >= 2;");
+        assert!(has(&chunk, |op| matches!(op, OP_NOT_EQUAL)));
+        assert!(has(&chunk, |op| matches!(op, OP_LESS_EQUAL)));
+        assert!(has(&chunk, |op| matches!(op, OP_GREATER_EQUAL)));
+        assert!(!has(&chunk, |op| matches!(op, OP_NOT)));
+    }
+
+    #[test]
+    fn optimizer_inverts_jumps_test() {
+        let chunk = optimized("var a = 1; if (!(a == 2)) { a = 3; }");
+        assert!(has(&chunk, |op| matches!(op, OP_JUMP_IF_TRUE)));
+        assert!(!has(&chunk, |op| matches!(op, OP_NOT | OP_NOT_EQUAL)));
+
+        // `a` might be a number, the OP_NOT has to stay to raise the error.
+        let chunk = optimized("var a = 1; if (!a) { a = 3; }");
+        assert!(has(&chunk, |op| matches!(op, OP_NOT)));
+        assert!(!has(&chunk, |op| matches!(op, OP_JUMP_IF_TRUE)));
+    }
+
+    #[test]
+    fn optimizer_removes_dead_code_test() {
+        let chunk = optimized("var a = 1; if (false) { print a; } else { a = 2; }");
+        assert!(!has(&chunk, |op| matches!(op, OP_PRINT)));
+        assert!(!has(&chunk, |op| matches!(op, OP_JUMP_IF_FALSE)));
+
+        let chunk = optimized("var a = 1; while (false) { print a; }");
+        assert!(!has(&chunk, |op| matches!(op, OP_PRINT | OP_LOOP)));
+    }
+
+    #[test]
+    fn optimizer_disabled_test() {
+        let options = CompileOptions {
+            optimize: false,
+            ..CompileOptions::default()
+        };
+        let chunk = compile_with_options(&"1 != 2".to_string(), options).unwrap();
+        assert!(has(&chunk, |op| matches!(op, OP_EQUAL)));
+        assert!(has(&chunk, |op| matches!(op, OP_NOT)));
+    }
+
+    #[test]
+    fn optimizer_keeps_lines_test() {
+        let chunk = optimized("var a = 1;\n\nwhile (a < 3) {\n  a = a + (2 - 1);\n}\nprint a;");
+        let lines: Vec<usize> = (0..chunk.code.len()).map(|i| chunk.line(i)).collect();
+        assert_eq!(1, lines[0]);
+        assert_eq!(6, *lines.last().unwrap());
+        assert!(lines.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(has(&chunk, |op| matches!(op, OP_PRINT)));
+    }
+}
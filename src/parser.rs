@@ -0,0 +1,857 @@
+//! Builds the syntax tree in `ast` from source code. Expressions are parsed with a Pratt parser:
+//! every token kind has a precedence, and the token that starts or continues an expression decides
+//! how the rest of it is read.
+
+use crate::ast::*;
+use crate::scanner::TokenKind::*;
+use crate::scanner::{Scanner, Token, TokenKind};
+use crate::util::{is_alpha, is_digit};
+use std::fmt::{Display, Formatter};
+use std::mem;
+use std::path::Path;
+
+/// A syntax error and the token it was found at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+    /// text of the offending token, `None` at the end of the source
+    pub lexeme: Option<String>,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.lexeme {
+            Some(lexeme) => write!(
+                f,
+                "[line {}] Error at '{lexeme}': {}",
+                self.span.line, self.message
+            ),
+            None => write!(
+                f,
+                "[line {}] Error at end: {}",
+                self.span.line, self.message
+            ),
+        }
+    }
+}
+
+/// Parses a whole program. After a syntax error the parser skips to the next statement and keeps
+/// going, so every error in the source is returned, not only the first one.
+pub fn parse(source: &String) -> Result<Vec<Stmt>, Vec<ParseError>> {
+    let mut parser = Parser::new(source);
+    parser.advance();
+
+    let mut statements = Vec::new();
+    while !parser.match_token(TOKEN_EOF) {
+        statements.push(parser.declaration());
+    }
+
+    if parser.errors.is_empty() {
+        Ok(statements)
+    } else {
+        Err(parser.errors)
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialOrd, PartialEq)]
+enum Precedence {
+    PREC_NONE,
+    PREC_ASSIGNMENT, // =
+    PREC_OR,         // or
+    PREC_AND,        // and
+    PREC_EQUALITY,   // == !=
+    PREC_COMPARISON, // < > <= >=
+    PREC_TERM,       // + -
+    PREC_FACTOR,     // * / %
+    PREC_UNARY,      // ! -
+    PREC_POWER,      // **
+    PREC_CALL,       // . ()
+}
+
+impl Precedence {
+    /// The precedence that binds one step tighter.
+    fn next(self) -> Precedence {
+        match self {
+            Precedence::PREC_NONE => Precedence::PREC_ASSIGNMENT,
+            Precedence::PREC_ASSIGNMENT => Precedence::PREC_OR,
+            Precedence::PREC_OR => Precedence::PREC_AND,
+            Precedence::PREC_AND => Precedence::PREC_EQUALITY,
+            Precedence::PREC_EQUALITY => Precedence::PREC_COMPARISON,
+            Precedence::PREC_COMPARISON => Precedence::PREC_TERM,
+            Precedence::PREC_TERM => Precedence::PREC_FACTOR,
+            Precedence::PREC_FACTOR => Precedence::PREC_UNARY,
+            Precedence::PREC_UNARY => Precedence::PREC_POWER,
+            Precedence::PREC_POWER | Precedence::PREC_CALL => Precedence::PREC_CALL,
+        }
+    }
+}
+
+/// How tightly a token binds when it follows an expression.
+fn infix_precedence(kind: TokenKind) -> Precedence {
+    match kind {
+        TOKEN_LEFT_PAREN | TOKEN_DOT => Precedence::PREC_CALL,
+        TOKEN_STAR_STAR => Precedence::PREC_POWER,
+        TOKEN_STAR | TOKEN_SLASH | TOKEN_PERCENT => Precedence::PREC_FACTOR,
+        TOKEN_PLUS | TOKEN_MINUS => Precedence::PREC_TERM,
+        TOKEN_GREATER | TOKEN_GREATER_EQUAL | TOKEN_LESS | TOKEN_LESS_EQUAL => {
+            Precedence::PREC_COMPARISON
+        }
+        TOKEN_EQUAL_EQUAL | TOKEN_BANG_EQUAL => Precedence::PREC_EQUALITY,
+        TOKEN_AND => Precedence::PREC_AND,
+        TOKEN_OR => Precedence::PREC_OR,
+        _ => Precedence::PREC_NONE,
+    }
+}
+
+/// The precedence the right side of an infix operator is parsed at. Operators group to the left,
+/// `1 - 2 - 3` is `(1 - 2) - 3`, except `**`, which groups to the right: `2 ** 3 ** 2` is
+/// `2 ** (3 ** 2)`.
+fn operand_precedence(kind: TokenKind) -> Precedence {
+    match kind {
+        TOKEN_STAR_STAR => Precedence::PREC_POWER,
+        _ => infix_precedence(kind).next(),
+    }
+}
+
+fn binary_op(kind: TokenKind) -> Option<BinaryOp> {
+    let op = match kind {
+        TOKEN_EQUAL_EQUAL => BinaryOp::Equal,
+        TOKEN_BANG_EQUAL => BinaryOp::NotEqual,
+        TOKEN_GREATER => BinaryOp::Greater,
+        TOKEN_GREATER_EQUAL => BinaryOp::GreaterEqual,
+        TOKEN_LESS => BinaryOp::Less,
+        TOKEN_LESS_EQUAL => BinaryOp::LessEqual,
+        TOKEN_PLUS => BinaryOp::Add,
+        TOKEN_MINUS => BinaryOp::Subtract,
+        TOKEN_STAR => BinaryOp::Multiply,
+        TOKEN_SLASH => BinaryOp::Divide,
+        TOKEN_PERCENT => BinaryOp::Modulo,
+        TOKEN_STAR_STAR => BinaryOp::Power,
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn span_of(token: &Token) -> Span {
+    let end = match token.kind {
+        TOKEN_EOF | TOKEN_ERROR => token.start,
+        _ => token.start + token.slice.len(),
+    };
+    Span::new(token.start, end, token.line)
+}
+
+/// Whether `name` could be written as a variable name.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars().map(|c| c.to_string());
+    match chars.next() {
+        Some(first) => is_alpha(&first) && chars.all(|c| is_alpha(&c) || is_digit(&c)),
+        None => false,
+    }
+}
+
+enum ErrorAt {
+    Current,
+    Before,
+}
+
+struct Parser<'source> {
+    scanner: Scanner<'source>,
+    current: Token<'source>,
+    previous: Token<'source>,
+    errors: Vec<ParseError>,
+    panic_mode: bool,
+    /// how many blocks the parser is inside, imports are only allowed outside of them
+    block_depth: usize,
+}
+
+impl<'source> Parser<'source> {
+    fn new(source: &'source String) -> Self {
+        Parser {
+            scanner: Scanner::from(source),
+            current: Token::new(TOKEN_EOF, "", 1, 0),
+            previous: Token::new(TOKEN_EOF, "", 1, 0),
+            errors: Vec::new(),
+            panic_mode: false,
+            block_depth: 0,
+        }
+    }
+
+    fn advance(&mut self) {
+        let next = loop {
+            let token = self.scanner.scan_token();
+
+            // The scanner only moves past literals, identifiers and `assert_eq` by itself.
+            if !matches!(
+                token.kind,
+                TOKEN_NUMBER
+                    | TOKEN_STRING
+                    | TOKEN_IDENTIFIER
+                    | TOKEN_NIL
+                    | TOKEN_TRUE
+                    | TOKEN_FALSE
+                    | TOKEN_ASSERT_EQ
+            ) {
+                self.scanner.advance();
+            }
+
+            if token.kind != TOKEN_ERROR {
+                break token;
+            }
+            // Error tokens carry their message as the slice.
+            if !self.panic_mode {
+                self.panic_mode = true;
+                let source = self.scanner.source;
+                self.errors.push(ParseError {
+                    message: token.slice.to_string(),
+                    span: span_of(&token),
+                    lexeme: source[token.start..].chars().next().map(String::from),
+                });
+            }
+        };
+        self.previous = mem::replace(&mut self.current, next);
+    }
+
+    fn error_at(&mut self, error_at: ErrorAt, message: &str) {
+        if self.panic_mode {
+            return;
+        }
+        self.panic_mode = true;
+
+        let token = match error_at {
+            ErrorAt::Current => &self.current,
+            ErrorAt::Before => &self.previous,
+        };
+        let lexeme = match token.kind {
+            TOKEN_EOF => None,
+            _ => Some(token.slice.to_string()),
+        };
+        self.errors.push(ParseError {
+            message: message.to_string(),
+            span: span_of(token),
+            lexeme,
+        });
+    }
+
+    /// Advances past the current token if it is a `kind`, reports `message` otherwise.
+    fn consume(&mut self, kind: TokenKind, message: &str) {
+        if self.current.kind == kind {
+            self.advance();
+        } else {
+            self.error_at(ErrorAt::Current, message);
+        }
+    }
+
+    fn check(&self, kind: TokenKind) -> bool {
+        self.current.kind == kind
+    }
+
+    fn match_token(&mut self, kind: TokenKind) -> bool {
+        if !self.check(kind) {
+            return false;
+        }
+        self.advance();
+        true
+    }
+
+    /// The span from `start` to the end of the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(span_of(&self.previous))
+    }
+
+    // ####################################################################################
+    // Statements
+    // ####################################################################################
+
+    fn declaration(&mut self) -> Stmt {
+        let statement = if self.match_token(TOKEN_VAR) {
+            self.var_declaration()
+        } else if self.at_test() {
+            self.advance();
+            self.test_declaration()
+        } else {
+            self.statement()
+        };
+
+        if self.panic_mode {
+            self.synchronize();
+        }
+        statement
+    }
+
+    /// Whether a test starts here. `test` is only a keyword in front of the test's name, it can
+    /// still name a variable.
+    fn at_test(&self) -> bool {
+        let rest = &self.scanner.source[self.scanner.current..];
+        self.check(TOKEN_IDENTIFIER)
+            && self.current.slice == "test"
+            && rest.trim_start().starts_with('"')
+    }
+
+    /// `test "name" { ... }`, a block that only runs under `yellowstone test`.
+    fn test_declaration(&mut self) -> Stmt {
+        let start = span_of(&self.previous);
+        if self.block_depth > 0 {
+            self.error_at(ErrorAt::Before, "Can only declare tests at the top level.");
+        }
+
+        self.consume(TOKEN_STRING, "Expect test name after 'test'.");
+        let name = self.previous.slice.trim_matches('"').to_string();
+        let body_start = span_of(&self.current);
+        // Without its brace the body is left out, rather than read up to a `}` that never comes.
+        let statements = if self.match_token(TOKEN_LEFT_BRACE) {
+            self.block()
+        } else {
+            self.error_at(ErrorAt::Current, "Expect '{' before test body.");
+            Vec::new()
+        };
+        let body = Stmt {
+            kind: StmtKind::Block(statements),
+            span: self.span_from(body_start),
+        };
+
+        Stmt {
+            kind: StmtKind::Test {
+                name,
+                body: Box::new(body),
+            },
+            span: self.span_from(start),
+        }
+    }
+
+    fn var_declaration(&mut self) -> Stmt {
+        let start = span_of(&self.previous);
+        self.consume(TOKEN_IDENTIFIER, "Expect variable name.");
+        let name = self.previous.slice.to_string();
+
+        let initializer = if self.match_token(TOKEN_EQUAL) {
+            Some(self.expression())
+        } else {
+            None
+        };
+        self.consume(TOKEN_SEMICOLON, "Expect ';' after variable declaration.");
+
+        Stmt {
+            kind: StmtKind::Var { name, initializer },
+            span: self.span_from(start),
+        }
+    }
+
+    fn statement(&mut self) -> Stmt {
+        let start = span_of(&self.current);
+        let kind = if self.match_token(TOKEN_PRINT) {
+            let value = self.expression();
+            self.consume(TOKEN_SEMICOLON, "Expect ';' after value.");
+            StmtKind::Print(value)
+        } else if self.match_token(TOKEN_IF) {
+            self.if_statement()
+        } else if self.match_token(TOKEN_ASSERT_EQ) {
+            self.assert_eq_statement()
+        } else if self.match_token(TOKEN_LEFT_BRACE) {
+            StmtKind::Block(self.block())
+        } else if self.match_token(TOKEN_WHILE) {
+            self.while_statement()
+        } else if self.match_token(TOKEN_FOR) {
+            self.for_statement()
+        } else if self.match_token(TOKEN_IMPORT) {
+            self.import_statement()
+        } else if self.match_token(TOKEN_SEMICOLON) {
+            StmtKind::Empty
+        } else {
+            // The semicolon after an expression statement is optional.
+            let expression = self.expression();
+            self.match_token(TOKEN_SEMICOLON);
+            StmtKind::Expression(expression)
+        };
+
+        Stmt {
+            kind,
+            span: self.span_from(start),
+        }
+    }
+
+    fn block(&mut self) -> Vec<Stmt> {
+        self.block_depth += 1;
+        let mut statements = Vec::new();
+        while !self.check(TOKEN_RIGHT_BRACE) && !self.check(TOKEN_EOF) {
+            statements.push(self.declaration());
+        }
+        self.consume(TOKEN_RIGHT_BRACE, "Expect '}' after block.");
+        self.block_depth -= 1;
+        statements
+    }
+
+    fn if_statement(&mut self) -> StmtKind {
+        self.consume(TOKEN_LEFT_PAREN, "Expect '(' after 'if'.");
+        let condition = self.expression();
+        self.consume(TOKEN_RIGHT_PAREN, "Expect ')' after condition");
+
+        let then_branch = Box::new(self.statement());
+        let else_branch = if self.match_token(TOKEN_ELSE) {
+            Some(Box::new(self.statement()))
+        } else {
+            None
+        };
+
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        }
+    }
+
+    fn while_statement(&mut self) -> StmtKind {
+        self.consume(TOKEN_LEFT_PAREN, "Expect '(' after 'while'.");
+        let condition = self.expression();
+        self.consume(TOKEN_RIGHT_PAREN, "Expect ')' after 'while'.");
+        let body = Box::new(self.statement());
+        StmtKind::While { condition, body }
+    }
+
+    fn for_statement(&mut self) -> StmtKind {
+        self.consume(TOKEN_LEFT_PAREN, "Expect '(' after 'for'.");
+        let initializer = if self.match_token(TOKEN_SEMICOLON) {
+            None
+        } else if self.match_token(TOKEN_VAR) {
+            Some(Box::new(self.var_declaration()))
+        } else {
+            let start = span_of(&self.current);
+            let expression = self.expression();
+            self.consume(TOKEN_SEMICOLON, "Expect ';' after loop initializer.");
+            Some(Box::new(Stmt {
+                kind: StmtKind::Expression(expression),
+                span: self.span_from(start),
+            }))
+        };
+
+        let mut condition = None;
+        if !self.match_token(TOKEN_SEMICOLON) {
+            condition = Some(self.expression());
+            self.consume(TOKEN_SEMICOLON, "Expect ';' after loop condition.");
+        }
+
+        let mut increment = None;
+        if !self.match_token(TOKEN_RIGHT_PAREN) {
+            increment = Some(self.expression());
+            self.consume(TOKEN_RIGHT_PAREN, "Expect ')' after for clauses.");
+        }
+
+        let body = Box::new(self.statement());
+        StmtKind::For {
+            initializer,
+            condition,
+            increment,
+            body,
+        }
+    }
+
+    fn assert_eq_statement(&mut self) -> StmtKind {
+        self.consume(TOKEN_LEFT_PAREN, "Expect '(' after assert statement.");
+        let left = self.expression();
+        self.consume(TOKEN_COMMA, "Expect ',' after expression.");
+        let right = self.expression();
+        let message = if self.match_token(TOKEN_COMMA) {
+            Some(self.expression())
+        } else {
+            None
+        };
+        self.consume(TOKEN_RIGHT_PAREN, "Expect ')' after statement.");
+        self.consume(TOKEN_SEMICOLON, "Expect ';' after statement.");
+        StmtKind::AssertEq {
+            left,
+            right,
+            message,
+        }
+    }
+
+    /// `import "path.ys";` binds the module to the file's name, `import name from "path.ys";` binds
+    /// it to `name`. The path is resolved by the VM, relative to the importing file.
+    fn import_statement(&mut self) -> StmtKind {
+        if self.block_depth > 0 {
+            self.error_at(ErrorAt::Before, "Can only import at the top level.");
+        }
+
+        let mut alias = None;
+        if self.match_token(TOKEN_IDENTIFIER) {
+            alias = Some(self.previous.slice.to_string());
+            if self.check(TOKEN_IDENTIFIER) && self.current.slice == "from" {
+                self.advance();
+            } else {
+                self.error_at(ErrorAt::Current, "Expect 'from' after import name.");
+            }
+        }
+
+        self.consume(TOKEN_STRING, "Expect module path after 'import'.");
+        let path = match self.previous.kind {
+            TOKEN_STRING => self.previous.slice[1..self.previous.slice.len() - 1].to_string(),
+            _ => String::new(),
+        };
+
+        let name = match &alias {
+            Some(alias) => alias.clone(),
+            None => {
+                let stem = Path::new(&path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if !is_identifier(&stem) {
+                    let message = format!(
+                        "Module name '{stem}' is not an identifier, use 'import name from'."
+                    );
+                    self.error_at(ErrorAt::Before, &message);
+                }
+                stem
+            }
+        };
+        self.consume(TOKEN_SEMICOLON, "Expect ';' after import.");
+
+        StmtKind::Import { alias, path, name }
+    }
+
+    /// Continue to advance the scanner until the end of the statement, or a token that starts a
+    /// new one, is found.
+    fn synchronize(&mut self) {
+        self.panic_mode = false;
+
+        while self.current.kind != TOKEN_EOF {
+            if self.previous.kind == TOKEN_SEMICOLON {
+                return;
+            }
+            match self.current.kind {
+                TOKEN_CLASS | TOKEN_FUN | TOKEN_VAR | TOKEN_FOR | TOKEN_IF | TOKEN_WHILE
+                | TOKEN_PRINT | TOKEN_RETURN | TOKEN_ASSERT_EQ | TOKEN_IMPORT => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    // ####################################################################################
+    // Expressions
+    // ####################################################################################
+
+    fn expression(&mut self) -> Expr {
+        self.parse_precedence(Precedence::PREC_ASSIGNMENT)
+    }
+
+    /// Parses an expression whose operators bind at least as tightly as `precedence`. The right
+    /// side of a binary operator is parsed at `operand_precedence`, so `1 - 2 - 3` is
+    /// `(1 - 2) - 3`.
+    fn parse_precedence(&mut self, precedence: Precedence) -> Expr {
+        self.advance();
+        let can_assign = precedence <= Precedence::PREC_ASSIGNMENT;
+        let mut expression = self.prefix(can_assign);
+
+        while precedence <= infix_precedence(self.current.kind) {
+            self.advance();
+            expression = self.infix(expression);
+        }
+
+        if can_assign && self.match_token(TOKEN_EQUAL) {
+            self.error_at(ErrorAt::Before, "Invalid assignment target.");
+        }
+        expression
+    }
+
+    /// Parses the expression started by the token just consumed.
+    fn prefix(&mut self, can_assign: bool) -> Expr {
+        let start = span_of(&self.previous);
+        let kind = match self.previous.kind {
+            TOKEN_LEFT_PAREN => {
+                let inner = self.expression();
+                self.consume(TOKEN_RIGHT_PAREN, "Expect ')' after expression.");
+                ExprKind::Grouping(Box::new(inner))
+            }
+            TOKEN_MINUS | TOKEN_BANG => {
+                let op = match self.previous.kind {
+                    TOKEN_MINUS => UnaryOp::Negate,
+                    _ => UnaryOp::Not,
+                };
+                let operand = self.parse_precedence(Precedence::PREC_UNARY);
+                ExprKind::Unary {
+                    op,
+                    operand: Box::new(operand),
+                }
+            }
+            TOKEN_NUMBER => {
+                let number = self.previous.slice.parse::<f32>().unwrap();
+                ExprKind::Literal(Literal::Number(number))
+            }
+            TOKEN_STRING => {
+                let slice = self.previous.slice;
+                ExprKind::Literal(Literal::String(slice[1..slice.len() - 1].to_string()))
+            }
+            TOKEN_TRUE => ExprKind::Literal(Literal::Bool(true)),
+            TOKEN_FALSE => ExprKind::Literal(Literal::Bool(false)),
+            TOKEN_NIL => ExprKind::Literal(Literal::Nil),
+            TOKEN_IDENTIFIER => {
+                let name = self.previous.slice.to_string();
+                if can_assign && self.match_token(TOKEN_EQUAL) {
+                    let value = self.expression();
+                    ExprKind::Assign {
+                        name,
+                        value: Box::new(value),
+                    }
+                } else {
+                    ExprKind::Variable(name)
+                }
+            }
+            _ => {
+                self.error_at(ErrorAt::Before, "Expect expression.");
+                ExprKind::Literal(Literal::Nil)
+            }
+        };
+
+        Expr {
+            kind,
+            span: self.span_from(start),
+        }
+    }
+
+    /// Parses the rest of an expression continued by the token just consumed.
+    fn infix(&mut self, left: Expr) -> Expr {
+        let start = left.span;
+        let operator = self.previous.kind;
+        let kind = match operator {
+            TOKEN_LEFT_PAREN => {
+                let mut arguments = Vec::new();
+                if !self.check(TOKEN_RIGHT_PAREN) {
+                    loop {
+                        arguments.push(self.expression());
+                        if !self.match_token(TOKEN_COMMA) {
+                            break;
+                        }
+                    }
+                }
+                self.consume(TOKEN_RIGHT_PAREN, "Expect ')' after arguments.");
+                ExprKind::Call {
+                    callee: Box::new(left),
+                    arguments,
+                }
+            }
+            TOKEN_DOT => {
+                self.consume(TOKEN_IDENTIFIER, "Expect member name after '.'.");
+                ExprKind::Get {
+                    object: Box::new(left),
+                    name: self.previous.slice.to_string(),
+                }
+            }
+            TOKEN_AND | TOKEN_OR => {
+                let op = match operator {
+                    TOKEN_AND => LogicalOp::And,
+                    _ => LogicalOp::Or,
+                };
+                let right = self.parse_precedence(operand_precedence(operator));
+                ExprKind::Logical {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }
+            }
+            _ => {
+                let op = binary_op(operator).expect("only operators have an infix precedence");
+                let right = self.parse_precedence(operand_precedence(operator));
+                ExprKind::Binary {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }
+            }
+        };
+
+        Expr {
+            kind,
+            span: self.span_from(start),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::*;
+    use crate::parser::parse;
+
+    fn statements(source: &str) -> Vec<Stmt> {
+        parse(&source.to_string()).unwrap()
+    }
+
+    fn expression(source: &str) -> Expr {
+        match statements(source).remove(0).kind {
+            StmtKind::Expression(expression) => expression,
+            kind => panic!("expected an expression statement, found {kind:?}"),
+        }
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        let errors = parse(&source.to_string()).unwrap_err();
+        errors.iter().map(|error| error.to_string()).collect()
+    }
+
+    fn binary(expression: &Expr) -> (BinaryOp, &Expr, &Expr) {
+        match &expression.kind {
+            ExprKind::Binary { op, left, right } => (*op, left, right),
+            kind => panic!("expected a binary expression, found {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_precedence_test() {
+        let sum = expression("1 + 2 * 3");
+        let (op, left, right) = binary(&sum);
+        assert_eq!(BinaryOp::Add, op);
+        assert_eq!(ExprKind::Literal(Literal::Number(1.)), left.kind);
+        assert_eq!(BinaryOp::Multiply, binary(right).0);
+
+        // Operators of the same precedence group to the left.
+        let difference = expression("1 - 2 - 3");
+        let (op, left, right) = binary(&difference);
+        assert_eq!(BinaryOp::Subtract, op);
+        assert_eq!(BinaryOp::Subtract, binary(left).0);
+        assert_eq!(ExprKind::Literal(Literal::Number(3.)), right.kind);
+
+        // Except `**`, which groups to the right.
+        let power = expression("2 ** 3 ** 2");
+        let (op, left, right) = binary(&power);
+        assert_eq!(BinaryOp::Power, op);
+        assert_eq!(ExprKind::Literal(Literal::Number(2.)), left.kind);
+        assert_eq!(BinaryOp::Power, binary(right).0);
+    }
+
+    #[test]
+    fn parse_spans_test() {
+        let source = "var answer = 6 * 7;\nprint answer;";
+        let program = statements(source);
+        assert_eq!(2, program.len());
+        assert_eq!("var answer = 6 * 7;", program[0].span.slice(source));
+        assert_eq!(1, program[0].span.line);
+        assert_eq!("print answer;", program[1].span.slice(source));
+        assert_eq!(2, program[1].span.line);
+
+        match &program[0].kind {
+            StmtKind::Var {
+                name,
+                initializer: Some(initializer),
+            } => {
+                assert_eq!("answer", name);
+                assert_eq!("6 * 7", initializer.span.slice(source));
+            }
+            kind => panic!("expected a variable declaration, found {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_statements_test() {
+        let program = statements(
+            "
+            import math from \"lib/math.ys\";
+            import \"geometry.ys\";
+            var n = 0;
+            while (n < 3) { n = n + 1; }
+            for (var i = 0; i < 3; i = i + 1) print i;
+            if (n == 3) print \"three\"; else { assert_eq(n, 3); }
+            ;
+            ",
+        );
+        let kinds: Vec<&str> = program
+            .iter()
+            .map(|statement| match &statement.kind {
+                StmtKind::Import { .. } => "import",
+                StmtKind::Var { .. } => "var",
+                StmtKind::While { .. } => "while",
+                StmtKind::For { .. } => "for",
+                StmtKind::If { .. } => "if",
+                StmtKind::Empty => "empty",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            vec!["import", "import", "var", "while", "for", "if", "empty"],
+            kinds
+        );
+
+        match (&program[0].kind, &program[1].kind) {
+            (
+                StmtKind::Import { alias, name, .. },
+                StmtKind::Import {
+                    alias: None,
+                    name: stem,
+                    path,
+                },
+            ) => {
+                assert_eq!(Some("math".to_string()), *alias);
+                assert_eq!("math", name);
+                assert_eq!("geometry", stem);
+                assert_eq!("geometry.ys", path);
+            }
+            kinds => panic!("expected two imports, found {kinds:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_tests_test() {
+        let source = "var test = 1;\ntest \"adds up\" {\n    assert_eq(test + 1, 2);\n}\ntest;";
+        let program = statements(source);
+        assert_eq!(3, program.len());
+        match &program[1].kind {
+            StmtKind::Test { name, body } => {
+                assert_eq!("adds up", name);
+                assert!(matches!(&body.kind, StmtKind::Block(statements) if statements.len() == 1));
+                assert_eq!("{\n    assert_eq(test + 1, 2);\n}", body.span.slice(source));
+            }
+            kind => panic!("expected a test, found {kind:?}"),
+        }
+        assert!(matches!(program[2].kind, StmtKind::Expression(_)));
+    }
+
+    #[test]
+    fn parse_calls_and_members_test() {
+        let call = expression("geometry.area(2, max(1, 3))");
+        match call.kind {
+            ExprKind::Call { callee, arguments } => {
+                assert!(matches!(callee.kind, ExprKind::Get { ref name, .. } if name == "area"));
+                assert_eq!(2, arguments.len());
+            }
+            kind => panic!("expected a call, found {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_errors_test() {
+        assert_eq!(
+            vec!["[line 1] Error at '=': Expect variable name."],
+            errors("var = 1;")
+        );
+        // The parser recovers at the next statement and reports that error as well.
+        assert_eq!(
+            vec![
+                "[line 1] Error at '=': Expect variable name.",
+                "[line 2] Error at ';': Expect expression.",
+            ],
+            errors("var = 1;\nprint ;")
+        );
+        assert_eq!(
+            vec!["[line 1] Error at '=': Invalid assignment target."],
+            errors("1 + a = 2")
+        );
+        assert_eq!(
+            vec!["[line 1] Error at end: Expect ')' after expression."],
+            errors("(1")
+        );
+        assert_eq!(
+            vec!["[line 1] Error at 'import': Can only import at the top level."],
+            errors("{ import \"a.ys\"; }")
+        );
+        assert_eq!(
+            vec![
+                "[line 1] Error at 'test': Can only declare tests at the top level.",
+                "[line 1] Error at end: Expect '}' after block."
+            ],
+            errors("{ test \"inner\" {} }")
+        );
+        assert_eq!(
+            vec!["[line 1] Error at 'print': Expect '{' before test body."],
+            errors("test \"name\" print 1;")
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Counts the instructions a program runs, by opcode and by source line, while `VM::profile` is
+//! set.
+//!
+//! There are no functions yet, so the stack of a line is the chain of modules that imported its
+//! file. Stacks are written in the collapsed format flamegraph tools read, one
+//! `main.ys;geometry.ys;geometry.ys:3 120` line per stack, with the file and line as the leaf.
+
+use crate::chunk::Chunk;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct Profile {
+    /// instructions run in total
+    pub instructions: u64,
+    /// instructions run by opcode name
+    pub opcodes: HashMap<&'static str, u64>,
+    /// instructions run by module stack and line, see `collapsed`
+    pub stacks: HashMap<(Rc<str>, usize), u64>,
+    /// when the first and the last instruction ran
+    span: Option<(Instant, Instant)>,
+    /// the files running when `frames` was built, and their names joined with `;`
+    frames: (Vec<PathBuf>, Rc<str>),
+}
+
+impl Profile {
+    /// Counts the instruction at `ip`, called by the VM before running it.
+    pub fn record(&mut self, chunk: &Chunk, ip: usize, import_stack: &[PathBuf]) {
+        let now = Instant::now();
+        let start = self.span.map_or(now, |(start, _)| start);
+        self.span = Some((start, now));
+
+        self.instructions += 1;
+        *self.opcodes.entry(chunk.code[ip].name()).or_default() += 1;
+
+        if self.frames.0 != import_stack || self.frames.1.is_empty() {
+            let frames: Vec<String> = if import_stack.is_empty() {
+                vec!["<script>".to_string()]
+            } else {
+                import_stack.iter().map(|path| file_name(path)).collect()
+            };
+            self.frames = (import_stack.to_vec(), frames.join(";").into());
+        }
+        let key = (self.frames.1.clone(), chunk.line(ip));
+        *self.stacks.entry(key).or_default() += 1;
+    }
+
+    /// Time from the first instruction to the last one.
+    pub fn elapsed(&self) -> Duration {
+        self.span
+            .map_or(Duration::ZERO, |(start, end)| end.duration_since(start))
+    }
+
+    /// Instructions run on each line, as `file:line` and count, the busiest first.
+    pub fn lines(&self) -> Vec<(String, u64)> {
+        let mut lines: HashMap<String, u64> = HashMap::new();
+        for ((frames, line), count) in &self.stacks {
+            *lines.entry(leaf(frames, *line)).or_default() += count;
+        }
+        sorted(lines.into_iter().collect())
+    }
+
+    /// The summary printed by `--profile`: the total, then the `top` busiest lines and opcodes.
+    pub fn report(&self, top: usize) -> String {
+        let mut report = format!(
+            "== profile ==\n{} instructions in {:.3?}\n-- hot lines --\n",
+            self.instructions,
+            self.elapsed()
+        );
+        for (line, count) in self.lines().into_iter().take(top) {
+            let _ = writeln!(report, "{count:>10} {:>6} {line}", self.share(count));
+        }
+        report.push_str("-- hot opcodes --\n");
+        let opcodes = self
+            .opcodes
+            .iter()
+            .map(|(name, count)| (name.to_string(), *count))
+            .collect();
+        for (name, count) in sorted(opcodes).into_iter().take(top) {
+            let _ = writeln!(report, "{count:>10} {:>6} {name}", self.share(count));
+        }
+        report
+    }
+
+    /// The stacks in the collapsed format, sorted so the output is the same for the same run.
+    pub fn collapsed(&self) -> String {
+        let mut stacks: Vec<(String, u64)> = self
+            .stacks
+            .iter()
+            .map(|((frames, line), count)| (format!("{frames};{}", leaf(frames, *line)), *count))
+            .collect();
+        stacks.sort();
+        let mut collapsed = String::new();
+        for (stack, count) in stacks {
+            let _ = writeln!(collapsed, "{stack} {count}");
+        }
+        collapsed
+    }
+
+    fn share(&self, count: u64) -> String {
+        format!(
+            "{:.1}%",
+            count as f64 * 100.0 / self.instructions.max(1) as f64
+        )
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or(path.display().to_string(), |name| {
+        name.to_string_lossy().to_string()
+    })
+}
+
+/// `file:line` for a line of the innermost file in `frames`.
+fn leaf(frames: &str, line: usize) -> String {
+    let file = frames.rsplit(';').next().unwrap_or(frames);
+    format!("{file}:{line}")
+}
+
+/// Busiest first, ties by name.
+fn sorted(mut counts: Vec<(String, u64)>) -> Vec<(String, u64)> {
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::profiler::Profile;
+    use crate::vm::VM;
+    use std::path::PathBuf;
+
+    fn profiled() -> VM {
+        VM {
+            profile: Some(Profile::default()),
+            ..VM::default()
+        }
+    }
+
+    #[test]
+    fn profile_counts_test() {
+        let mut vm = profiled();
+        let source = "var i = 0;\nwhile (i < 3) {\n    i = i + 1;\n}\n";
+        vm.interpret(&source.to_string()).unwrap();
+        let profile = vm.profile.take().unwrap();
+
+        assert_eq!(3, profile.opcodes["OP_ADD"]);
+        assert_eq!(4, profile.opcodes["OP_LESS"]);
+        assert_eq!(1, profile.opcodes["OP_RETURN"]);
+        assert_eq!(profile.instructions, profile.opcodes.values().sum::<u64>());
+
+        let lines = profile.lines();
+        assert_eq!("<script>:2", lines[0].0);
+        assert_eq!("<script>:3", lines[1].0);
+        assert_eq!("<script>:1", lines[2].0);
+        assert_eq!(2, lines[2].1);
+
+        let report = profile.report(2);
+        assert!(report.contains(&format!("{} instructions", profile.instructions)));
+        assert_eq!(2, report.matches("<script>:").count(), "{report}");
+    }
+
+    #[test]
+    fn profile_collapsed_stacks_test() {
+        let mut vm = profiled();
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("modules")
+            .join("main.ys");
+        vm.interpret_file(&path).unwrap();
+        let profile = vm.profile.take().unwrap();
+
+        let collapsed = profile.collapsed();
+        // geometry.ys: `var sides = 4;` is a constant and a definition.
+        assert!(
+            collapsed.contains("main.ys;geometry.ys;geometry.ys:1 2\n"),
+            "{collapsed}"
+        );
+        assert!(
+            collapsed.contains("main.ys;consts.ys;consts.ys:3 "),
+            "{collapsed}"
+        );
+        assert!(collapsed.contains("main.ys;main.ys:4 "), "{collapsed}");
+        let total: u64 = collapsed
+            .lines()
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<u64>().unwrap())
+            .sum();
+        assert_eq!(profile.instructions, total);
+    }
+}
@@ -0,0 +1,637 @@
+//! The interactive prompt started by running `yellowstone` without a script.
+//!
+//! Input is read a line at a time and run once it is a whole program: while a `{` or `(` is left
+//! open or a string is not closed, the prompt changes to `..` and the next line is added to it.
+//! Globals carry over from one input to the next. When the input ends with an expression
+//! statement its value is shown, and lines starting with `:` are commands, see `HELP`.
+//!
+//! On a terminal the line can be edited with the arrow keys, Home, End, Delete and the usual
+//! Ctrl shortcuts, and Up and Down walk through the lines entered before, which are kept in
+//! `~/.yellowstone_history`. Ctrl-C throws away the input typed so far and Ctrl-D on an empty line
+//! ends the session. There are no terminal crates, the terminal is put in raw mode with `stty`.
+
+use crate::ast::StmtKind;
+use crate::compiler::compile_with_options;
+use crate::debug::disassemble;
+use crate::parser::parse;
+use crate::scanner::{Scanner, TokenKind};
+use crate::vm::VM;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, stdin, stdout, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+/// How many lines the history file keeps.
+const HISTORY_SIZE: usize = 1000;
+
+const HELP: &str = "\
+:globals       show the global variables
+:dis <code>    show the bytecode the code compiles to, without running it
+:load <path>   run a script, its globals stay defined
+:time <code>   run the code and show how long it took
+:reset         forget every global and imported module
+:help          show this help
+exit           end the session";
+
+/// What reading a line ended with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Line(String),
+    /// Ctrl-C, the line is thrown away
+    Interrupted,
+    /// Ctrl-D on an empty line or the end of the input
+    Eof,
+}
+
+/// Runs the prompt until the input ends or `exit` is entered.
+pub fn repl(mut vm: VM) {
+    println!("[yellowstone repl]");
+    println!("(type `exit` or `quit` to stop session, Ctrl-C discards the input)");
+
+    // Piped input is not worth remembering.
+    let history = if stdin().is_terminal() {
+        history_path()
+    } else {
+        None
+    };
+    let mut editor = LineEditor::new(history);
+    let mut source = String::new();
+    loop {
+        let prompt = if source.is_empty() { ">> " } else { ".. " };
+        let line = match editor.read_line(prompt) {
+            Ok(Input::Line(line)) => line,
+            Ok(Input::Interrupted) => {
+                source.clear();
+                continue;
+            }
+            Ok(Input::Eof) => break,
+            Err(error) => {
+                println!("> error: {error}");
+                break;
+            }
+        };
+        editor.add_history(&line);
+
+        if source.is_empty() {
+            match line.trim() {
+                "exit" | "Exit" | "Quit" | "quit" | "q" => break,
+                "" => continue,
+                command if command.starts_with(':') => {
+                    if let Err(error) = run_command(&mut vm, command, &mut stdout()) {
+                        println!("> error: {error}");
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        source.push_str(&line);
+        source.push('\n');
+        if is_incomplete(&source) {
+            continue;
+        }
+
+        if let Err(error) = run_code(&mut vm, &source, &mut stdout()) {
+            println!("> error: {error}");
+        }
+        source.clear();
+    }
+
+    vm.free_objects();
+}
+
+/// Runs a whole input and shows its value on `output` if it ends with an expression statement,
+/// written as in source code (see `Value::repr`), or the error it stopped with.
+pub fn run_code(vm: &mut VM, source: &String, output: &mut impl Write) -> io::Result<()> {
+    // Values left by earlier inputs would be taken for this one's.
+    vm.stack.clear();
+    let ends_with_expression = parse(source).is_ok_and(|program| {
+        program
+            .last()
+            .is_some_and(|stmt| matches!(stmt.kind, StmtKind::Expression(_)))
+    });
+    match vm.interpret(source) {
+        Ok(Some(value)) if ends_with_expression => writeln!(output, "{}", value.repr()),
+        Ok(_) => Ok(()),
+        Err(e) => writeln!(output, "{e:?}"),
+    }
+}
+
+/// Runs a `:` command, writing what it shows to `output`.
+pub fn run_command(vm: &mut VM, command: &str, output: &mut impl Write) -> io::Result<()> {
+    let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+    let argument = argument.trim();
+    match name {
+        ":help" | ":h" => writeln!(output, "{HELP}"),
+        ":globals" => {
+            for (name, value) in vm.globals() {
+                writeln!(output, "{name} = {}", value.repr())?;
+            }
+            Ok(())
+        }
+        ":dis" => match compile_with_options(&statement(argument), vm.compile_options) {
+            Ok(chunk) => write!(output, "{}", disassemble(&chunk, argument)),
+            Err(e) => writeln!(output, "{e:?}"),
+        },
+        ":load" if !argument.is_empty() => match vm.interpret_file(Path::new(argument)) {
+            Ok(_) => Ok(()),
+            Err(e) => writeln!(output, "{e:?}"),
+        },
+        ":time" if !argument.is_empty() => {
+            let start = Instant::now();
+            run_code(vm, &statement(argument), output)?;
+            writeln!(output, "took {:.3?}", start.elapsed())
+        }
+        ":reset" => {
+            let mut old = std::mem::take(vm);
+            *vm = VM {
+                compile_options: std::mem::take(&mut old.compile_options),
+                hook: old.hook.take(),
+                profile: old.profile.take(),
+                limits: std::mem::take(&mut old.limits),
+                output: std::mem::replace(&mut old.output, Box::new(std::io::sink())),
+                ..VM::default()
+            };
+            old.free_objects();
+            writeln!(output, "the session was reset")
+        }
+        ":load" | ":time" => writeln!(output, "{name} needs an argument, see :help"),
+        _ => writeln!(output, "unknown command {name}, see :help"),
+    }
+}
+
+/// `code` with the `;` that ends a statement added if it was left out, as in `:time 1 + 2`.
+fn statement(code: &str) -> String {
+    if code.ends_with(';') || code.ends_with('}') {
+        code.to_string()
+    } else {
+        format!("{code};")
+    }
+}
+
+/// `~/.yellowstone_history`, if there is a home directory.
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".yellowstone_history"))
+}
+
+/// Whether `source` needs more lines before it can be compiled: a bracket is left open or a
+/// string is not closed. Closing brackets with nothing to close are left to the compiler.
+pub fn is_incomplete(source: &String) -> bool {
+    let mut scanner = Scanner::new(source);
+    let mut depth: usize = 0;
+    loop {
+        let token = scanner.scan_token();
+        // The scanner only moves past literals, identifiers and `assert_eq` by itself.
+        if !matches!(
+            token.kind,
+            TokenKind::TOKEN_NUMBER
+                | TokenKind::TOKEN_STRING
+                | TokenKind::TOKEN_IDENTIFIER
+                | TokenKind::TOKEN_NIL
+                | TokenKind::TOKEN_TRUE
+                | TokenKind::TOKEN_FALSE
+                | TokenKind::TOKEN_ASSERT_EQ
+        ) {
+            scanner.advance();
+        }
+        match token.kind {
+            TokenKind::TOKEN_EOF => return depth > 0,
+            TokenKind::TOKEN_LEFT_BRACE | TokenKind::TOKEN_LEFT_PAREN => depth += 1,
+            TokenKind::TOKEN_RIGHT_BRACE | TokenKind::TOKEN_RIGHT_PAREN => {
+                depth = depth.saturating_sub(1)
+            }
+            TokenKind::TOKEN_ERROR if token.slice == "Unterminated string." => return true,
+            _ => {}
+        }
+    }
+}
+
+/// Reads lines from the terminal with editing and history, or plainly when the input is not a
+/// terminal.
+pub struct LineEditor {
+    pub history: Vec<String>,
+    history_file: Option<PathBuf>,
+}
+
+/// A key, as far as the editor cares.
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    KillToEnd,
+    KillToStart,
+    Interrupt,
+    EndOfInput,
+    /// the input has no more bytes
+    Closed,
+    Ignored,
+}
+
+impl LineEditor {
+    /// An editor whose history is read from and appended to `history_file`.
+    pub fn new(history_file: Option<PathBuf>) -> Self {
+        let mut history: Vec<String> = history_file
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| text.lines().map(String::from).collect())
+            .unwrap_or_default();
+        if history.len() > HISTORY_SIZE {
+            history.drain(..history.len() - HISTORY_SIZE);
+            if let Some(path) = &history_file {
+                let _ = fs::write(path, history.join("\n") + "\n");
+            }
+        }
+        LineEditor {
+            history,
+            history_file,
+        }
+    }
+
+    /// Adds a line to the history unless it is blank or the same as the last one.
+    pub fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if let Some(path) = &self.history_file {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{line}");
+            }
+        }
+    }
+
+    /// Shows `prompt` and reads a line from stdin.
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Input> {
+        if !stdin().is_terminal() || !stdout().is_terminal() {
+            print!("{prompt}");
+            stdout().flush()?;
+            let mut line = String::new();
+            if stdin().read_line(&mut line)? == 0 {
+                return Ok(Input::Eof);
+            }
+            let end = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(end);
+            return Ok(Input::Line(line));
+        }
+
+        let _raw = RawMode::enable()?;
+        self.edit(prompt, &mut stdin().lock(), &mut stdout().lock())
+    }
+
+    /// Reads keys from `input` and draws the line on `output` until it is entered. `input` is the
+    /// bytes a terminal in raw mode sends.
+    pub fn edit(
+        &mut self,
+        prompt: &str,
+        input: &mut impl Read,
+        output: &mut impl Write,
+    ) -> io::Result<Input> {
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // Which history entry is shown, `history.len()` is the line being typed, kept in `draft`.
+        let mut browsing = self.history.len();
+        let mut draft = Vec::new();
+
+        redraw(output, prompt, &line, cursor)?;
+        loop {
+            match read_key(input)? {
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Enter => {
+                    write!(output, "\r\n")?;
+                    output.flush()?;
+                    return Ok(Input::Line(line.into_iter().collect()));
+                }
+                Key::Interrupt => {
+                    write!(output, "^C\r\n")?;
+                    output.flush()?;
+                    return Ok(Input::Interrupted);
+                }
+                Key::Closed if line.is_empty() => return Ok(Input::Eof),
+                Key::Closed => return Ok(Input::Line(line.into_iter().collect())),
+                Key::EndOfInput if line.is_empty() => {
+                    write!(output, "\r\n")?;
+                    output.flush()?;
+                    return Ok(Input::Eof);
+                }
+                Key::EndOfInput | Key::Delete => {
+                    if cursor < line.len() {
+                        line.remove(cursor);
+                    }
+                }
+                Key::Backspace => {
+                    if cursor > 0 {
+                        cursor -= 1;
+                        line.remove(cursor);
+                    }
+                }
+                Key::Left => cursor = cursor.saturating_sub(1),
+                Key::Right => cursor = (cursor + 1).min(line.len()),
+                Key::Home => cursor = 0,
+                Key::End => cursor = line.len(),
+                Key::KillToEnd => line.truncate(cursor),
+                Key::KillToStart => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::Up => {
+                    if browsing == 0 {
+                        continue;
+                    }
+                    if browsing == self.history.len() {
+                        draft = line.clone();
+                    }
+                    browsing -= 1;
+                    line = self.history[browsing].chars().collect();
+                    cursor = line.len();
+                }
+                Key::Down => {
+                    if browsing == self.history.len() {
+                        continue;
+                    }
+                    browsing += 1;
+                    line = match self.history.get(browsing) {
+                        Some(entry) => entry.chars().collect(),
+                        None => draft.clone(),
+                    };
+                    cursor = line.len();
+                }
+                Key::Ignored => continue,
+            }
+            redraw(output, prompt, &line, cursor)?;
+        }
+    }
+}
+
+/// Draws the whole line again and puts the cursor back where it was.
+fn redraw(output: &mut impl Write, prompt: &str, line: &[char], cursor: usize) -> io::Result<()> {
+    let text: String = line.iter().collect();
+    write!(output, "\r{prompt}{text}\x1b[K")?;
+    if cursor < line.len() {
+        write!(output, "\x1b[{}D", line.len() - cursor)?;
+    }
+    output.flush()
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match input.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+fn read_key(input: &mut impl Read) -> io::Result<Key> {
+    let Some(byte) = read_byte(input)? else {
+        return Ok(Key::Closed);
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        0x01 => Key::Home,
+        0x02 => Key::Left,
+        0x03 => Key::Interrupt,
+        0x04 => Key::EndOfInput,
+        0x05 => Key::End,
+        0x06 => Key::Right,
+        0x08 | 0x7f => Key::Backspace,
+        0x0b => Key::KillToEnd,
+        0x0e => Key::Down,
+        0x10 => Key::Up,
+        0x15 => Key::KillToStart,
+        0x1b => read_escape(input)?,
+        0x00..=0x1f => Key::Ignored,
+        _ => {
+            // The rest of a UTF-8 character follows its first byte.
+            let mut bytes = vec![byte];
+            let length = match byte {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            while bytes.len() < length {
+                match read_byte(input)? {
+                    Some(byte) => bytes.push(byte),
+                    None => break,
+                }
+            }
+            match std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(c) => Key::Char(c),
+                None => Key::Ignored,
+            }
+        }
+    };
+    Ok(key)
+}
+
+/// The key sent as `ESC [ ...` or `ESC O ...`, e.g. `ESC [ A` for Up or `ESC [ 3 ~` for Delete.
+fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+    if !matches!(read_byte(input)?, Some(b'[' | b'O')) {
+        return Ok(Key::Ignored);
+    }
+    let mut parameters = String::new();
+    while let Some(byte) = read_byte(input)? {
+        if (0x40..=0x7e).contains(&byte) {
+            let key = match (parameters.as_str(), byte) {
+                ("", b'A') => Key::Up,
+                ("", b'B') => Key::Down,
+                ("", b'C') => Key::Right,
+                ("", b'D') => Key::Left,
+                ("", b'H') | ("1" | "7", b'~') => Key::Home,
+                ("", b'F') | ("4" | "8", b'~') => Key::End,
+                ("3", b'~') => Key::Delete,
+                _ => Key::Ignored,
+            };
+            return Ok(key);
+        }
+        parameters.push(byte as char);
+    }
+    Ok(Key::Ignored)
+}
+
+/// Puts the terminal in raw mode while it is alive, so keys arrive one at a time and unechoed.
+struct RawMode {
+    /// the settings to go back to, as printed by `stty -g`
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        Ok(RawMode {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[self.saved.as_str()]);
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(message));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repl::{is_incomplete, run_code, run_command, Input, LineEditor};
+    use crate::vm::VM;
+    use std::fs;
+    use std::io::Cursor;
+
+    /// Runs each input in turn, code or a command, and returns what the REPL showed.
+    fn session(vm: &mut VM, inputs: &[&str]) -> String {
+        let mut output = Vec::new();
+        for input in inputs {
+            if input.starts_with(':') {
+                run_command(vm, input, &mut output).unwrap();
+            } else {
+                run_code(vm, &input.to_string(), &mut output).unwrap();
+            }
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    /// Types `keys` into an editor with `history` and returns what it read.
+    fn edit(history: &[&str], keys: &[u8]) -> Input {
+        let mut editor = LineEditor::new(None);
+        editor.history = history.iter().map(|line| line.to_string()).collect();
+        let mut output = Vec::new();
+        editor
+            .edit(">> ", &mut Cursor::new(keys.to_vec()), &mut output)
+            .unwrap()
+    }
+
+    fn line(text: &str) -> Input {
+        Input::Line(text.to_string())
+    }
+
+    #[test]
+    fn incomplete_input_test() {
+        let incomplete = |source: &str| is_incomplete(&source.to_string());
+        assert!(incomplete("if (true) {\n"));
+        assert!(incomplete("while (a < (1 +\n"));
+        assert!(incomplete("print \"two\nlines"));
+        assert!(!incomplete("{ print 1; }\n"));
+        assert!(!incomplete("print \"{\"; // {\n"));
+        // Left for the compiler to report.
+        assert!(!incomplete("}\n"));
+    }
+
+    #[test]
+    fn edit_keys_test() {
+        assert_eq!(line("print 1;"), edit(&[], b"print 1;\r"));
+        // Left, delete back, Home, type, End, type.
+        assert_eq!(
+            line("xprint 12;"),
+            edit(&[], b"print 1x2\x1b[D\x7f\x01x\x05;\r")
+        );
+        assert_eq!(line("ab"), edit(&[], b"a_b\x1b[D\x1b[D\x1b[3~\r"));
+        assert_eq!(
+            line("keep"),
+            edit(&[], b"keep this\x02\x02\x02\x02\x02\x0b\r")
+        );
+        assert_eq!(line("π = 3;"), edit(&[], "π = 3;\r".as_bytes()));
+        assert_eq!(Input::Interrupted, edit(&[], b"print \x03"));
+        assert_eq!(Input::Eof, edit(&[], b"\x04"));
+        assert_eq!(Input::Eof, edit(&[], b""));
+        assert_eq!(line("unfinished"), edit(&[], b"unfinished"));
+    }
+
+    #[test]
+    fn edit_history_test() {
+        let history = ["first", "second"];
+        assert_eq!(line("second"), edit(&history, b"\x1b[A\r"));
+        assert_eq!(line("first"), edit(&history, b"\x1b[A\x1b[A\x1b[A\r"));
+        // Down past the newest entry brings back what was being typed.
+        assert_eq!(
+            line("draft"),
+            edit(&history, b"draft\x1b[A\x1b[A\x1b[B\x1b[B\r")
+        );
+        assert_eq!(line("second!"), edit(&history, b"\x10!\r"));
+    }
+
+    #[test]
+    fn history_file_test() {
+        let path = std::env::temp_dir().join(format!("yellowstone_history_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut editor = LineEditor::new(Some(path.clone()));
+        editor.add_history("var a = 1;");
+        editor.add_history("var a = 1;");
+        editor.add_history("   ");
+        editor.add_history("print a;");
+        assert_eq!(
+            vec!["var a = 1;", "print a;"],
+            LineEditor::new(Some(path.clone())).history
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn echo_expressions_test() {
+        let mut vm = VM::default();
+        let shown = session(
+            &mut vm,
+            &[
+                "var a = 1;",
+                "a + 1;",
+                "\"text\";",
+                "print a;",
+                "1; var b = 2;",
+                "a == 1;",
+            ],
+        );
+        assert_eq!("2\n\"text\"\ntrue\n", shown);
+    }
+
+    #[test]
+    fn meta_commands_test() {
+        let mut vm = VM::default();
+        let shown = session(&mut vm, &["var b = \"x\";", "var a = 2;", ":globals"]);
+        assert!(shown.starts_with("a = 2\nb = \"x\"\n"), "{shown}");
+
+        let shown = session(&mut vm, &[":dis a + 1"]);
+        assert!(shown.starts_with("== a + 1 ==\n"), "{shown}");
+        assert!(shown.contains("OP_GET_GLOBAL 0 \"a\""), "{shown}");
+
+        let shown = session(&mut vm, &[":time a * 3"]);
+        assert!(shown.starts_with("6\ntook "), "{shown}");
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/modules/geometry.ys");
+        let shown = session(&mut vm, &[&format!(":load {path}"), "area;"]);
+        assert_eq!("9\n", shown);
+
+        let shown = session(&mut vm, &[":reset", ":globals", ":nope", ":load"]);
+        assert_eq!(
+            "the session was reset\npi = 3.1415927\nunknown command :nope, see :help\n:load needs an argument, see :help\n",
+            shown
+        );
+        assert!(session(&mut vm, &[":help"]).contains(":globals"));
+    }
+}
@@ -20,6 +20,7 @@ pub enum TokenKind {
     TOKEN_SEMICOLON,
     TOKEN_SLASH,
     TOKEN_STAR,
+    TOKEN_PERCENT,
     // One or two character tokens.
     TOKEN_BANG,
     TOKEN_BANG_EQUAL,
@@ -29,6 +30,7 @@ pub enum TokenKind {
     TOKEN_GREATER_EQUAL,
     TOKEN_LESS,
     TOKEN_LESS_EQUAL,
+    TOKEN_STAR_STAR,
     // Literals.
     TOKEN_IDENTIFIER,
     TOKEN_STRING,
@@ -42,6 +44,7 @@ pub enum TokenKind {
     TOKEN_FOR,
     TOKEN_FUN,
     TOKEN_IF,
+    TOKEN_IMPORT,
     TOKEN_NIL,
     TOKEN_OR,
     TOKEN_PRINT,
@@ -56,6 +59,56 @@ pub enum TokenKind {
     TOKEN_EOF,
 }
 
+impl TokenKind {
+    /// Every keyword, in the order they are declared.
+    pub const KEYWORDS: [TokenKind; 18] = [
+        TOKEN_ASSERT_EQ,
+        TOKEN_AND,
+        TOKEN_CLASS,
+        TOKEN_ELSE,
+        TOKEN_FALSE,
+        TOKEN_FOR,
+        TOKEN_FUN,
+        TOKEN_IF,
+        TOKEN_IMPORT,
+        TOKEN_NIL,
+        TOKEN_OR,
+        TOKEN_PRINT,
+        TOKEN_RETURN,
+        TOKEN_SUPER,
+        TOKEN_THIS,
+        TOKEN_TRUE,
+        TOKEN_VAR,
+        TOKEN_WHILE,
+    ];
+
+    /// How the keyword is spelled, `None` for tokens that are not keywords.
+    pub fn keyword(self) -> Option<&'static str> {
+        let keyword = match self {
+            TOKEN_ASSERT_EQ => "assert_eq",
+            TOKEN_AND => "and",
+            TOKEN_CLASS => "class",
+            TOKEN_ELSE => "else",
+            TOKEN_FALSE => "false",
+            TOKEN_FOR => "for",
+            TOKEN_FUN => "fun",
+            TOKEN_IF => "if",
+            TOKEN_IMPORT => "import",
+            TOKEN_NIL => "nil",
+            TOKEN_OR => "or",
+            TOKEN_PRINT => "print",
+            TOKEN_RETURN => "return",
+            TOKEN_SUPER => "super",
+            TOKEN_THIS => "this",
+            TOKEN_TRUE => "true",
+            TOKEN_VAR => "var",
+            TOKEN_WHILE => "while",
+            _ => return None,
+        };
+        Some(keyword)
+    }
+}
+
 impl Display for TokenKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
@@ -66,12 +119,19 @@ impl Display for TokenKind {
 pub struct Token<'source> {
     pub kind: TokenKind,
     pub slice: &'source str,
-    pub line: u8,
+    pub line: usize,
+    /// byte offset of the token in the source
+    pub start: usize,
 }
 
 impl<'source> Token<'source> {
-    pub fn new(kind: TokenKind, slice: &'source str, line: u8) -> Self {
-        Token { kind, slice, line }
+    pub fn new(kind: TokenKind, slice: &'source str, line: usize, start: usize) -> Self {
+        Token {
+            kind,
+            slice,
+            line,
+            start,
+        }
     }
 }
 
@@ -80,7 +140,7 @@ pub struct Scanner<'source> {
     pub start: usize,
     pub current: usize,
     pub source_length: usize,
-    pub line: u8,
+    pub line: usize,
 }
 
 impl<'source> Scanner<'source> {
@@ -94,16 +154,23 @@ impl<'source> Scanner<'source> {
         }
     }
 
+    /// The character at byte `offset` as a slice, `None` at the end of the source. The scanner only
+    /// moves a whole character at a time, so `offset` is always on a character boundary.
+    fn char_at(&self, offset: usize) -> Option<&'source str> {
+        let c = self.source.get(offset..)?.chars().next()?;
+        Some(&self.source[offset..offset + c.len_utf8()])
+    }
+
     fn start(&self) -> &'source str {
-        &self.source[self.start..self.start + 1]
+        self.char_at(self.start).unwrap_or("")
     }
 
     fn start_next(&self) -> &'source str {
-        &self.source[self.start + 1..self.start + 2]
+        self.char_at(self.start + self.start().len()).unwrap_or("")
     }
 
     fn current(&self) -> &'source str {
-        &self.source[self.current..self.current + 1]
+        self.char_at(self.current).unwrap_or("")
     }
 
     pub fn scan_token(&mut self) -> Token<'source> {
@@ -136,20 +203,28 @@ impl<'source> Scanner<'source> {
             "-" => return self.make_token(TOKEN_MINUS),
             "+" => return self.make_token(TOKEN_PLUS),
             "/" => return self.make_token(TOKEN_SLASH),
-            "*" => return self.make_token(TOKEN_STAR),
+            "%" => return self.make_token(TOKEN_PERCENT),
             // optional two character
             "!" => {
                 if self.expect("=") {
                     self.advance();
-                    return self.make_token(TOKEN_BANG_EQUAL);
+                    return self.make_symbol(TOKEN_BANG_EQUAL, 2);
                 } else {
                     return self.make_token(TOKEN_BANG);
                 }
             }
+            "*" => {
+                if self.expect("*") {
+                    self.advance();
+                    return self.make_symbol(TOKEN_STAR_STAR, 2);
+                } else {
+                    return self.make_token(TOKEN_STAR);
+                }
+            }
             "=" => {
                 if self.expect("=") {
                     self.advance();
-                    return self.make_token(TOKEN_EQUAL_EQUAL);
+                    return self.make_symbol(TOKEN_EQUAL_EQUAL, 2);
                 } else {
                     return self.make_token(TOKEN_EQUAL);
                 }
@@ -157,7 +232,7 @@ impl<'source> Scanner<'source> {
             "<" => {
                 if self.expect("=") {
                     self.advance();
-                    return self.make_token(TOKEN_LESS_EQUAL);
+                    return self.make_symbol(TOKEN_LESS_EQUAL, 2);
                 } else {
                     return self.make_token(TOKEN_LESS);
                 }
@@ -165,7 +240,7 @@ impl<'source> Scanner<'source> {
             ">" => {
                 if self.expect("=") {
                     self.advance();
-                    return self.make_token(TOKEN_GREATER_EQUAL);
+                    return self.make_symbol(TOKEN_GREATER_EQUAL, 2);
                 } else {
                     return self.make_token(TOKEN_GREATER);
                 }
@@ -181,17 +256,23 @@ impl<'source> Scanner<'source> {
 
     fn make_token(&self, kind: TokenKind) -> Token<'source> {
         if kind == TokenKind::TOKEN_EOF {
-            return Token::new(kind, "EOF", self.line);
+            return Token::new(kind, "EOF", self.line, self.source_length);
         }
-        let line = self.line;
-        let slice = &self.source[self.start..self.current];
-        Token::new(kind, slice, line)
+        // Single character tokens are made before `current` moves past them.
+        self.make_symbol(kind, (self.current - self.start).max(1))
+    }
+
+    /// Makes a token `length` bytes long from the start of the current token.
+    fn make_symbol(&self, kind: TokenKind, length: usize) -> Token<'source> {
+        let end = (self.start + length).min(self.source_length);
+        let slice = &self.source[self.start..end];
+        Token::new(kind, slice, self.line, self.start)
     }
 
     fn error_token(&self, message: &'source str) -> Token<'source> {
         let kind = TOKEN_ERROR;
         let line = self.line;
-        Token::new(kind, message, line)
+        Token::new(kind, message, line, self.start)
     }
 
     // debug: check for off by one
@@ -200,25 +281,20 @@ impl<'source> Scanner<'source> {
     }
 
     pub fn is_at_peek_next_end(&self) -> bool {
-        self.current + 1 >= self.source_length
+        self.current + self.current().len() >= self.source_length
     }
 
+    /// Moves past the current character, however many bytes it takes, and never past the end.
     pub fn advance(&mut self) {
-        self.current += 1;
+        self.current = (self.current + self.current().len().max(1)).min(self.source_length);
     }
 
     pub fn peek(&self) -> Option<&'source str> {
-        if self.is_at_end() {
-            return None;
-        }
-        Some(&self.source[self.current..self.current + 1])
+        self.char_at(self.current)
     }
 
     pub fn peek_next(&self) -> Option<&'source str> {
-        if self.is_at_peek_next_end() {
-            return None;
-        }
-        Some(&self.source[self.current + 1..self.current + 2])
+        self.char_at(self.current + self.peek()?.len())
     }
 
     pub fn expect(&self, expected: &'source str) -> bool {
@@ -252,7 +328,6 @@ impl<'source> Scanner<'source> {
                     } else {
                         return;
                     }
-                    break;
                 }
                 _ => return,
             }
@@ -343,7 +418,15 @@ impl<'source> Scanner<'source> {
                     }
                 }
             }
-            "i" => return self.check_keyword(1, 1, "f", TOKEN_IF),
+            "i" => {
+                if self.current - self.start > 1 {
+                    match self.start_next() {
+                        "f" => return self.check_keyword(2, 0, "", TOKEN_IF),
+                        "m" => return self.check_keyword(2, 4, "port", TOKEN_IMPORT),
+                        _ => {}
+                    }
+                }
+            }
             "n" => return self.check_keyword(1, 2, "il", TOKEN_NIL),
             "o" => return self.check_keyword(1, 1, "r", TOKEN_OR),
             "p" => return self.check_keyword(1, 4, "rint", TOKEN_PRINT),
@@ -374,7 +457,10 @@ impl<'source> Scanner<'source> {
         kind: TokenKind,
     ) -> TokenKind {
         if self.current - self.start == start + end
-            && the_rest == &self.source[self.start + start..(self.start + start + end)]
+            && self
+                .source
+                .get(self.start + start..self.start + start + end)
+                == Some(the_rest)
         {
             return kind;
         }
@@ -0,0 +1,423 @@
+//! Binary format for compiled chunks, stored in `.ysc` files.
+//!
+//! Every number is little endian and every `usize` operand is stored as a `u32`.
+//!
+//! ```text
+//! magic     "YSC\0"
+//! version   u16
+//! checksum  u64        FNV-1a hash of everything after it
+//! lines     u32 length, then the bytes of `Chunk::lines`
+//! constants u32 count, then one value each
+//! code      u32 count, then one instruction each
+//! ```
+//!
+//! A value is a tag byte followed by its data: `0` nil, `1` bool (one byte), `2` number (`f32`),
+//! `3` string (`u32` length then UTF-8 bytes). An instruction is an opcode byte followed by its
+//! operand, if it has one.
+
+use crate::chunk::Chunk;
+use crate::chunk::OpCode::{self, *};
+use crate::table::fnv1a;
+use crate::value::{allocate_object, Value, ValueKind};
+use anyhow::Result;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+pub const MAGIC: &[u8; 4] = b"YSC\0";
+/// Version 2 writes a `0` entry in the line table for each line without instructions.
+pub const FORMAT_VERSION: u16 = 2;
+/// Extension of files holding a serialized chunk.
+pub const EXTENSION: &str = "ysc";
+
+const HEADER_LEN: usize = 4 + 2 + 8;
+
+const TAG_NIL: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_NUMBER: u8 = 2;
+const TAG_STRING: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatError {
+    /// The file does not start with `MAGIC`, it is not a yellowstone bytecode file.
+    BadMagic,
+    UnsupportedVersion(u16),
+    ChecksumMismatch,
+    UnexpectedEnd,
+    TrailingBytes,
+    UnknownOpcode(u8),
+    UnknownValueTag(u8),
+    InvalidString,
+    InvalidLineTable,
+    /// The chunk holds a value that only exists at runtime, like a native function.
+    UnserializableValue,
+    OperandTooLarge(usize),
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::BadMagic => write!(f, "bytecode error: not a yellowstone bytecode file."),
+            FormatError::UnsupportedVersion(version) => write!(
+                f,
+                "bytecode error: format version {version} is not supported, expected {FORMAT_VERSION}."
+            ),
+            FormatError::ChecksumMismatch => {
+                write!(f, "bytecode error: checksum mismatch, the file is corrupted.")
+            }
+            FormatError::UnexpectedEnd => write!(f, "bytecode error: unexpected end of file."),
+            FormatError::TrailingBytes => write!(f, "bytecode error: unexpected data after chunk."),
+            FormatError::UnknownOpcode(op) => write!(f, "bytecode error: unknown opcode {op}."),
+            FormatError::UnknownValueTag(tag) => write!(f, "bytecode error: unknown value tag {tag}."),
+            FormatError::InvalidString => write!(f, "bytecode error: string is not valid UTF-8."),
+            FormatError::InvalidLineTable => write!(f, "bytecode error: invalid line table."),
+            FormatError::UnserializableValue => {
+                write!(f, "bytecode error: only nil, booleans, numbers and strings can be stored.")
+            }
+            FormatError::OperandTooLarge(operand) => {
+                write!(f, "bytecode error: operand {operand} does not fit in 32 bits.")
+            }
+        }
+    }
+}
+
+impl Error for FormatError {}
+
+impl Chunk {
+    /// Encodes the chunk in the `.ysc` format.
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        write_bytes(&mut payload, self.lines.as_bytes())?;
+
+        write_len(&mut payload, self.constants.len())?;
+        for constant in self.constants.iter() {
+            write_value(&mut payload, constant)?;
+        }
+
+        write_len(&mut payload, self.code.len())?;
+        for op in self.code.iter() {
+            write_op(&mut payload, op)?;
+        }
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&fnv1a(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        Ok(bytes)
+    }
+
+    /// Decodes a chunk written by `Chunk::serialize`, rejecting files of another version and
+    /// files that were truncated or corrupted.
+    pub fn deserialize(bytes: &[u8]) -> Result<Chunk> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(FormatError::BadMagic.into());
+        }
+        let mut reader = Reader::new(&bytes[MAGIC.len()..]);
+        let version = u16::from_le_bytes(reader.array()?);
+        if version != FORMAT_VERSION {
+            return Err(FormatError::UnsupportedVersion(version).into());
+        }
+        let checksum = u64::from_le_bytes(reader.array()?);
+        if checksum != fnv1a(reader.rest()) {
+            return Err(FormatError::ChecksumMismatch.into());
+        }
+
+        let lines = reader.string()?;
+
+        let constant_count = reader.len()?;
+        let mut constants = Vec::new();
+        for _ in 0..constant_count {
+            constants.push(reader.value()?);
+        }
+
+        let code_count = reader.len()?;
+        let mut code = Vec::new();
+        for _ in 0..code_count {
+            code.push(reader.op()?);
+        }
+
+        if !reader.rest().is_empty() {
+            return Err(FormatError::TrailingBytes.into());
+        }
+        if !is_valid_line_table(&lines, code.len()) {
+            return Err(FormatError::InvalidLineTable.into());
+        }
+
+        Ok(Chunk {
+            code,
+            constants,
+            lines,
+            locals: Vec::new(),
+        })
+    }
+}
+
+/// The line table is a list of `<count>_` entries whose counts add up to the number of
+/// instructions.
+fn is_valid_line_table(lines: &str, code_len: usize) -> bool {
+    if !lines.is_empty() && !lines.ends_with('_') {
+        return false;
+    }
+
+    let mut total: usize = 0;
+    for count in lines.split_terminator('_') {
+        match count.parse::<usize>() {
+            Ok(count) => total += count,
+            Err(_) => return false,
+        }
+    }
+    total == code_len
+}
+
+fn write_len(out: &mut Vec<u8>, len: usize) -> Result<()> {
+    let len = u32::try_from(len).map_err(|_| FormatError::OperandTooLarge(len))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) -> Result<()> {
+    write_len(out, bytes.len())?;
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) -> Result<()> {
+    match value.kind() {
+        ValueKind::ValNil => out.push(TAG_NIL),
+        ValueKind::ValBool => {
+            out.push(TAG_BOOL);
+            out.push(value.as_bool().unwrap() as u8);
+        }
+        ValueKind::ValNumber => {
+            out.push(TAG_NUMBER);
+            out.extend_from_slice(&value.as_number().unwrap().to_le_bytes());
+        }
+        ValueKind::ValObj => {
+            let obj = value.as_obj().unwrap();
+            let string = obj.as_str().ok_or(FormatError::UnserializableValue)?;
+            out.push(TAG_STRING);
+            write_bytes(out, string.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn write_op(out: &mut Vec<u8>, op: &OpCode) -> Result<()> {
+    let (code, operand) = match op {
+        OP_CONSTANT(value) => {
+            out.push(0);
+            return write_value(out, value);
+        }
+        OP_NIL => (1, None),
+        OP_TRUE => (2, None),
+        OP_FALSE => (3, None),
+        OP_EQUAL => (4, None),
+        OP_GREATER => (5, None),
+        OP_LESS => (6, None),
+        OP_RETURN => (7, None),
+        OP_DEBUG => (8, None),
+        OP_NEGATE => (9, None),
+        OP_NOT => (10, None),
+        OP_ADD => (11, None),
+        OP_SUBTRACT => (12, None),
+        OP_MULTIPLY => (13, None),
+        OP_DIVIDE => (14, None),
+        OP_MODULO => (15, None),
+        OP_POWER => (16, None),
+        OP_PRINT => (17, None),
+        OP_POP => (18, None),
+        OP_GET_LOCAL(i) => (19, Some(*i)),
+        OP_SET_LOCAL(i) => (20, Some(*i)),
+        OP_DEFINE_GLOBAL(i) => (21, Some(*i)),
+        OP_GET_GLOBAL(i) => (22, Some(*i)),
+        OP_SET_GLOBAL(i) => (23, Some(*i)),
+        OP_ASSERT_EQ => (24, None),
+        OP_CALL(arg_count) => (25, Some(*arg_count)),
+        OP_IMPORT(i) => (26, Some(*i)),
+        OP_GET_PROPERTY(i) => (27, Some(*i)),
+        OP_JUMP_IF_FALSE => (28, None),
+        OP_JUMP => (29, None),
+        OP_PLACEHOLDER_JUMP_AMOUNT => (30, None),
+        OP_JUMP_AMOUNT(amount) => (31, Some(*amount)),
+        OP_LOOP => (32, None),
+        OP_NOT_EQUAL => (33, None),
+        OP_GREATER_EQUAL => (34, None),
+        OP_LESS_EQUAL => (35, None),
+        OP_JUMP_IF_TRUE => (36, None),
+        OP_ASSERT_EQ_MESSAGE => (37, None),
+    };
+    out.push(code);
+    if let Some(operand) = operand {
+        write_len(out, operand)?;
+    }
+    Ok(())
+}
+
+/// Cursor over the bytes of a file being deserialized.
+struct Reader<'bytes> {
+    bytes: &'bytes [u8],
+    position: usize,
+}
+
+impl<'bytes> Reader<'bytes> {
+    fn new(bytes: &'bytes [u8]) -> Self {
+        Reader { bytes, position: 0 }
+    }
+
+    fn rest(&self) -> &'bytes [u8] {
+        &self.bytes[self.position..]
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'bytes [u8], FormatError> {
+        if self.rest().len() < count {
+            return Err(FormatError::UnexpectedEnd);
+        }
+        let taken = &self.bytes[self.position..self.position + count];
+        self.position += count;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], FormatError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn byte(&mut self) -> Result<u8, FormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn len(&mut self) -> Result<usize, FormatError> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    fn string(&mut self) -> Result<String, FormatError> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| FormatError::InvalidString)
+    }
+
+    fn value(&mut self) -> Result<Value, FormatError> {
+        match self.byte()? {
+            TAG_NIL => Ok(Value::nil_value()),
+            TAG_BOOL => Ok(Value::bool_val(self.byte()? != 0)),
+            TAG_NUMBER => Ok(Value::number_value(f32::from_le_bytes(self.array()?))),
+            TAG_STRING => Ok(allocate_object(self.string()?)),
+            tag => Err(FormatError::UnknownValueTag(tag)),
+        }
+    }
+
+    fn op(&mut self) -> Result<OpCode, FormatError> {
+        let op = match self.byte()? {
+            0 => OP_CONSTANT(self.value()?),
+            1 => OP_NIL,
+            2 => OP_TRUE,
+            3 => OP_FALSE,
+            4 => OP_EQUAL,
+            5 => OP_GREATER,
+            6 => OP_LESS,
+            7 => OP_RETURN,
+            8 => OP_DEBUG,
+            9 => OP_NEGATE,
+            10 => OP_NOT,
+            11 => OP_ADD,
+            12 => OP_SUBTRACT,
+            13 => OP_MULTIPLY,
+            14 => OP_DIVIDE,
+            15 => OP_MODULO,
+            16 => OP_POWER,
+            17 => OP_PRINT,
+            18 => OP_POP,
+            19 => OP_GET_LOCAL(self.len()?),
+            20 => OP_SET_LOCAL(self.len()?),
+            21 => OP_DEFINE_GLOBAL(self.len()?),
+            22 => OP_GET_GLOBAL(self.len()?),
+            23 => OP_SET_GLOBAL(self.len()?),
+            24 => OP_ASSERT_EQ,
+            25 => OP_CALL(self.len()?),
+            26 => OP_IMPORT(self.len()?),
+            27 => OP_GET_PROPERTY(self.len()?),
+            28 => OP_JUMP_IF_FALSE,
+            29 => OP_JUMP,
+            30 => OP_PLACEHOLDER_JUMP_AMOUNT,
+            31 => OP_JUMP_AMOUNT(self.len()?),
+            32 => OP_LOOP,
+            33 => OP_NOT_EQUAL,
+            34 => OP_GREATER_EQUAL,
+            35 => OP_LESS_EQUAL,
+            36 => OP_JUMP_IF_TRUE,
+            37 => OP_ASSERT_EQ_MESSAGE,
+            op => return Err(FormatError::UnknownOpcode(op)),
+        };
+        Ok(op)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::compiler::compile;
+    use crate::serialize::{FormatError, FORMAT_VERSION};
+
+    fn compile_str(source: &str) -> Chunk {
+        compile(&source.to_string()).unwrap()
+    }
+
+    fn format_error(bytes: &[u8]) -> FormatError {
+        let error = Chunk::deserialize(bytes).err().unwrap();
+        error.downcast_ref::<FormatError>().unwrap().clone()
+    }
+
+    #[test]
+    fn serialize_round_trip_test() {
+        let chunk = compile_str(
+            "var name = \"yellow\nstone\"; var n = 1.5; if (n > 1) { print name; } var t = nil;",
+        );
+        let bytes = chunk.serialize().unwrap();
+        let loaded = Chunk::deserialize(&bytes).unwrap();
+
+        assert_eq!(chunk.lines, loaded.lines);
+        assert_eq!(chunk.code.len(), loaded.code.len());
+        assert_eq!(chunk.constants.len(), loaded.constants.len());
+        // Serializing again gives the exact same bytes.
+        assert_eq!(bytes, loaded.serialize().unwrap());
+    }
+
+    #[test]
+    fn serialize_bad_magic_test() {
+        assert_eq!(format_error(b"YSX\0"), FormatError::BadMagic);
+        assert_eq!(format_error(b""), FormatError::BadMagic);
+    }
+
+    #[test]
+    fn serialize_wrong_version_test() {
+        let mut bytes = compile_str("1 + 2").serialize().unwrap();
+        bytes[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            format_error(&bytes),
+            FormatError::UnsupportedVersion(FORMAT_VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn serialize_version_1_test() {
+        // Version 1 files skip empty lines in the line table, their lines would be read wrong.
+        let mut bytes = compile_str("1 + 2").serialize().unwrap();
+        bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+        assert_eq!(format_error(&bytes), FormatError::UnsupportedVersion(1));
+    }
+
+    #[test]
+    fn serialize_corrupted_test() {
+        let mut bytes = compile_str("1 + 2").serialize().unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert_eq!(format_error(&bytes), FormatError::ChecksumMismatch);
+    }
+
+    #[test]
+    fn serialize_truncated_test() {
+        let bytes = compile_str("1 + 2").serialize().unwrap();
+        assert_eq!(format_error(&bytes[..8]), FormatError::UnexpectedEnd);
+    }
+}
@@ -0,0 +1,105 @@
+//! Assertions for tests, next to the `assert_eq` statement. Each one takes a message as its last
+//! argument, which replaces the default one when the assertion fails.
+
+use crate::error::InterpretError::{self, *};
+use crate::stdlib::{define_native_optional, number_arg, string_arg};
+use crate::value::{allocate_object, Value};
+use crate::vm::VM;
+use anyhow::{Context, Result};
+
+pub fn define(vm: &mut VM) {
+    define_native_optional(vm, "assert", 1, 1, assert);
+    define_native_optional(vm, "assert_ne", 2, 1, assert_ne);
+    define_native_optional(vm, "assert_approx", 3, 1, assert_approx);
+    define_native_optional(vm, "assert_error", 1, 1, assert_error);
+}
+
+/// Fails when the condition is `false` or `nil`.
+fn assert(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    let message = message_arg("assert", args, 1)?;
+    if VM::is_falsey(&args[0]) {
+        return Err(RUNTIME_ASSERT_ERROR)
+            .context(message.unwrap_or_else(|| "assert failed".to_string()));
+    }
+    Ok(Value::nil_value())
+}
+
+/// Fails when the two values are equal, compared with `==` as `assert_eq` does.
+fn assert_ne(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    let message = message_arg("assert_ne", args, 2)?;
+    if args[0] == args[1] {
+        return Err(RUNTIME_ASSERT_ERROR).context(format!(
+            "{}, left: {}, right: {}",
+            message.unwrap_or_else(|| "assert_ne failed".to_string()),
+            args[0].repr(),
+            args[1].repr()
+        ));
+    }
+    Ok(Value::nil_value())
+}
+
+/// Fails when the two numbers are further apart than the epsilon.
+fn assert_approx(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    let left = number_arg("assert_approx", args, 0)?;
+    let right = number_arg("assert_approx", args, 1)?;
+    let epsilon = number_arg("assert_approx", args, 2)?;
+    let message = message_arg("assert_approx", args, 3)?;
+    if epsilon < 0. {
+        return Err(RUNTIME_ERROR).context(format!(
+            "assert_approx() expects a positive epsilon, got {epsilon}"
+        ));
+    }
+    // A NaN on either side is never close.
+    let distance = (left - right).abs();
+    if distance > epsilon || distance.is_nan() {
+        return Err(RUNTIME_ASSERT_ERROR).context(format!(
+            "{}, left: {left}, right: {right}, epsilon: {epsilon}",
+            message.unwrap_or_else(|| "assert_approx failed".to_string())
+        ));
+    }
+    Ok(Value::nil_value())
+}
+
+/// Runs the code given as a string where the assertion is, and fails unless the code stops with a
+/// runtime error. Returns the error's message, to check with `assert_eq`.
+fn assert_error(vm: &mut VM, args: &[Value]) -> Result<Value> {
+    let code = string_arg("assert_error", args, 0)?;
+    let message = message_arg("assert_error", args, 1)?;
+
+    match vm.evaluate(&code) {
+        Ok(_) => Err(RUNTIME_ASSERT_ERROR).context(format!(
+            "{}, no error from: {}",
+            message.unwrap_or_else(|| "assert_error failed".to_string()),
+            args[0].repr()
+        )),
+        // Code that does not compile, or a script stopped by its limits, is not what is tested.
+        Err(e) if !is_script_error(&e) => Err(e),
+        Err(e) => {
+            let error = allocate_object(e.to_string());
+            vm.track_object(&error);
+            Ok(error)
+        }
+    }
+}
+
+/// Whether the error is one the code itself caused, rather than the compiler or the VM's limits.
+fn is_script_error(error: &anyhow::Error) -> bool {
+    matches!(
+        error.root_cause().downcast_ref::<InterpretError>(),
+        Some(
+            RUNTIME_ERROR
+                | RUNTIME_UNRECOGNIZED_VARIABLE_ERROR
+                | RUNTIME_ASSERT_ERROR
+                | RUNTIME_IMPORT_ERROR
+        )
+    )
+}
+
+/// Reads the optional message argument at `index`, which must be a string when it is given.
+fn message_arg(name: &str, args: &[Value], index: usize) -> Result<Option<String>> {
+    if index < args.len() {
+        string_arg(name, args, index).map(Some)
+    } else {
+        Ok(None)
+    }
+}
@@ -0,0 +1,122 @@
+//! Math library: rounding, trigonometry, exponents and a seedable pseudo random generator.
+
+use crate::stdlib::{define_native, number_arg};
+use crate::value::Value;
+use crate::vm::VM;
+use anyhow::Result;
+
+/// Seed used by every new VM so `random()` is reproducible unless a script reseeds it.
+pub const DEFAULT_SEED: u64 = 0x2545f4914f6cdd1d;
+
+pub fn define(vm: &mut VM) {
+    let _ = vm
+        .table
+        .insert("pi", Value::number_value(std::f32::consts::PI));
+
+    define_native(vm, "sqrt", 1, sqrt);
+    define_native(vm, "pow", 2, pow);
+    define_native(vm, "abs", 1, abs);
+    define_native(vm, "floor", 1, floor);
+    define_native(vm, "ceil", 1, ceil);
+    define_native(vm, "round", 1, round);
+    define_native(vm, "min", 2, min);
+    define_native(vm, "max", 2, max);
+    define_native(vm, "sin", 1, sin);
+    define_native(vm, "cos", 1, cos);
+    define_native(vm, "log", 1, log);
+    define_native(vm, "random", 0, random);
+    define_native(vm, "random_seed", 1, random_seed);
+}
+
+fn sqrt(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    Ok(Value::number_value(number_arg("sqrt", args, 0)?.sqrt()))
+}
+
+fn pow(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    let base = number_arg("pow", args, 0)?;
+    let exponent = number_arg("pow", args, 1)?;
+    Ok(Value::number_value(base.powf(exponent)))
+}
+
+fn abs(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    Ok(Value::number_value(number_arg("abs", args, 0)?.abs()))
+}
+
+fn floor(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    Ok(Value::number_value(number_arg("floor", args, 0)?.floor()))
+}
+
+fn ceil(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    Ok(Value::number_value(number_arg("ceil", args, 0)?.ceil()))
+}
+
+fn round(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    Ok(Value::number_value(number_arg("round", args, 0)?.round()))
+}
+
+fn min(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    let a = number_arg("min", args, 0)?;
+    let b = number_arg("min", args, 1)?;
+    Ok(Value::number_value(a.min(b)))
+}
+
+fn max(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    let a = number_arg("max", args, 0)?;
+    let b = number_arg("max", args, 1)?;
+    Ok(Value::number_value(a.max(b)))
+}
+
+fn sin(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    Ok(Value::number_value(number_arg("sin", args, 0)?.sin()))
+}
+
+fn cos(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    Ok(Value::number_value(number_arg("cos", args, 0)?.cos()))
+}
+
+/// Natural logarithm.
+fn log(_vm: &mut VM, args: &[Value]) -> Result<Value> {
+    Ok(Value::number_value(number_arg("log", args, 0)?.ln()))
+}
+
+/// Returns a number in the range [0, 1).
+fn random(vm: &mut VM, _args: &[Value]) -> Result<Value> {
+    Ok(Value::number_value(vm.random.next_f32()))
+}
+
+fn random_seed(vm: &mut VM, args: &[Value]) -> Result<Value> {
+    let seed = number_arg("random_seed", args, 0)?;
+    vm.random = Random::new(seed.to_bits() as u64);
+    Ok(Value::nil_value())
+}
+
+/// Deterministic xorshift64* generator. Not suitable for anything security related.
+pub struct Random {
+    state: u64,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Random::new(DEFAULT_SEED)
+    }
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck on a zero state.
+        let state = if seed == 0 { DEFAULT_SEED } else { seed };
+        Random { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    /// Uses the top 24 bits so every result is exactly representable as an f32.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
@@ -0,0 +1,65 @@
+//! Native functions and values that are defined in every new VM's globals.
+
+pub mod assert;
+pub mod math;
+pub mod process;
+
+use crate::error::InterpretError::RUNTIME_ERROR;
+use crate::value::{allocate_object, NativeFn, NativeFunction, Value};
+use crate::vm::VM;
+use anyhow::{Context, Result};
+
+/// Defines every standard library module in the VM's globals.
+pub fn define_stdlib(vm: &mut VM) {
+    assert::define(vm);
+    math::define(vm);
+    process::define(vm);
+}
+
+/// Stores a native function in the VM's globals under `name`.
+pub fn define_native(vm: &mut VM, name: &str, arity: usize, function: NativeFn) {
+    define_native_optional(vm, name, arity, 0, function);
+}
+
+/// Stores a native function that takes `arity` arguments and up to `optional` more.
+pub fn define_native_optional(
+    vm: &mut VM,
+    name: &str,
+    arity: usize,
+    optional: usize,
+    function: NativeFn,
+) {
+    let native = allocate_object(NativeFunction {
+        name: name.to_string(),
+        arity,
+        optional,
+        function,
+    });
+    let _ = vm.table.insert(name, native);
+}
+
+/// Reads the argument at `index` as a number, erroring with the native's name otherwise.
+pub(crate) fn number_arg(name: &str, args: &[Value], index: usize) -> Result<f32> {
+    match args.get(index) {
+        Some(arg) if arg.is_number() => Ok(arg.as_number().unwrap()),
+        _ => Err(RUNTIME_ERROR).context(format!(
+            "{name}() expects a number as argument {}",
+            index + 1
+        )),
+    }
+}
+
+/// Reads the argument at `index` as a string, erroring with the native's name otherwise.
+pub(crate) fn string_arg(name: &str, args: &[Value], index: usize) -> Result<String> {
+    let text = args
+        .get(index)
+        .and_then(|arg| arg.as_obj().ok())
+        .and_then(|obj| obj.as_str().map(str::to_string));
+    match text {
+        Some(text) => Ok(text),
+        None => Err(RUNTIME_ERROR).context(format!(
+            "{name}() expects a string as argument {}",
+            index + 1
+        )),
+    }
+}
@@ -0,0 +1,33 @@
+//! What the program was started with: the arguments given after the script on the command line.
+
+use crate::error::InterpretError::RUNTIME_ERROR;
+use crate::stdlib::{define_native, number_arg};
+use crate::value::{allocate_object, Value};
+use crate::vm::VM;
+use anyhow::{Context, Result};
+
+pub fn define(vm: &mut VM) {
+    define_native(vm, "arg_count", 0, arg_count);
+    define_native(vm, "arg", 1, arg);
+}
+
+fn arg_count(vm: &mut VM, _args: &[Value]) -> Result<Value> {
+    Ok(Value::number_value(vm.args.len() as f32))
+}
+
+/// The argument at an index from 0, as a string.
+fn arg(vm: &mut VM, args: &[Value]) -> Result<Value> {
+    let index = number_arg("arg", args, 0)?;
+    let text = match vm.args.get(index as usize) {
+        Some(text) if index.fract() == 0. && index >= 0. => text.clone(),
+        _ => {
+            return Err(RUNTIME_ERROR).context(format!(
+                "arg() expects an index below {}, got {index}",
+                vm.args.len()
+            ))
+        }
+    };
+    let value = allocate_object(text);
+    vm.track_object(&value);
+    Ok(value)
+}
@@ -44,12 +44,12 @@ impl Table {
     }
 
     /// Returns a value given a key.
-    pub fn get(&mut self, key: &str) -> Option<&Value> {
+    pub fn get(&self, key: &str) -> Option<&Value> {
         if self.count == 0 {
             return None;
         }
 
-        let entry = Table::find_entry(&mut self.entries, &key, self.capacity);
+        let entry = Table::find_entry(&self.entries, key, self.capacity);
 
         return match entry {
             Some(i) => match &self.entries.get(i).unwrap() {
@@ -60,12 +60,12 @@ impl Table {
         };
     }
 
-    pub fn get_unchecked(&mut self, key: &str) -> &Value {
+    pub fn get_unchecked(&self, key: &str) -> &Value {
         if self.count == 0 {
             panic!("{key} not found.");
         }
 
-        let entry = Table::find_entry(&mut self.entries, key, self.capacity);
+        let entry = Table::find_entry(&self.entries, key, self.capacity);
 
         return match entry {
             Some(i) => &self.entries.get(i).unwrap().as_ref().unwrap().value,
@@ -115,7 +115,7 @@ impl Table {
 
     /// Finds the first occurrence of the key or the first empty bucket in the hash table with
     /// linear probing.
-    fn find_entry(map: &mut Vec<Option<Entry>>, key: &str, capacity: usize) -> Option<usize> {
+    fn find_entry(map: &[Option<Entry>], key: &str, capacity: usize) -> Option<usize> {
         let hash = fnv1a(key.as_bytes());
         let mut i = index(hash, capacity);
         let start_i = i;
@@ -156,7 +156,7 @@ impl Table {
                     // Only add the entries which are not tombstones.
                     if !e.is_tombstone {
                         // Will always return usize because the array was just initialized.
-                        let destination = Table::find_entry(&mut new, &e.key, self.capacity);
+                        let destination = Table::find_entry(&mut new, &e.key, new_capacity);
                         *new.get_mut(destination.unwrap()).unwrap() = old_entry.take();
                         self.count = self.count + 1;
                     }
@@ -277,6 +277,22 @@ mod tests {
         assert_eq!(None, table.get("bicycle"))
     }
 
+    #[test]
+    fn table_test_grow() {
+        let mut table = Table::default();
+        for i in 0..100 {
+            let _ = table.insert(format!("key{i}"), Value::number_value(i as f32));
+        }
+        for i in 0..100 {
+            let key = format!("key{i}");
+            assert_eq!(
+                table.get(&key),
+                Some(&Value::number_value(i as f32)),
+                "{key}"
+            );
+        }
+    }
+
     #[test]
     fn table_test_integration() {
         let mut table = Table::default();
@@ -319,7 +335,7 @@ mod tests {
         assert_eq!(table.get_unchecked("answer"), &42.);
         assert_eq!(table.get_unchecked("happy?"), &true);
         assert_eq!(table.get_unchecked("name").as_string().unwrap(), "kristoff");
-        assert_eq!(table.get_unchecked("null"), &0.);
+        assert_eq!(table.get_unchecked("null"), &Value::nil_value());
     }
 
     #[test]
@@ -0,0 +1,209 @@
+//! Runs the tests written in scripts, for `yellowstone test`.
+//!
+//! Test files are the scripts given on the command line and the `*_test.ys` files in the
+//! directories given. A file with `test "name" { ... }` blocks holds one test per block: the
+//! file's other statements run first, then the block, in a VM of its own. A file without blocks is
+//! a single test. A test passes when it runs to the end, so every assertion in it held.
+
+use crate::ast::{Stmt, StmtKind};
+use crate::compiler::{compile_program, CompileOptions};
+use crate::parser::parse;
+use crate::vm::VM;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// The name test files end with.
+pub const TEST_SUFFIX: &str = "_test.ys";
+
+#[derive(Debug, Clone, Default)]
+pub struct TestOptions {
+    /// only run the tests whose name contains this
+    pub filter: Option<String>,
+    pub compile_options: CompileOptions,
+}
+
+/// A test that ran and what became of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    /// `path` for a file that is a test, `path::name` for a test block
+    pub name: String,
+    /// why the test failed, `None` when it passed
+    pub failure: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    /// tests left out by `TestOptions::filter`
+    pub filtered: usize,
+}
+
+impl Summary {
+    pub fn is_ok(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+/// The test files in `paths`, sorted: the files themselves and the `*_test.ys` files under the
+/// directories.
+pub fn discover(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let mut dirs = vec![path.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.to_string_lossy().ends_with(TEST_SUFFIX) {
+                    files.push(path);
+                }
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Runs the tests in the file at `path` that `options` selects. Tests left out by the filter are
+/// counted in the returned number.
+pub fn run_file(path: &Path, options: &TestOptions) -> (Vec<TestResult>, usize) {
+    let file = path.display().to_string();
+    let program = fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {file}: {e}"))
+        .and_then(|source| {
+            parse(&source).map_err(|errors| {
+                let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                errors.join("\n")
+            })
+        });
+    let program = match program {
+        Ok(program) => program,
+        Err(failure) => {
+            let result = TestResult {
+                name: file,
+                failure: Some(failure),
+            };
+            return (vec![result], 0);
+        }
+    };
+
+    let tests: Vec<(usize, String)> = program
+        .iter()
+        .enumerate()
+        .filter_map(|(index, statement)| match &statement.kind {
+            StmtKind::Test { name, .. } => Some((index, format!("{file}::{name}"))),
+            _ => None,
+        })
+        .collect();
+    let tests = if tests.is_empty() {
+        vec![(usize::MAX, file)]
+    } else {
+        tests
+    };
+
+    let mut results = Vec::new();
+    let mut filtered = 0;
+    for (index, name) in tests {
+        if options
+            .filter
+            .as_ref()
+            .is_some_and(|filter| !name.contains(filter.as_str()))
+        {
+            filtered += 1;
+            continue;
+        }
+        let failure = run_test(path, &program, index, options.compile_options).err();
+        results.push(TestResult { name, failure });
+    }
+    (results, filtered)
+}
+
+/// Runs `program` with the test at `index` in place of its declaration, the other tests are left
+/// out.
+fn run_test(
+    path: &Path,
+    program: &[Stmt],
+    index: usize,
+    options: CompileOptions,
+) -> Result<(), String> {
+    let program: Vec<Stmt> = program
+        .iter()
+        .enumerate()
+        .map(|(at, statement)| match &statement.kind {
+            StmtKind::Test { body, .. } if at == index => (**body).clone(),
+            _ => statement.clone(),
+        })
+        .collect();
+    let chunk = compile_program(&program, options).map_err(|e| format!("{e:#}"))?;
+
+    let mut vm = VM {
+        compile_options: options,
+        ..VM::default()
+    };
+    // Imports are found next to the test file.
+    vm.import_stack
+        .push(path.canonicalize().unwrap_or(path.to_path_buf()));
+    let result = vm.interpret_chunk(chunk);
+    vm.import_stack.pop();
+
+    result.map(|_| ()).map_err(|e| {
+        // The instruction that failed is the one before `ip`.
+        let line = vm.chunk.line(vm.ip.saturating_sub(1));
+        format!("[line {line}] {e:#}")
+    })
+}
+
+/// Runs every test in `paths`, reporting each one and a summary on `output`.
+pub fn run(
+    paths: &[PathBuf],
+    options: &TestOptions,
+    output: &mut impl Write,
+) -> io::Result<Summary> {
+    let start = Instant::now();
+    let files = discover(paths)?;
+    let mut summary = Summary::default();
+    let mut failures = Vec::new();
+
+    for file in &files {
+        let (results, filtered) = run_file(file, options);
+        summary.filtered += filtered;
+        for result in results {
+            match result.failure {
+                None => {
+                    summary.passed += 1;
+                    writeln!(output, "test {} ... ok", result.name)?;
+                }
+                Some(failure) => {
+                    summary.failed += 1;
+                    writeln!(output, "test {} ... FAILED", result.name)?;
+                    failures.push((result.name, failure));
+                }
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        writeln!(output, "\nfailures:")?;
+        for (name, failure) in &failures {
+            writeln!(output, "---- {name} ----\n{failure}")?;
+        }
+    }
+    writeln!(
+        output,
+        "\ntest result: {}. {} passed; {} failed; {} filtered out; finished in {:.2?}",
+        if summary.is_ok() { "ok" } else { "FAILED" },
+        summary.passed,
+        summary.failed,
+        summary.filtered,
+        start.elapsed()
+    )?;
+    Ok(summary)
+}
@@ -7,5 +7,9 @@ pub fn is_alpha(c: &str) -> bool {
 }
 
 pub fn grow_capacity(capacity: usize) -> usize {
-    return if capacity <= 1 { 8 } else { capacity * 2 };
+    if capacity <= 1 {
+        8
+    } else {
+        capacity * 2
+    }
 }
@@ -1,23 +1,28 @@
 use crate::error::InterpretError;
+use crate::table::{fnv1a, Table};
+use crate::vm::VM;
+use anyhow::Context;
 use std::fmt::{Debug, Display, Formatter};
-use std::mem::ManuallyDrop;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::PathBuf;
 use std::rc::Rc;
 
-#[repr(C)]
-pub struct Value {
-    pub kind: ValueKind,
-    u: ValueUnion,
-}
+// The layout of `Value`, chosen with the `safe-value` feature. Both have the same API.
+#[cfg(feature = "safe-value")]
+mod safe;
+#[cfg(not(feature = "safe-value"))]
+mod union;
+#[cfg(feature = "safe-value")]
+pub use safe::Value;
+#[cfg(not(feature = "safe-value"))]
+pub use union::Value;
 
-#[repr(C)]
-pub union ValueUnion {
-    f: f32,
-    b: bool,
-    o: ManuallyDrop<Rc<dyn ObjectHandler>>,
-}
+/// A reference to an object, shared by the values that hold it.
+pub type ObjRef = Rc<dyn ObjectHandler>;
 
 #[repr(u32)]
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ValueKind {
     ValBool,
     ValNil,
@@ -38,34 +43,50 @@ impl Display for ValueKind {
     }
 }
 
-impl Clone for Value {
-    fn clone(&self) -> Self {
-        match self.kind {
-            ValueKind::ValBool => {
-                let b = self.as_bool().unwrap();
-                Value {
-                    kind: ValueKind::ValBool,
-                    u: ValueUnion { b },
+/// Values are equal as `==` compares them in scripts: numbers as IEEE floats, so `NaN` is not
+/// equal to itself and `0` equals `-0`, booleans and `nil` by value, strings by their text and
+/// other objects, natives and modules, only to themselves. Values of different kinds are never
+/// equal. `Value` is not `Eq` because of `NaN`.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        if self.kind() != other.kind() {
+            return false;
+        }
+
+        match self.kind() {
+            ValueKind::ValNil => true,
+            ValueKind::ValBool => self.as_bool() == other.as_bool(),
+            ValueKind::ValNumber => self.as_number() == other.as_number(),
+            ValueKind::ValObj => {
+                let a = self.as_obj().unwrap();
+                let b = other.as_obj().unwrap();
+                match (a.as_str(), b.as_str()) {
+                    (Some(a), Some(b)) => a == b,
+                    (None, None) => std::ptr::addr_eq(Rc::as_ptr(&a), Rc::as_ptr(&b)),
+                    _ => false,
                 }
             }
-            ValueKind::ValNil => Value {
-                kind: ValueKind::ValNil,
-                u: ValueUnion { f: 0. },
-            },
+        }
+    }
+}
+
+/// Consistent with `PartialEq`: strings hash their text as the globals table hashes its keys,
+/// `0` and `-0` hash the same and other objects hash their address.
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.kind() {
+            ValueKind::ValNil => 0u8.hash(state),
+            ValueKind::ValBool => (1u8, self.as_bool().unwrap()).hash(state),
             ValueKind::ValNumber => {
-                let f = self.as_number().unwrap();
-                Value {
-                    kind: ValueKind::ValNumber,
-                    u: ValueUnion { f },
-                }
+                let number = self.as_number().unwrap();
+                let number = if number == 0. { 0f32 } else { number };
+                (2u8, number.to_bits()).hash(state)
             }
             ValueKind::ValObj => {
-                let o = self.as_obj().unwrap();
-                Value {
-                    kind: ValueKind::ValObj,
-                    u: ValueUnion {
-                        o: ManuallyDrop::new(o),
-                    },
+                let obj = self.as_obj().unwrap();
+                match obj.as_str() {
+                    Some(text) => (3u8, fnv1a(text.as_bytes())).hash(state),
+                    None => (4u8, Rc::as_ptr(&obj) as *const () as usize).hash(state),
                 }
             }
         }
@@ -74,265 +95,125 @@ impl Clone for Value {
 
 impl PartialEq<str> for Value {
     fn eq(&self, other: &str) -> bool {
-        let conv = self.as_string().unwrap();
-        // todo: reason out the extra " " around the word
-
-        &conv.as_str()[1..conv.len() - 1] == other
+        self.as_obj()
+            .is_ok_and(|obj| obj.as_str().is_some_and(|text| text == other))
     }
 }
 
 impl PartialEq<bool> for Value {
     fn eq(&self, other: &bool) -> bool {
-        let conv = self.as_bool().unwrap();
-        conv == *other
+        self.as_bool() == Ok(*other)
     }
 }
 
 impl PartialEq<f32> for Value {
     fn eq(&self, other: &f32) -> bool {
-        let conv = match self.kind {
-            ValueKind::ValNil => self.as_nil().unwrap(),
-            ValueKind::ValNumber => self.as_number().unwrap(),
-            _ => return false,
-        };
-        conv == *other
+        self.as_number() == Ok(*other)
     }
 }
 
 impl PartialEq<Value> for str {
     fn eq(&self, other: &Value) -> bool {
-        let other = other.as_string().unwrap();
-        // todo: reason out the extra " " around the word
-
-        self == &other.as_str()[1..other.len() - 1]
+        other == self
     }
 }
 
 impl PartialEq<Value> for f32 {
     fn eq(&self, other: &Value) -> bool {
-        let conv = other.as_number().unwrap();
-        *self == conv
+        other == self
     }
 }
 
 impl PartialEq<Value> for bool {
     fn eq(&self, other: &Value) -> bool {
-        let conv = other.as_bool().unwrap();
-        *self == conv
+        other == self
     }
 }
 
-impl PartialEq for Value {
-    fn eq(&self, other: &Self) -> bool {
-        return if self.is_number() && other.is_number() {
-            let a = self.as_number().unwrap();
-            let b = self.as_number().unwrap();
-            a == b
-        } else if self.is_nil() && other.is_nil() {
-            let a = self.as_nil().unwrap();
-            let b = self.as_nil().unwrap();
-            a == b
-        } else if self.is_bool() && other.is_number() {
-            let a = self.as_bool().unwrap();
-            let b = self.as_bool().unwrap();
-            a == b
-        } else if self.is_obj() && other.is_obj() {
-            //let a = self.as_obj().unwrap();
-            //let b = self.as_obj().unwrap();
-            //a == b
-            todo!("no mechanism to compare raw objects yet")
-        } else {
-            false
-        };
+impl Display for Value {
+    /// The value as `print` shows it: `nil`, `true`, numbers without a trailing `.0`, strings as
+    /// their text and objects such as `<native fn sqrt>` by kind and name.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.kind() {
+            ValueKind::ValNil => write!(f, "nil"),
+            ValueKind::ValBool => write!(f, "{}", self.as_bool().unwrap()),
+            ValueKind::ValNumber => write!(f, "{}", self.as_number().unwrap()),
+            ValueKind::ValObj => {
+                let obj = self.as_obj().unwrap();
+                match obj.as_str() {
+                    Some(text) => write!(f, "{text}"),
+                    None => write!(f, "{}", obj.to_string()),
+                }
+            }
+        }
     }
 }
 
+/// Same as `Value::repr`.
 impl Debug for Value {
-    fn fmt(&self, fmtr: &mut Formatter<'_>) -> std::fmt::Result {
-        unsafe {
-            match self {
-                Value {
-                    kind: ValueKind::ValBool,
-                    u: ValueUnion { b },
-                } => write!(fmtr, "{b}"),
-                Value {
-                    kind: ValueKind::ValNil,
-                    u: ValueUnion { f },
-                } => write!(fmtr, "{f}"),
-                Value {
-                    kind: ValueKind::ValNumber,
-                    u: ValueUnion { f },
-                } => write!(fmtr, "{f}"),
-                Value {
-                    kind: ValueKind::ValObj,
-                    u: ValueUnion { o },
-                } => write!(fmtr, "{}", o.to_string()),
-            }
-        }
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.repr())
     }
 }
 
 impl Value {
+    /// Whether the values are equal, as `==` compares them, see `PartialEq`.
     pub fn values_equal(a: Value, b: Value) -> bool {
-        if a.kind != b.kind {
-            return false;
-        }
-
-        match a.kind {
-            ValueKind::ValBool => Value::as_bool(&a) == Value::as_bool(&b),
-            ValueKind::ValNil => true,
-            ValueKind::ValNumber => Value::as_number(&a) == Value::as_number(&b),
-            ValueKind::ValObj => {
-                let string_a = a.as_string().unwrap();
-                let string_b = b.as_string().unwrap();
-
-                string_a == string_b
-            }
-        }
-    }
-
-    pub fn print(&self) {
-        match self.kind {
-            ValueKind::ValBool => {
-                println!("{}", self.as_bool().unwrap())
-            }
-            ValueKind::ValNil => {
-                println!("{}", self.as_nil().unwrap())
-            }
-            ValueKind::ValNumber => {
-                println!("{}", self.as_number().unwrap())
-            }
-            ValueKind::ValObj => {
-                println!("{:?}", self.as_obj())
-            }
-        }
-    }
-
-    // instantiate a Value from a Rust primitive
-    // primitive -> Value
-    pub fn bool_val(b: bool) -> Self {
-        Self {
-            kind: ValueKind::ValBool,
-            u: ValueUnion { b },
-        }
-    }
-
-    pub fn nil_value() -> Self {
-        Self {
-            kind: ValueKind::ValNil,
-            u: ValueUnion { f: 0. },
-        }
-    }
-
-    pub fn number_value(num: f32) -> Self {
-        Self {
-            kind: ValueKind::ValNumber,
-            u: ValueUnion { f: num },
-        }
-    }
-
-    fn obj_value(o: Rc<dyn ObjectHandler>) -> Self {
-        Self {
-            kind: ValueKind::ValObj,
-            u: ValueUnion {
-                o: ManuallyDrop::new(Rc::clone(&o)),
-            },
-        }
-    }
-
-    // read the rust value from the Value struct
-    // Value -> primitive
-    pub fn as_bool(&self) -> Result<bool, InterpretError> {
-        if self.is_bool() {
-            unsafe { Ok(self.u.b) }
-        } else {
-            Err(InterpretError::RUNTIME_ERROR)
-        }
+        a == b
     }
 
-    pub fn as_nil(&self) -> Result<f32, InterpretError> {
-        if self.is_nil() {
-            unsafe { Ok(self.u.f) }
-        } else {
-            Err(InterpretError::RUNTIME_ERROR)
-        }
-    }
-
-    pub fn as_number(&self) -> Result<f32, InterpretError> {
-        if self.is_number() {
-            unsafe { Ok(self.u.f) }
-        } else {
-            Err(InterpretError::RUNTIME_ERROR)
-        }
+    /// Writes the value on a line of its own, as the `print` statement shows it.
+    pub fn print(&self, output: &mut dyn Write) -> io::Result<()> {
+        writeln!(output, "{self}")
     }
 
-    pub fn as_obj(&self) -> Result<Rc<dyn ObjectHandler>, InterpretError> {
-        if self.is_obj() {
-            unsafe { Ok(Rc::clone(&self.u.o)) }
-        } else {
-            Err(InterpretError::RUNTIME_ERROR)
+    /// The value as it is written in source code, shown by the REPL, the debugger, listings and
+    /// assertion failures: the same as `Display`, except that strings are in double quotes with
+    /// `\"`, `\\`, `\n`, `\r` and `\t` escaped.
+    pub fn repr(&self) -> String {
+        let text = match self
+            .as_obj()
+            .ok()
+            .and_then(|obj| obj.as_str().map(str::to_string))
+        {
+            Some(text) => text,
+            None => return self.to_string(),
+        };
+        let mut quoted = String::from('"');
+        for c in text.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c => quoted.push(c),
+            }
         }
+        quoted.push('"');
+        quoted
     }
 
-    pub fn as_string(&self) -> Result<String, InterpretError> {
+    /// The text of a string, or an object as `print` shows it.
+    pub fn as_string(&self) -> anyhow::Result<String> {
         if self.is_obj() {
-            let obj = self.as_obj().unwrap();
-            Ok(obj.to_string().as_str().trim_matches('\"').to_string())
+            Ok(self.to_string())
         } else {
-            eprintln!("Error: cannot convert type to a string. It is not an object.");
             Err(InterpretError::RUNTIME_ERROR)
+                .context("cannot convert type to a string, it is not an object")
         }
     }
 
-    // check the kind of a value and return true or false
-    pub fn is_bool(&self) -> bool {
-        unsafe {
-            matches!(
-                self,
-                Value {
-                    kind: ValueKind::ValBool,
-                    u: ValueUnion { b: _b },
-                }
-            )
-        }
-    }
-
-    pub fn is_nil(&self) -> bool {
-        matches!(
-            self,
-            Value {
-                kind: ValueKind::ValNil,
-                u: ValueUnion { f: _ },
-            }
-        )
-    }
-
-    pub fn is_number(&self) -> bool {
-        unsafe {
-            matches!(
-                self,
-                Value {
-                    kind: ValueKind::ValNumber,
-                    u: ValueUnion { f: _f },
-                }
-            )
-        }
+    pub fn is_string(value: &Value) -> bool {
+        Value::is_obj_kind(value, ObjKind::OBJ_STRING)
     }
 
-    pub fn is_obj(&self) -> bool {
-        unsafe {
-            matches!(
-                self,
-                Value {
-                    kind: ValueKind::ValObj,
-                    u: ValueUnion { o: _o },
-                }
-            )
-        }
+    pub fn is_native(value: &Value) -> bool {
+        Value::is_obj_kind(value, ObjKind::OBJ_NATIVE)
     }
 
-    pub fn is_string(value: &Value) -> bool {
-        Value::is_obj_kind(value, ObjKind::OBJ_STRING)
+    pub fn is_module(value: &Value) -> bool {
+        Value::is_obj_kind(value, ObjKind::OBJ_MODULE)
     }
 
     fn is_obj_kind(value: &Value, obj_kind: ObjKind) -> bool {
@@ -351,17 +232,31 @@ where
     T: ObjectHandler + 'static,
 {
     let rc = Rc::new(data);
-    let obj = Value::obj_value(rc);
-
-    obj
+    Value::obj_value(rc)
 }
 
 pub trait ObjectHandler: std::fmt::Debug {
     fn kind(self: Rc<Self>) -> ObjKind;
 
+    /// The object as `print` shows it, strings excepted: their text is read with `as_str`.
     fn to_string(&self) -> String {
         format!("{self:?}")
     }
+
+    /// Returns the text of a string object.
+    fn as_str(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the native function wrapped by the object, if the object is one.
+    fn as_native(&self) -> Option<&NativeFunction> {
+        None
+    }
+
+    /// Returns the module wrapped by the object, if the object is one.
+    fn as_module(&self) -> Option<&Module> {
+        None
+    }
 }
 
 // ##############################################################
@@ -372,16 +267,78 @@ pub trait ObjectHandler: std::fmt::Debug {
 #[derive(PartialOrd, PartialEq, Debug)]
 pub enum ObjKind {
     OBJ_STRING,
+    OBJ_NATIVE,
+    OBJ_MODULE,
 }
 
 impl ObjectHandler for String {
     fn kind(self: Rc<Self>) -> ObjKind {
         ObjKind::OBJ_STRING
     }
+
+    fn as_str(&self) -> Option<&str> {
+        Some(self)
+    }
 }
 
 impl ObjectHandler for &str {
     fn kind(self: Rc<Self>) -> ObjKind {
         ObjKind::OBJ_STRING
     }
+
+    fn as_str(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+/// Signature of a function implemented in Rust and callable from yellowstone code. The arguments
+/// are the values passed at the call site, in order.
+pub type NativeFn = fn(&mut VM, &[Value]) -> anyhow::Result<Value>;
+
+/// A function implemented in Rust, stored in the VM's globals like any other value.
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: usize,
+    /// arguments after the first `arity` ones that callers can leave out
+    pub optional: usize,
+    pub function: NativeFn,
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl ObjectHandler for NativeFunction {
+    fn kind(self: Rc<Self>) -> ObjKind {
+        ObjKind::OBJ_NATIVE
+    }
+
+    fn as_native(&self) -> Option<&NativeFunction> {
+        Some(self)
+    }
+}
+
+/// An imported script. Holds the globals the script defined, which are read with `module.name`.
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+    pub globals: Table,
+}
+
+impl Debug for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+impl ObjectHandler for Module {
+    fn kind(self: Rc<Self>) -> ObjKind {
+        ObjKind::OBJ_MODULE
+    }
+
+    fn as_module(&self) -> Option<&Module> {
+        Some(self)
+    }
 }
@@ -0,0 +1,88 @@
+//! The value layout used with the `safe-value` feature: an enum, without any `unsafe`, so the VM
+//! can run under Miri. Values release their reference count when dropped, but `VM::objects` still
+//! holds one for every object, as in `union`, so objects live until `VM::free_objects`. Numbers
+//! stay `f32`, as in `union`, so both layouts compute the same results.
+
+use crate::error::InterpretError;
+use crate::value::{ObjRef, ValueKind};
+use std::rc::Rc;
+
+#[derive(Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f32),
+    Obj(ObjRef),
+}
+
+impl Value {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::Nil => ValueKind::ValNil,
+            Value::Bool(_) => ValueKind::ValBool,
+            Value::Number(_) => ValueKind::ValNumber,
+            Value::Obj(_) => ValueKind::ValObj,
+        }
+    }
+
+    pub fn bool_val(b: bool) -> Self {
+        Value::Bool(b)
+    }
+
+    pub fn nil_value() -> Self {
+        Value::Nil
+    }
+
+    pub fn number_value(num: f32) -> Self {
+        Value::Number(num)
+    }
+
+    pub(crate) fn obj_value(o: ObjRef) -> Self {
+        Value::Obj(o)
+    }
+
+    pub fn as_bool(&self) -> Result<bool, InterpretError> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(InterpretError::RUNTIME_ERROR),
+        }
+    }
+
+    /// `nil` reads as `0`, as it does in `union`.
+    pub fn as_nil(&self) -> Result<f32, InterpretError> {
+        match self {
+            Value::Nil => Ok(0.),
+            _ => Err(InterpretError::RUNTIME_ERROR),
+        }
+    }
+
+    pub fn as_number(&self) -> Result<f32, InterpretError> {
+        match self {
+            Value::Number(num) => Ok(*num),
+            _ => Err(InterpretError::RUNTIME_ERROR),
+        }
+    }
+
+    pub fn as_obj(&self) -> Result<ObjRef, InterpretError> {
+        match self {
+            Value::Obj(o) => Ok(Rc::clone(o)),
+            _ => Err(InterpretError::RUNTIME_ERROR),
+        }
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Value::Bool(_))
+    }
+
+    pub fn is_nil(&self) -> bool {
+        matches!(self, Value::Nil)
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, Value::Number(_))
+    }
+
+    pub fn is_obj(&self) -> bool {
+        matches!(self, Value::Obj(_))
+    }
+}
@@ -0,0 +1,174 @@
+//! The value layout used by default: a C-style tagged union, as in clox. Reading the union is
+//! `unsafe`, guarded by `kind`, and an object's reference count is never released, so its objects
+//! are owned by `VM::objects`. See `safe` for the layout without `unsafe`.
+
+use crate::error::InterpretError;
+use crate::value::{ObjRef, ValueKind};
+use std::mem::ManuallyDrop;
+use std::rc::Rc;
+
+#[repr(C)]
+pub struct Value {
+    kind: ValueKind,
+    u: ValueUnion,
+}
+
+#[repr(C)]
+union ValueUnion {
+    f: f32,
+    b: bool,
+    o: ManuallyDrop<ObjRef>,
+}
+
+impl Clone for Value {
+    fn clone(&self) -> Self {
+        match self.kind {
+            ValueKind::ValBool => {
+                let b = self.as_bool().unwrap();
+                Value {
+                    kind: ValueKind::ValBool,
+                    u: ValueUnion { b },
+                }
+            }
+            ValueKind::ValNil => Value {
+                kind: ValueKind::ValNil,
+                u: ValueUnion { f: 0. },
+            },
+            ValueKind::ValNumber => {
+                let f = self.as_number().unwrap();
+                Value {
+                    kind: ValueKind::ValNumber,
+                    u: ValueUnion { f },
+                }
+            }
+            ValueKind::ValObj => {
+                let o = self.as_obj().unwrap();
+                Value {
+                    kind: ValueKind::ValObj,
+                    u: ValueUnion {
+                        o: ManuallyDrop::new(o),
+                    },
+                }
+            }
+        }
+    }
+}
+
+impl Value {
+    pub fn kind(&self) -> ValueKind {
+        self.kind
+    }
+
+    // instantiate a Value from a Rust primitive
+    // primitive -> Value
+    pub fn bool_val(b: bool) -> Self {
+        Self {
+            kind: ValueKind::ValBool,
+            u: ValueUnion { b },
+        }
+    }
+
+    pub fn nil_value() -> Self {
+        Self {
+            kind: ValueKind::ValNil,
+            u: ValueUnion { f: 0. },
+        }
+    }
+
+    pub fn number_value(num: f32) -> Self {
+        Self {
+            kind: ValueKind::ValNumber,
+            u: ValueUnion { f: num },
+        }
+    }
+
+    pub(crate) fn obj_value(o: ObjRef) -> Self {
+        Self {
+            kind: ValueKind::ValObj,
+            u: ValueUnion {
+                o: ManuallyDrop::new(Rc::clone(&o)),
+            },
+        }
+    }
+
+    // read the rust value from the Value struct
+    // Value -> primitive
+    pub fn as_bool(&self) -> Result<bool, InterpretError> {
+        if self.is_bool() {
+            unsafe { Ok(self.u.b) }
+        } else {
+            Err(InterpretError::RUNTIME_ERROR)
+        }
+    }
+
+    pub fn as_nil(&self) -> Result<f32, InterpretError> {
+        if self.is_nil() {
+            unsafe { Ok(self.u.f) }
+        } else {
+            Err(InterpretError::RUNTIME_ERROR)
+        }
+    }
+
+    pub fn as_number(&self) -> Result<f32, InterpretError> {
+        if self.is_number() {
+            unsafe { Ok(self.u.f) }
+        } else {
+            Err(InterpretError::RUNTIME_ERROR)
+        }
+    }
+
+    pub fn as_obj(&self) -> Result<ObjRef, InterpretError> {
+        if self.is_obj() {
+            unsafe { Ok(Rc::clone(&self.u.o)) }
+        } else {
+            Err(InterpretError::RUNTIME_ERROR)
+        }
+    }
+
+    // check the kind of a value and return true or false
+    pub fn is_bool(&self) -> bool {
+        unsafe {
+            matches!(
+                self,
+                Value {
+                    kind: ValueKind::ValBool,
+                    u: ValueUnion { b: _b },
+                }
+            )
+        }
+    }
+
+    pub fn is_nil(&self) -> bool {
+        matches!(
+            self,
+            Value {
+                kind: ValueKind::ValNil,
+                u: ValueUnion { f: _ },
+            }
+        )
+    }
+
+    pub fn is_number(&self) -> bool {
+        unsafe {
+            matches!(
+                self,
+                Value {
+                    kind: ValueKind::ValNumber,
+                    u: ValueUnion { f: _f },
+                }
+            )
+        }
+    }
+
+    pub fn is_obj(&self) -> bool {
+        unsafe {
+            matches!(
+                self,
+                Value {
+                    kind: ValueKind::ValObj,
+                    u: ValueUnion { o: _o },
+                }
+            )
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Checks that a chunk is safe to run before the VM executes it. Chunks can come from `.ysc`
+//! files written by anyone, and the VM trusts its bytecode: a jump past the end of the code or a
+//! pop from an empty stack would panic the host instead of failing the script.
+
+use crate::chunk::Chunk;
+use crate::chunk::OpCode::{self, *};
+use crate::error::InterpretError::VERIFY_ERROR;
+use crate::value::Value;
+use anyhow::{Context, Result};
+
+/// Rejects the chunk unless every jump lands on an instruction, every constant and local index is
+/// in range, no path through the code pops more values than it pushed, and the code ends with
+/// `OP_RETURN`.
+pub fn verify(chunk: &Chunk) -> Result<()> {
+    let code = &chunk.code;
+    if !matches!(code.last(), Some(OP_RETURN)) {
+        return invalid("the chunk does not end with OP_RETURN".to_string());
+    }
+
+    let boundaries = verify_instructions(chunk)?;
+    verify_stack(code, &boundaries)
+}
+
+fn invalid(message: String) -> Result<()> {
+    Err(VERIFY_ERROR).context(format!("invalid chunk: {message}"))
+}
+
+/// Checks each instruction on its own and returns which offsets start an instruction. Jumps take
+/// two slots, the jump itself and the `OP_JUMP_AMOUNT` after it.
+fn verify_instructions(chunk: &Chunk) -> Result<Vec<bool>> {
+    let code = &chunk.code;
+    let mut boundaries = vec![false; code.len()];
+    let mut offset = 0;
+
+    while offset < code.len() {
+        boundaries[offset] = true;
+        match &code[offset] {
+            OP_JUMP | OP_JUMP_IF_FALSE | OP_JUMP_IF_TRUE | OP_LOOP => {
+                match code.get(offset + 1) {
+                    Some(OP_JUMP_AMOUNT(_)) => {}
+                    Some(OP_PLACEHOLDER_JUMP_AMOUNT) => {
+                        invalid(format!("the jump at offset {offset} was never patched"))?
+                    }
+                    _ => invalid(format!(
+                        "the jump at offset {offset} is not followed by a jump amount"
+                    ))?,
+                }
+                offset += 2;
+                continue;
+            }
+            OP_JUMP_AMOUNT(_) | OP_PLACEHOLDER_JUMP_AMOUNT => invalid(format!(
+                "the jump amount at offset {offset} does not follow a jump"
+            ))?,
+            OP_DEBUG => invalid(format!("OP_DEBUG at offset {offset} cannot be executed"))?,
+            OP_DEFINE_GLOBAL(index)
+            | OP_GET_GLOBAL(index)
+            | OP_SET_GLOBAL(index)
+            | OP_IMPORT(index)
+            | OP_GET_PROPERTY(index) => match chunk.constants.get(*index) {
+                Some(name) if Value::is_string(name) => {}
+                Some(_) => invalid(format!(
+                    "constant {index} used as a name at offset {offset} is not a string"
+                ))?,
+                None => invalid(format!(
+                    "constant {index} at offset {offset} is out of range ({} constants)",
+                    chunk.constants.len()
+                ))?,
+            },
+            // Locals live in the constant table.
+            OP_GET_LOCAL(index) | OP_SET_LOCAL(index) if *index >= chunk.constants.len() => {
+                invalid(format!(
+                    "local slot {index} at offset {offset} is out of range ({} slots)",
+                    chunk.constants.len()
+                ))?
+            }
+            _ => {}
+        }
+        offset += 1;
+    }
+
+    Ok(boundaries)
+}
+
+/// Where the jump at `offset` continues, mirroring how the VM moves its instruction pointer.
+fn jump_target(code: &[OpCode], offset: usize, boundaries: &[bool]) -> Result<usize> {
+    let amount = match code[offset + 1] {
+        OP_JUMP_AMOUNT(amount) => amount,
+        _ => unreachable!("checked by verify_instructions"),
+    };
+    let target = match code[offset] {
+        OP_LOOP => (offset + 2).checked_sub(amount),
+        _ => (offset + 2).checked_add(amount),
+    };
+
+    match target {
+        Some(target) if target < code.len() && boundaries[target] => Ok(target),
+        Some(target) if target < code.len() => Err(VERIFY_ERROR).context(format!(
+            "invalid chunk: the jump at offset {offset} lands inside the instruction at {}",
+            target - 1
+        )),
+        _ => Err(VERIFY_ERROR).context(format!(
+            "invalid chunk: the jump at offset {offset} leaves the chunk ({} instructions)",
+            code.len()
+        )),
+    }
+}
+
+/// How many values an instruction needs on the stack, and how many it pops and pushes.
+fn stack_effect(op: &OpCode) -> (usize, usize, usize) {
+    match op {
+        OP_CONSTANT(_) | OP_NIL | OP_TRUE | OP_FALSE | OP_GET_LOCAL(_) | OP_GET_GLOBAL(_)
+        | OP_IMPORT(_) => (0, 0, 1),
+        OP_POP | OP_PRINT | OP_DEFINE_GLOBAL(_) => (1, 1, 0),
+        OP_NEGATE | OP_NOT | OP_GET_PROPERTY(_) => (1, 1, 1),
+        // These only peek at the top of the stack.
+        OP_SET_LOCAL(_) | OP_SET_GLOBAL(_) | OP_JUMP_IF_FALSE | OP_JUMP_IF_TRUE => (1, 0, 0),
+        OP_EQUAL | OP_GREATER | OP_LESS | OP_NOT_EQUAL | OP_GREATER_EQUAL | OP_LESS_EQUAL
+        | OP_ADD | OP_SUBTRACT | OP_MULTIPLY | OP_DIVIDE | OP_MODULO | OP_POWER | OP_ASSERT_EQ => {
+            (2, 2, 1)
+        }
+        OP_ASSERT_EQ_MESSAGE => (3, 3, 1),
+        OP_CALL(arg_count) => (arg_count + 1, arg_count + 1, 1),
+        OP_RETURN
+        | OP_JUMP
+        | OP_LOOP
+        | OP_DEBUG
+        | OP_JUMP_AMOUNT(_)
+        | OP_PLACEHOLDER_JUMP_AMOUNT => (0, 0, 0),
+    }
+}
+
+/// Walks every path through the code and checks no instruction can run with fewer values on the
+/// stack than it needs. Paths may join with different stack depths, so the smallest depth that
+/// reaches each instruction is the one that is checked.
+fn verify_stack(code: &[OpCode], boundaries: &[bool]) -> Result<()> {
+    let mut min_depth: Vec<Option<usize>> = vec![None; code.len()];
+    let mut pending = vec![(0, 0)];
+
+    while let Some((offset, depth)) = pending.pop() {
+        if matches!(min_depth[offset], Some(seen) if seen <= depth) {
+            continue;
+        }
+        min_depth[offset] = Some(depth);
+
+        let op = &code[offset];
+        let (needs, pops, pushes) = stack_effect(op);
+        if depth < needs {
+            invalid(format!(
+                "stack underflow at offset {offset}, {op:?} needs {needs} values but only {depth} can be on the stack"
+            ))?
+        }
+        let depth = depth - pops + pushes;
+
+        match op {
+            OP_RETURN => {}
+            OP_JUMP | OP_LOOP => pending.push((jump_target(code, offset, boundaries)?, depth)),
+            OP_JUMP_IF_FALSE | OP_JUMP_IF_TRUE => {
+                pending.push((jump_target(code, offset, boundaries)?, depth));
+                pending.push((offset + 2, depth));
+            }
+            _ => pending.push((offset + 1, depth)),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk::OpCode::{self, *};
+    use crate::compiler::compile;
+    use crate::value::{allocate_object, Value};
+    use crate::verifier::verify;
+
+    fn chunk_of(code: Vec<OpCode>) -> Chunk {
+        let mut chunk = Chunk::default();
+        for op in code {
+            chunk.write_chunk(op, 1);
+        }
+        chunk
+    }
+
+    fn rejection(chunk: &Chunk) -> String {
+        format!("{:#}", verify(chunk).unwrap_err())
+    }
+
+    #[test]
+    fn verify_compiled_code_test() {
+        let source = "
+            var num = 1;
+            if (num > 0 and true) { num = 2; } else { num = 3; }
+            while (num < 10) { num = num + sqrt(4); }
+            { var local = \"yellow\"; print local + \"stone\"; }
+            assert_eq(num, 10);
+        ";
+        assert!(verify(&compile(&source.to_string()).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn verify_missing_return_test() {
+        let chunk = chunk_of(vec![OP_NIL, OP_POP]);
+        assert!(rejection(&chunk).contains("does not end with OP_RETURN"));
+        assert!(rejection(&Chunk::default()).contains("does not end with OP_RETURN"));
+    }
+
+    #[test]
+    fn verify_stack_underflow_test() {
+        let chunk = chunk_of(vec![OP_NIL, OP_ADD, OP_RETURN]);
+        assert!(rejection(&chunk).contains("stack underflow at offset 1"));
+    }
+
+    #[test]
+    fn verify_underflow_on_one_branch_test() {
+        // The false branch skips the OP_NIL, leaving nothing for OP_NEGATE but the condition.
+        let chunk = chunk_of(vec![
+            OP_TRUE,
+            OP_JUMP_IF_FALSE,
+            OP_JUMP_AMOUNT(1),
+            OP_NIL,
+            OP_ADD,
+            OP_RETURN,
+        ]);
+        assert!(rejection(&chunk).contains("stack underflow at offset 4"));
+    }
+
+    #[test]
+    fn verify_jump_out_of_range_test() {
+        let chunk = chunk_of(vec![OP_JUMP, OP_JUMP_AMOUNT(10), OP_RETURN]);
+        assert!(rejection(&chunk).contains("leaves the chunk"));
+
+        let chunk = chunk_of(vec![OP_NIL, OP_LOOP, OP_JUMP_AMOUNT(10), OP_RETURN]);
+        assert!(rejection(&chunk).contains("leaves the chunk"));
+    }
+
+    #[test]
+    fn verify_jump_inside_instruction_test() {
+        let chunk = chunk_of(vec![
+            OP_TRUE,
+            OP_JUMP_IF_FALSE,
+            OP_JUMP_AMOUNT(0),
+            OP_JUMP,
+            OP_JUMP_AMOUNT(0),
+            OP_RETURN,
+        ]);
+        assert!(verify(&chunk).is_ok());
+
+        let chunk = chunk_of(vec![
+            OP_TRUE,
+            OP_JUMP_IF_FALSE,
+            OP_JUMP_AMOUNT(1),
+            OP_JUMP,
+            OP_JUMP_AMOUNT(0),
+            OP_RETURN,
+        ]);
+        assert!(rejection(&chunk).contains("lands inside the instruction at 3"));
+    }
+
+    #[test]
+    fn verify_unpatched_jump_test() {
+        let chunk = chunk_of(vec![OP_JUMP, OP_PLACEHOLDER_JUMP_AMOUNT, OP_RETURN]);
+        assert!(rejection(&chunk).contains("never patched"));
+
+        let chunk = chunk_of(vec![OP_JUMP_AMOUNT(0), OP_RETURN]);
+        assert!(rejection(&chunk).contains("does not follow a jump"));
+    }
+
+    #[test]
+    fn verify_constant_index_test() {
+        let mut bad = chunk_of(vec![OP_NIL, OP_DEFINE_GLOBAL(0), OP_RETURN]);
+        assert!(rejection(&bad).contains("constant 0 at offset 1 is out of range"));
+
+        bad.add_constant(Value::number_value(1.));
+        assert!(rejection(&bad).contains("is not a string"));
+
+        let mut good = chunk_of(vec![OP_NIL, OP_DEFINE_GLOBAL(0), OP_RETURN]);
+        good.add_constant(allocate_object("name".to_string()));
+        assert!(verify(&good).is_ok());
+    }
+
+    #[test]
+    fn verify_local_index_test() {
+        let chunk = chunk_of(vec![OP_GET_LOCAL(3), OP_RETURN]);
+        assert!(rejection(&chunk).contains("local slot 3 at offset 0 is out of range"));
+    }
+}
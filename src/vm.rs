@@ -1,12 +1,24 @@
 use crate::chunk::{Chunk, OpCode, OpCode::*};
-use crate::compiler::compile;
-use crate::debug::disassemble_chunk;
+use crate::compiler::{compile_in_scope, compile_with_options, CompileOptions};
+use crate::debug::Tracer;
 use crate::error::InterpretError;
+use crate::limits::{Limits, Usage};
+use crate::profiler::Profile;
+use crate::serialize;
+use crate::stdlib::define_stdlib;
+use crate::stdlib::math::Random;
 use crate::table::Table;
-use crate::value::{allocate_object, ObjectHandler, Value, ValueKind};
+use crate::value::{allocate_object, Module, ObjectHandler, Value};
+use crate::verifier::verify;
 use anyhow::{Context, Result};
-use std::collections::LinkedList;
+use std::cmp::Ordering;
+use std::collections::{HashMap, LinkedList};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
 use InterpretError::*;
 
 #[allow(non_camel_case_types)]
@@ -17,7 +29,6 @@ pub enum InterpretOk {
 }
 
 #[allow(non_snake_case)]
-#[derive(Default)]
 pub struct VM {
     pub chunk: Chunk,
     /// instruction pointer, points at bytecode about to be executed
@@ -25,23 +36,240 @@ pub struct VM {
     pub stack: Vec<Value>,
     pub table: Table,
     pub objects: LinkedList<Rc<dyn ObjectHandler>>,
+    /// generator behind the `random` native
+    pub random: Random,
+    /// every module imported so far, keyed by canonical path
+    pub modules: HashMap<PathBuf, Value>,
+    /// files currently being executed, the last one is running now
+    pub import_stack: Vec<PathBuf>,
+    /// the arguments given to the script on the command line, read with the `arg` native
+    pub args: Vec<String>,
+    /// how source code run by this VM, including imported modules, is compiled
+    pub compile_options: CompileOptions,
+    /// called before every instruction, e.g. by the debugger. Without one the checks are compiled
+    /// out of the run loop.
+    pub hook: Option<Box<dyn Hook>>,
+    /// counts every instruction run while set, see `profiler::Profile`
+    pub profile: Option<Profile>,
+    /// what scripts run by this VM may do, see `limits::Limits`
+    pub limits: Limits,
+    /// what the running script has used against `limits`
+    pub usage: Usage,
+    /// set from another thread to stop the running script, see `VM::interrupt_handle`
+    pub interrupt: InterruptHandle,
+    /// the script stopped by an interrupt, until it is resumed, see `VM::resume`
+    pub suspended: Option<Suspension>,
+    /// how many modules or evaluations are running inside the script, they cannot be suspended
+    pub nested_runs: usize,
+    /// where `print` writes, stdout unless it is replaced to capture the output of scripts or to
+    /// drop it with `io::sink()`
+    pub output: Box<dyn Write>,
+}
+
+/// Stops a running VM from another thread, see `VM::interrupt_handle`.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+    /// Asks the VM to stop at its next backward jump or call.
+    pub fn interrupt(&self) {
+        self.0.store(true, AtomicOrdering::Relaxed);
+    }
+
+    /// Whether an interrupt is waiting to be handled.
+    pub fn is_interrupted(&self) -> bool {
+        self.0.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Clears the interrupt, returning whether there was one.
+    fn take(&self) -> bool {
+        self.0.swap(false, AtomicOrdering::Relaxed)
+    }
+}
+
+/// What `VM::resume` needs to carry on with a suspended script.
+pub struct Suspension {
+    /// the file the script was read from, put back on the import stack while it runs
+    file: Option<PathBuf>,
+}
+
+/// Watches a program run, see `VM::hook`.
+pub trait Hook {
+    /// Called with `vm.ip` at the instruction about to run. Returning an error stops the program
+    /// with it.
+    fn before_instruction(&mut self, vm: &mut VM) -> Result<()>;
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        let mut vm = VM {
+            chunk: Chunk::default(),
+            ip: 0,
+            stack: Vec::default(),
+            table: Table::default(),
+            objects: LinkedList::default(),
+            random: Random::default(),
+            modules: HashMap::default(),
+            import_stack: Vec::default(),
+            args: Vec::default(),
+            compile_options: CompileOptions::default(),
+            hook: None,
+            profile: None,
+            limits: Limits::default(),
+            usage: Usage::default(),
+            interrupt: InterruptHandle::default(),
+            suspended: None,
+            nested_runs: 0,
+            output: Box::new(io::stdout()),
+        };
+        define_stdlib(&mut vm);
+        vm
+    }
 }
 
 impl VM {
-    pub const DEBUG_EXECUTION_TRACING: bool = false;
+    /// Reseeds the generator behind the `random` native so runs can be reproduced.
+    pub fn seed_random(&mut self, seed: u64) {
+        self.random = Random::new(seed);
+    }
 
     pub fn interpret(&mut self, source: &String) -> Result<Option<Value>> {
-        let result = compile(source);
+        let result = compile_with_options(source, self.compile_options);
         match result {
             // todo: fix compile errors to be more descriptive.
             Err(e) => Err(e),
-            Ok(chunk) => {
-                self.chunk = chunk;
-                self.ip = 0; // Q
+            Ok(chunk) => self.interpret_chunk(chunk),
+        }
+    }
 
-                self.run()
-            }
+    /// Runs an already compiled chunk, e.g. one loaded from a `.ysc` file. The chunk is verified
+    /// first and rejected without running any of it if it is malformed.
+    ///
+    /// A script stopped by an error, e.g. for going over one of `limits`, leaves the stack empty
+    /// so the VM can run the next one. A script stopped by an interrupt is suspended instead, see
+    /// `resume`.
+    pub fn interpret_chunk(&mut self, chunk: Chunk) -> Result<Option<Value>> {
+        self.start(chunk)?;
+        self.run_script(None)
+    }
+
+    /// Interprets the script at `path`, either source code or a compiled `.ysc` file. Imports
+    /// inside it are resolved relative to its directory.
+    pub fn interpret_file(&mut self, path: &Path) -> Result<Option<Value>> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("The file at {} does not exist", path.display()))?;
+        let chunk = load_chunk(&path, self.compile_options)?;
+        self.start(chunk)?;
+        self.run_script(Some(path))
+    }
+
+    /// A handle that stops the running script from another thread. The script is suspended at its
+    /// next backward jump or call and `interpret` returns a `RUNTIME_INTERRUPT_ERROR`. A module
+    /// being imported or code evaluated by the debugger cannot be suspended, an interrupt there
+    /// stops the script for good.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Whether a script was stopped by an interrupt and can be resumed.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+
+    /// Carries on running the suspended script where the interrupt stopped it, with the stack and
+    /// globals it left and the resources it has used so far counted against `limits`. The time it
+    /// spent suspended is not.
+    pub fn resume(&mut self) -> Result<Option<Value>> {
+        let Some(suspension) = self.suspended.take() else {
+            return Err(RUNTIME_ERROR).context("There is no suspended script to resume.");
+        };
+        self.usage.start_clock();
+        self.run_script(suspension.file)
+    }
+
+    /// Makes `chunk` the script to run from its start, discarding any suspended one.
+    fn start(&mut self, chunk: Chunk) -> Result<()> {
+        verify(&chunk)?;
+        self.chunk = chunk;
+        self.ip = 0; // Q
+        self.usage = Usage::default();
+        self.suspended = None;
+        Ok(())
+    }
+
+    /// Runs the script from `ip` with `file`, if it was read from one, on the import stack.
+    fn run_script(&mut self, file: Option<PathBuf>) -> Result<Option<Value>> {
+        if let Some(file) = &file {
+            self.import_stack.push(file.clone());
+        }
+        let result = self.run();
+        if file.is_some() {
+            self.import_stack.pop();
         }
+
+        match &mut self.suspended {
+            Some(suspension) => suspension.file = file,
+            None if result.is_err() => self.stack.clear(),
+            None => {}
+        }
+        result
+    }
+
+    /// Writes a line to `sink` before every instruction runs, see `debug::Tracer`. The tracer
+    /// becomes the VM's hook, replacing any other.
+    pub fn trace(&mut self, sink: impl Write + 'static) {
+        self.hook = Some(Box::new(Tracer::new(sink)));
+    }
+
+    /// Runs `source` as if it were written where the program is paused: it sees the globals and
+    /// the locals in scope on the current line, and assignments to them change the program's
+    /// variables. Returns the value of the last expression, if it is left on the stack.
+    pub fn evaluate(&mut self, source: &String) -> Result<Option<Value>> {
+        let line = self.chunk.line(self.ip);
+        let locals: Vec<(String, usize)> = self
+            .chunk
+            .locals_at(line)
+            .iter()
+            .map(|local| (local.name.clone(), local.slot))
+            .collect();
+        let chunk = compile_in_scope(source, self.chunk.constants.clone(), &locals)?;
+        verify(&chunk)?;
+
+        let paused_constants = self.chunk.constants.len();
+        let chunk = std::mem::replace(&mut self.chunk, chunk);
+        let ip = std::mem::replace(&mut self.ip, 0);
+        let stack = std::mem::take(&mut self.stack);
+        let hook = self.hook.take();
+        let profile = self.profile.take();
+
+        self.nested_runs += 1;
+        let result = self.run();
+        self.nested_runs -= 1;
+
+        self.hook = hook;
+        self.profile = profile;
+        self.stack = stack;
+        self.ip = ip;
+        let evaluated = std::mem::replace(&mut self.chunk, chunk);
+        self.chunk
+            .constants
+            .clone_from_slice(&evaluated.constants[..paused_constants]);
+        result
+    }
+
+    /// The globals the program defined, without the natives, sorted by name.
+    pub fn globals(&self) -> Vec<(&String, &Value)> {
+        let mut globals: Vec<(&String, &Value)> = self
+            .table
+            .entries
+            .iter()
+            .flatten()
+            .filter(|entry| !entry.is_tombstone && !Value::is_native(&entry.value))
+            .map(|entry| (&entry.key, &entry.value))
+            .collect();
+        globals.sort_by(|a, b| a.0.cmp(b.0));
+        globals
     }
 
     pub fn free_objects(mut self) {
@@ -66,18 +294,20 @@ impl VM {
     }
 
     // Pushes the newly created object to the objects linked list. Ensures the Value is of type object.
-    fn track_object(&mut self, val: &Value) {
+    pub(crate) fn track_object(&mut self, val: &Value) {
         if !val.is_obj() {
             panic!("Cannot track a Value which is not an Object");
         } else {
             let obj = val.as_obj().unwrap();
+            self.usage.heap +=
+                std::mem::size_of_val(&*obj) + obj.as_str().map_or(0, |text| text.len());
             let rc = Rc::clone(&obj);
             self.objects.push_front(rc);
         }
     }
 
     // nil and false are falsey and every other value behaves like true
-    fn is_falsey(value: &Value) -> bool {
+    pub(crate) fn is_falsey(value: &Value) -> bool {
         Value::is_nil(value) || (Value::is_bool(value) && !Value::as_bool(value).unwrap())
     }
 
@@ -87,31 +317,144 @@ impl VM {
         let b = Value::as_string(&self.pop()).unwrap();
         let a = Value::as_string(&self.pop()).unwrap();
 
-        let cat = format!("{a}{b}").replace('\"', "");
+        let cat = format!("{a}{b}");
         let obj = allocate_object(cat);
 
         self.track_object(&obj);
         self.stack.push(obj);
     }
 
+    /// Calls the value `arg_count` slots below the top of the stack with the values above it as
+    /// arguments. The callee and arguments are replaced by the call's result.
+    fn call_value(&mut self, arg_count: usize) -> Result<()> {
+        let callee = self.peek(arg_count).unwrap().clone();
+        if !Value::is_native(&callee) {
+            return Err(RUNTIME_ERROR).context("Can only call functions.");
+        }
+
+        let obj = callee.as_obj().unwrap();
+        let native = obj.as_native().unwrap();
+        if arg_count < native.arity || arg_count > native.arity + native.optional {
+            let expected = match native.optional {
+                0 => native.arity.to_string(),
+                optional => format!("{} to {}", native.arity, native.arity + optional),
+            };
+            return Err(RUNTIME_ERROR).context(format!(
+                "{}() expected {expected} arguments but got {arg_count}.",
+                native.name
+            ));
+        }
+
+        let args = self.stack.split_off(self.stack.len() - arg_count);
+        self.pop(); // the callee
+        let result = (native.function)(self, &args)?;
+        self.push(result);
+        Ok(())
+    }
+
+    /// Resolves `path` relative to the running file (or the working directory outside of a file)
+    /// and returns the module it defines. Each module is compiled and run once, later imports of
+    /// the same file share the cached module.
+    fn import_module(&mut self, path: &str) -> Result<Value> {
+        if !self.limits.allow_imports {
+            return Err(RUNTIME_SANDBOX_ERROR).context(format!("cannot import \"{path}\" here"));
+        }
+        let base = match self.import_stack.last().and_then(|file| file.parent()) {
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::from("."),
+        };
+        let full_path = match base.join(path).canonicalize() {
+            Ok(full_path) => full_path,
+            Err(_) => {
+                return Err(RUNTIME_IMPORT_ERROR).context(format!("cannot find module \"{path}\""));
+            }
+        };
+
+        if let Some(module) = self.modules.get(&full_path) {
+            return Ok(module.clone());
+        }
+
+        if let Some(start) = self.import_stack.iter().position(|file| *file == full_path) {
+            let cycle = self.import_stack[start..]
+                .iter()
+                .chain(std::iter::once(&full_path))
+                .map(|file| file.display().to_string())
+                .collect::<Vec<String>>()
+                .join(" -> ");
+            return Err(RUNTIME_IMPORT_ERROR).context(format!("cyclic import: {cycle}"));
+        }
+
+        let chunk = load_chunk(&full_path, self.compile_options)
+            .with_context(|| format!("in module \"{path}\""))?;
+        let globals = self
+            .run_module(full_path.clone(), chunk)
+            .with_context(|| format!("in module \"{path}\""))?;
+
+        let name = full_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let module = allocate_object(Module {
+            name,
+            path: full_path.clone(),
+            globals,
+        });
+        self.track_object(&module);
+        self.modules.insert(full_path, module.clone());
+        Ok(module)
+    }
+
+    /// Runs a module's chunk with its own globals and returns them. The state of the importing
+    /// script is put back afterwards, whether the module succeeded or not.
+    fn run_module(&mut self, path: PathBuf, chunk: Chunk) -> Result<Table> {
+        verify(&chunk)?;
+        let chunk = std::mem::replace(&mut self.chunk, chunk);
+        let ip = std::mem::replace(&mut self.ip, 0);
+        let stack = std::mem::take(&mut self.stack);
+        let table = std::mem::take(&mut self.table);
+        define_stdlib(self);
+        self.import_stack.push(path);
+
+        self.nested_runs += 1;
+        let result = self.run();
+        self.nested_runs -= 1;
+
+        self.import_stack.pop();
+        let globals = std::mem::replace(&mut self.table, table);
+        self.stack = stack;
+        self.ip = ip;
+        self.chunk = chunk;
+
+        result.map(|_| globals)
+    }
+
     //Q: what happens when there are multiple chunks?
     pub fn run(&mut self) -> Result<Option<Value>> {
-        // if debug flag enabled, print each instruction before execution
-        if VM::DEBUG_EXECUTION_TRACING {
-            for val in self.stack.iter() {
-                println!("[{val:?}]");
-            }
-            disassemble_chunk(&self.chunk, "chunk");
-            println!();
+        if self.hook.is_some() || self.profile.is_some() || self.limits.is_bounded() {
+            self.execute::<true>()
+        } else {
+            self.execute::<false>()
         }
+    }
 
+    /// The run loop, built once with the limit checks, profiler and hook calls and once without
+    /// them.
+    fn execute<const HOOKED: bool>(&mut self) -> Result<Option<Value>> {
         loop {
+            if HOOKED {
+                if self.limits.is_bounded() {
+                    self.usage.check(&self.limits, self.stack.len())?;
+                }
+                if let Some(profile) = &mut self.profile {
+                    profile.record(&self.chunk, self.ip, &self.import_stack);
+                }
+                self.call_hook()?;
+            }
             let instruction = self.read_byte();
             let result: Result<()> = match instruction {
                 OP_RETURN => {
                     //changed in the global variable chapter
                     return if let Some(v) = self.stack.pop() {
-                        println!("chunk result: {v:?}");
                         Ok(Some(v))
                     } else {
                         //println!("Stack is empty, nothing to pop");
@@ -199,10 +542,6 @@ impl VM {
                     let local = self.chunk.constants.get(index);
                     match local {
                         Some(l) => {
-                            for (i, val) in self.chunk.constants.iter().enumerate() {
-                                print!("{} ", i);
-                                Value::print(val);
-                            }
                             self.push(l.clone());
                             Ok(())
                         }
@@ -221,6 +560,14 @@ impl VM {
                 }
                 OP_GREATER => binary_operator(self, '>'),
                 OP_LESS => binary_operator(self, '<'),
+                OP_NOT_EQUAL => {
+                    let a: Value = self.pop();
+                    let b: Value = self.pop();
+                    self.push(Value::bool_val(!Value::values_equal(a, b)));
+                    Ok(())
+                }
+                OP_GREATER_EQUAL => binary_operator(self, '≥'),
+                OP_LESS_EQUAL => binary_operator(self, '≤'),
                 OP_ADD => {
                     if Value::is_string(self.peek(0).unwrap())
                         && Value::is_string(self.peek(1).unwrap())
@@ -241,47 +588,73 @@ impl VM {
                 OP_SUBTRACT => binary_operator(self, '-'),
                 OP_MULTIPLY => binary_operator(self, '*'),
                 OP_DIVIDE => binary_operator(self, '/'),
-                OP_DEBUG => {
-                    unimplemented!()
+                OP_MODULO => binary_operator(self, '%'),
+                OP_POWER => binary_operator(self, '^'),
+                OP_CALL(arg_count) => {
+                    // Suspended before the call, so resuming makes it.
+                    if let Err(e) = self.check_interrupt() {
+                        self.ip -= 1;
+                        return Err(e);
+                    }
+                    self.call_value(arg_count)
+                }
+                OP_IMPORT(index) => {
+                    let path = self.chunk.get_constant_name(&index).unwrap();
+                    let module = self.import_module(&path)?;
+                    self.push(module);
+                    Ok(())
                 }
-                OP_ASSERT_EQ => {
-                    let a = self.pop();
-                    let b = self.pop();
-                    if a.kind == b.kind {
-                        let result = match a.kind {
-                            ValueKind::ValBool => a.as_bool() == b.as_bool(),
-                            ValueKind::ValNil => a.as_nil() == b.as_nil(),
-                            ValueKind::ValNumber => a.as_number() == b.as_number(),
-                            ValueKind::ValObj => a.as_string() == b.as_string(),
-                        };
-                        if !result {
-                            Err(RUNTIME_ASSERT_ERROR).context(format!(
-                                "Failed because assert values are not equal.
-                                left:  {b:?} \n 
-                                right: {a:?}"
-                            ))
-                        } else {
-                            self.push(Value::bool_val(true));
+                OP_GET_PROPERTY(index) => {
+                    let name = self.chunk.get_constant_name(&index).unwrap();
+                    let value = self.pop();
+                    if !Value::is_module(&value) {
+                        return Err(RUNTIME_ERROR).context("Only modules have members.");
+                    }
+                    let obj = value.as_obj().unwrap();
+                    let module = obj.as_module().unwrap();
+                    match module.globals.get(&name) {
+                        Some(member) => {
+                            let member = member.clone();
+                            self.push(member);
                             Ok(())
                         }
+                        None => Err(RUNTIME_UNRECOGNIZED_VARIABLE_ERROR).context(format!(
+                            "undefined member '{name}' in module '{}'",
+                            module.name
+                        )),
+                    }
+                }
+                OP_DEBUG => {
+                    unimplemented!()
+                }
+                OP_ASSERT_EQ | OP_ASSERT_EQ_MESSAGE => {
+                    let message = match instruction {
+                        OP_ASSERT_EQ_MESSAGE => Some(self.pop()),
+                        _ => None,
+                    };
+                    let right = self.pop();
+                    let left = self.pop();
+                    if left == right {
+                        self.push(Value::bool_val(true));
+                        Ok(())
                     } else {
-                        Err(RUNTIME_ERROR).context(format!(
-                            "Failed to compare values of the same type. left {} , right {}",
-                            a.kind, b.kind
+                        Err(RUNTIME_ASSERT_ERROR).context(format!(
+                            "{}, left: {}, right: {}",
+                            message.map_or("assert_eq failed".to_string(), |m| m.to_string()),
+                            left.repr(),
+                            right.repr()
                         ))
                     }
                 }
                 OP_PRINT => {
+                    if !self.limits.allow_print {
+                        return Err(RUNTIME_SANDBOX_ERROR).context("cannot print here");
+                    }
                     let pop = self.pop();
-                    match pop.kind {
-                        crate::value::ValueKind::ValBool => println!("{}", pop.as_bool().unwrap()),
-                        crate::value::ValueKind::ValNil => println!("{}", pop.as_nil().unwrap()),
-                        crate::value::ValueKind::ValNumber => {
-                            println!("{}", pop.as_number().unwrap())
-                        }
-                        crate::value::ValueKind::ValObj => println!("{}", pop.as_string().unwrap()),
+                    match pop.print(&mut self.output) {
+                        Ok(()) => Ok(()),
+                        Err(e) => Err(RUNTIME_ERROR).context(format!("cannot print: {e}")),
                     }
-                    Ok(())
                 }
                 OP_JUMP_IF_FALSE => {
                     if let OP_JUMP_AMOUNT(amount) = self.read_byte() {
@@ -291,6 +664,14 @@ impl VM {
                     }
                     Ok(())
                 }
+                OP_JUMP_IF_TRUE => {
+                    if let OP_JUMP_AMOUNT(amount) = self.read_byte() {
+                        if !VM::is_falsey(self.peek(0).unwrap()) {
+                            self.ip += amount;
+                        }
+                    }
+                    Ok(())
+                }
                 OP_JUMP => {
                     if let OP_JUMP_AMOUNT(amount) = self.read_byte() {
                         self.ip += amount;
@@ -303,7 +684,7 @@ impl VM {
                     if let OP_JUMP_AMOUNT(amount) = self.read_byte() {
                         self.ip -= amount;
                     }
-                    Ok(())
+                    self.check_interrupt()
                 }
             };
 
@@ -311,6 +692,30 @@ impl VM {
         }
     }
 
+    /// Stops the script if it was interrupted, suspending it unless a module or evaluation is
+    /// running inside it.
+    fn check_interrupt(&mut self) -> Result<()> {
+        if !self.interrupt.take() {
+            return Ok(());
+        }
+        if self.nested_runs > 0 {
+            return Err(RUNTIME_INTERRUPT_ERROR).context("Interrupted.");
+        }
+        self.suspended = Some(Suspension { file: None });
+        self.usage.stop_clock();
+        Err(RUNTIME_INTERRUPT_ERROR).context("Interrupted, the script can be resumed.")
+    }
+
+    /// The hook is taken out while it runs, so it can use the VM, e.g. to evaluate code.
+    fn call_hook(&mut self) -> Result<()> {
+        let Some(mut hook) = self.hook.take() else {
+            return Ok(());
+        };
+        let result = hook.before_instruction(self);
+        self.hook = Some(hook);
+        result
+    }
+
     fn read_byte(&mut self) -> OpCode {
         let instruction = self.chunk.code.get(self.ip).unwrap().clone();
         self.ip += 1;
@@ -318,6 +723,23 @@ impl VM {
     }
 }
 
+/// Reads a script from disk. Files with the `.ysc` extension hold a serialized chunk, anything
+/// else is compiled as source code with `options`.
+pub fn load_chunk(path: &Path, options: CompileOptions) -> Result<Chunk> {
+    let is_bytecode = path
+        .extension()
+        .is_some_and(|ext| ext == serialize::EXTENSION);
+    if is_bytecode {
+        let bytes = fs::read(path)
+            .with_context(|| format!("The file at {} could not be read", path.display()))?;
+        Chunk::deserialize(&bytes)
+    } else {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("The file at {} could not be read", path.display()))?;
+        compile_with_options(&source, options)
+    }
+}
+
 fn binary_operator(vm: &mut VM, op: char) -> Result<()> {
     if !Value::is_number(vm.peek(0).unwrap()) || !Value::is_number(vm.peek(1).unwrap()) {
         return Err(RUNTIME_ERROR).context("Operands must be numbers");
@@ -329,11 +751,19 @@ fn binary_operator(vm: &mut VM, op: char) -> Result<()> {
         '-' => vm.stack.push(Value::number_value(a - b)),
         '/' => vm.stack.push(Value::number_value(a / b)),
         '*' => vm.stack.push(Value::number_value(a * b)),
+        '%' => vm.stack.push(Value::number_value(a % b)),
+        '^' => vm.stack.push(Value::number_value(a.powf(b))),
         '>' => vm.stack.push(Value::bool_val(a > b)),
         '<' => vm.stack.push(Value::bool_val(a < b)),
-        _ => {
-            println!("invalid operation {op}")
-        }
+        // Written as the negation of the strict comparison to match `OP_LESS, OP_NOT`, which
+        // differs from `a >= b` when either side is NaN.
+        '≥' => vm
+            .stack
+            .push(Value::bool_val(a.partial_cmp(&b) != Some(Ordering::Less))),
+        '≤' => vm.stack.push(Value::bool_val(
+            a.partial_cmp(&b) != Some(Ordering::Greater),
+        )),
+        _ => return Err(RUNTIME_ERROR).context(format!("invalid operation {op}")),
     }
     Ok(())
 }
@@ -0,0 +1,147 @@
+extern crate core;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use yellowstone::chunk::{Chunk, OpCode::*};
+use yellowstone::compiler::compile;
+use yellowstone::error::InterpretError::VERIFY_ERROR;
+use yellowstone::serialize::FormatError;
+use yellowstone::value::allocate_object;
+use yellowstone::vm::VM;
+
+#[test]
+fn bytecode_run_deserialized_chunk_test() {
+    let source = "
+        var greeting = \"yellow\" + \"stone\";
+        var total = 0;
+        while (total < 10) {
+            total = total + sqrt(9);
+        }
+        assert_eq(greeting, \"yellowstone\");
+        assert_eq(total, 12, \"three times four\");
+    "
+    .to_string();
+    let bytes = compile(&source).unwrap().serialize().unwrap();
+
+    let mut vm = VM::default();
+    let result = vm.interpret_chunk(Chunk::deserialize(&bytes).unwrap());
+    if result.is_err() {
+        eprintln!("{result:?}");
+        panic!()
+    }
+}
+
+#[test]
+fn bytecode_run_ysc_file_with_imports_test() {
+    let dir = scratch_dir("imports");
+    fs::write(dir.join("consts.ys"), "var answer = 42;").unwrap();
+    let source = "import \"consts.ys\"; assert_eq(consts.answer, 42);".to_string();
+    let bytes = compile(&source).unwrap().serialize().unwrap();
+    fs::write(dir.join("main.ysc"), bytes).unwrap();
+
+    let mut vm = VM::default();
+    let result = vm.interpret_file(&dir.join("main.ysc"));
+    if result.is_err() {
+        eprintln!("{result:?}");
+        panic!()
+    }
+}
+
+#[test]
+fn bytecode_reject_source_as_ysc_test() {
+    let dir = scratch_dir("reject");
+    fs::write(dir.join("fake.ysc"), "print \"not bytecode\";").unwrap();
+
+    let mut vm = VM::default();
+    let error = vm.interpret_file(&dir.join("fake.ysc")).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<FormatError>(),
+        Some(&FormatError::BadMagic)
+    );
+}
+
+#[test]
+fn bytecode_malformed_chunk_is_not_run_test() {
+    // Defines a global, then adds with only one value on the stack.
+    let mut chunk = Chunk::default();
+    let name = chunk.add_constant(allocate_object("ran".to_string()));
+    chunk.write_chunk(OP_TRUE, 1);
+    chunk.write_chunk(OP_DEFINE_GLOBAL(name), 1);
+    chunk.write_chunk(OP_NIL, 2);
+    chunk.write_chunk(OP_ADD, 2);
+    chunk.write_chunk(OP_RETURN, 2);
+    let bytes = chunk.serialize().unwrap();
+
+    let mut vm = VM::default();
+    let error = vm
+        .interpret_chunk(Chunk::deserialize(&bytes).unwrap())
+        .unwrap_err();
+    assert_eq!(format!("{}", error.root_cause()), VERIFY_ERROR.to_string());
+    assert!(format!("{error:#}").contains("stack underflow at offset 3"));
+    assert!(vm.table.get("ran").is_none());
+}
+
+#[test]
+fn bytecode_cli_compile_and_run_test() {
+    let dir = scratch_dir("cli");
+    let script = dir.join("hello.ys");
+    fs::write(&script, "print \"hello from bytecode\";").unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_yellowstone"))
+        .arg("compile")
+        .arg(&script)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(dir.join("hello.ysc").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_yellowstone"))
+        .arg(dir.join("hello.ysc"))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("hello from bytecode"));
+    // The source is not available, so it is not echoed.
+    assert!(!stdout.contains("print"));
+}
+
+#[test]
+fn bytecode_cli_corrupted_ysc_test() {
+    let dir = scratch_dir("corrupted");
+    let bytes = compile(&"print \"hello\";".to_string())
+        .unwrap()
+        .serialize()
+        .unwrap();
+
+    let mut flipped = bytes.clone();
+    *flipped.last_mut().unwrap() ^= 0xff;
+    fs::write(dir.join("flipped.ysc"), flipped).unwrap();
+    fs::write(dir.join("truncated.ysc"), &bytes[..6]).unwrap();
+
+    for (file, message) in [
+        ("flipped.ysc", "checksum mismatch"),
+        ("truncated.ysc", "unexpected end of file"),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_yellowstone"))
+            .arg(dir.join(file))
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        // A malformed file is a compile error, not a missing input.
+        assert_eq!(Some(65), output.status.code(), "{file}: {stderr}");
+        assert!(stderr.contains(message), "{file}: {stderr}");
+    }
+}
+
+// ################################################################################
+// Helper Functions
+// ################################################################################
+
+/// Creates an empty directory for a test to write scripts into.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("bytecode_{name}"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
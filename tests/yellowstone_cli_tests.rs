@@ -0,0 +1,138 @@
+extern crate core;
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs the `yellowstone` binary from the repository root with `args` and `stdin` as its input.
+fn yellowstone(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_yellowstone"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+#[test]
+fn cli_run_file_test() {
+    let output = yellowstone(&["tests/modules/main.ys"], "");
+    assert_eq!(Some(0), output.status.code(), "{}", stderr(&output));
+    // The source is not echoed.
+    assert_eq!("", stdout(&output));
+
+    let output = yellowstone(&["run", "tests/modules/main.ys"], "");
+    assert_eq!(Some(0), output.status.code(), "{}", stderr(&output));
+}
+
+#[test]
+fn cli_inline_and_stdin_test() {
+    let output = yellowstone(&["-e", "print 1 + 2;"], "");
+    assert_eq!("3\n", stdout(&output));
+    assert_eq!(Some(0), output.status.code());
+
+    let output = yellowstone(&["-"], "var a = 4;\nprint a * 2;\n");
+    assert_eq!("8\n", stdout(&output));
+    assert_eq!(Some(0), output.status.code());
+}
+
+#[test]
+fn cli_script_arguments_test() {
+    let source = "print arg_count(); print arg(0); print arg(1);";
+    let output = yellowstone(&["-e", source, "--flag", "value"], "");
+    assert_eq!("2\n--flag\nvalue\n", stdout(&output));
+
+    let output = yellowstone(&["-", "only"], "print arg(0);");
+    assert_eq!("only\n", stdout(&output));
+}
+
+#[test]
+fn cli_exit_codes_test() {
+    let compile_error = yellowstone(&["-e", "print 1 +;"], "");
+    assert_eq!(Some(65), compile_error.status.code());
+    assert!(stderr(&compile_error).contains("COMPILE_ERROR"));
+
+    let runtime_error = yellowstone(&["-e", "print missing;"], "");
+    assert_eq!(Some(70), runtime_error.status.code());
+    assert!(stderr(&runtime_error).contains("undefined variable: missing"));
+
+    assert_eq!(
+        Some(66),
+        yellowstone(&["no/such/file.ys"], "").status.code()
+    );
+    assert_eq!(Some(64), yellowstone(&["--bogus"], "").status.code());
+    assert_eq!(Some(64), yellowstone(&["disasm"], "").status.code());
+}
+
+#[test]
+fn cli_compile_errors_are_printed_once_test() {
+    let output = yellowstone(&["-e", "var = 1;\nprint ;"], "");
+    assert_eq!(Some(65), output.status.code());
+    let stderr = stderr(&output);
+    for error in [
+        "[line 1] Error at '=': Expect variable name.",
+        "[line 2] Error at ';': Expect expression.",
+    ] {
+        assert_eq!(1, stderr.matches(error).count(), "{stderr}");
+    }
+}
+
+#[test]
+fn cli_check_and_disasm_test() {
+    let output = yellowstone(&["check", "foo.ys", "tests/modules/geometry.ys"], "");
+    assert_eq!(Some(0), output.status.code(), "{}", stderr(&output));
+    assert_eq!("", stdout(&output));
+
+    let output = yellowstone(&["check", "tests/modules/broken.ys"], "");
+    assert_eq!(Some(65), output.status.code());
+    assert!(stderr(&output).contains("tests/modules/broken.ys: "));
+
+    let output = yellowstone(&["disasm", "tests/modules/geometry.ys"], "");
+    assert_eq!(Some(0), output.status.code());
+    assert!(stdout(&output).starts_with("== tests/modules/geometry.ys ==\n-- constants --\n"));
+}
+
+#[test]
+fn cli_test_command_test() {
+    let output = yellowstone(&["test", "tests/modules/geometry.ys"], "");
+    assert_eq!(Some(0), output.status.code());
+    assert!(stdout(&output).contains("test result: ok. 1 passed; 0 failed"));
+
+    let output = yellowstone(
+        &[
+            "test",
+            "tests/modules/geometry.ys",
+            "tests/modules/broken.ys",
+        ],
+        "",
+    );
+    assert_eq!(Some(1), output.status.code());
+    assert!(stdout(&output).contains("test tests/modules/broken.ys ... FAILED"));
+
+    let output = yellowstone(&["test", "--filter", "adds", "tests/runner"], "");
+    assert_eq!(Some(0), output.status.code());
+    assert!(stdout(&output).contains("1 passed; 0 failed; 3 filtered out"));
+}
+
+#[test]
+fn cli_repl_test() {
+    let output = yellowstone(&["repl"], "var a = 2;\na * 21;\nexit\n");
+    assert_eq!(Some(0), output.status.code());
+    assert!(stdout(&output).contains(">> 42\n"), "{}", stdout(&output));
+}
@@ -0,0 +1,171 @@
+extern crate core;
+
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use yellowstone::debugger::Debugger;
+use yellowstone::error::InterpretError;
+use yellowstone::vm::VM;
+
+/// Collects what the debugger or tracer writes while the VM owns it.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+fn debugged_vm(commands: &str, output: &Output) -> VM {
+    let debugger = Debugger::new(Cursor::new(commands.to_string()), output.clone());
+    VM {
+        hook: Some(Box::new(debugger)),
+        ..VM::default()
+    }
+}
+
+/// Runs `source` under the debugger with `commands` as its input, returns the result and the
+/// debugger's output.
+fn debug(source: &str, commands: &str) -> (anyhow::Result<()>, String) {
+    let output = Output::default();
+    let mut vm = debugged_vm(commands, &output);
+    let result = vm.interpret(&source.to_string()).map(|_| ());
+    (result, output.text())
+}
+
+/// Same as `debug` for a script in `tests/modules`.
+fn debug_file(name: &str, commands: &str) -> (anyhow::Result<()>, String) {
+    let output = Output::default();
+    let mut vm = debugged_vm(commands, &output);
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("modules")
+        .join(name);
+    let result = vm.interpret_file(&path).map(|_| ());
+    (result, output.text())
+}
+
+/// The places the debugger paused at, in order.
+fn pauses(output: &str) -> Vec<&str> {
+    output
+        .split("(ysdb) ")
+        .filter_map(|text| text.lines().last())
+        .filter(|line| !line.starts_with("breakpoint"))
+        .filter(|line| line.starts_with("line ") || line.contains(".ys:"))
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect()
+}
+
+const SOURCE: &str = "var total = 0;
+{
+    var step = 2;
+    var label = \"x\";
+    total = total + step;
+}
+assert_eq(total, 5);
+";
+
+#[test]
+fn debugger_step_test() {
+    let (result, output) = debug("var a = 1;\nvar b = a + 1;\n\nprint b;\n", "step\nnext\n");
+    assert!(result.is_ok(), "{result:?}");
+    // Running out of commands lets the program finish.
+    assert_eq!(vec!["line", "line", "line"], pauses(&output));
+    assert!(output.starts_with("line 1"), "{output}");
+    assert!(output.contains("line 2"), "{output}");
+    assert!(output.contains("line 4"), "{output}");
+}
+
+#[test]
+fn debugger_breakpoint_inspect_and_evaluate_test() {
+    let commands =
+        "break 5\ncontinue\nlocals\nglobals\nprint total + step * 10\nprint step = 5\ncontinue\n";
+    let (result, output) = debug(SOURCE, commands);
+    // `step = 5` changed the program's local, so the assertion holds.
+    assert!(result.is_ok(), "{output}");
+    assert!(output.contains("breakpoint at line 5"), "{output}");
+    assert!(output.contains("line 5"), "{output}");
+    assert!(output.contains("step = 2\nlabel = \"x\"\n"), "{output}");
+    assert!(output.contains("total = 0\n"), "{output}");
+    assert!(!output.contains("sqrt"), "natives are not listed: {output}");
+    assert!(output.contains("(ysdb) 20\n"), "{output}");
+    assert!(output.contains("(ysdb) 5\n"), "{output}");
+}
+
+#[test]
+fn debugger_evaluate_error_test() {
+    let (result, output) = debug(SOURCE, "print missing\nprint 1 +\ncontinue\n");
+    assert!(result.is_err());
+    assert!(
+        output.contains("RUNTIME_UNRECOGNIZED_VARIABLE_ERROR"),
+        "{output}"
+    );
+    assert!(output.contains("COMPILE_ERROR"), "{output}");
+}
+
+#[test]
+fn debugger_quit_test() {
+    let (result, output) = debug(SOURCE, "next\nquit\n");
+    assert_eq!(
+        InterpretError::RUNTIME_ERROR.to_string(),
+        result.unwrap_err().root_cause().to_string()
+    );
+    assert_eq!(2, pauses(&output).len());
+}
+
+#[test]
+fn debugger_step_into_and_out_of_modules_test() {
+    let (result, output) = debug_file("main.ys", "step\nstep\nout\nnext\nnext\n");
+    assert!(result.is_ok(), "{result:?}");
+    assert_eq!(
+        vec![
+            "main.ys:1",
+            "geometry.ys:1",
+            "geometry.ys:2",
+            "main.ys:1",
+            "main.ys:2",
+            "main.ys:4",
+        ],
+        pauses(&output)
+    );
+}
+
+#[test]
+fn debugger_module_breakpoint_test() {
+    let (result, output) = debug_file("main.ys", "break consts.ys:3\ncontinue\nstack\n");
+    assert!(result.is_ok(), "{result:?}");
+    assert_eq!(vec!["main.ys:1", "consts.ys:3"], pauses(&output));
+}
+
+#[test]
+fn trace_test() {
+    let output = Output::default();
+    let mut vm = VM::default();
+    vm.trace(output.clone());
+    let source = "var a = 1;\nwhile (a < 3) {\n    a = a + 1;\n}\n";
+    assert!(vm.interpret(&source.to_string()).is_ok());
+
+    let trace = output.text();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!("0000    1 OP_CONSTANT 1                []", lines[0]);
+    assert_eq!("0001    1 OP_DEFINE_GLOBAL 0 \"a\"       [1]", lines[1]);
+    assert_eq!("0005    2 OP_JUMP_IF_FALSE -> 0014     [true]", lines[5]);
+    assert_eq!("0010    3 OP_ADD                       [1, 1]", lines[9]);
+    assert_eq!("0012    3 OP_LOOP -> 0002              [2]", lines[11]);
+    // The condition runs three times, the body twice.
+    assert_eq!(3, trace.matches("OP_LESS").count());
+    assert_eq!(2, trace.matches("OP_ADD").count());
+    assert!(lines.last().unwrap().contains("OP_RETURN"));
+}
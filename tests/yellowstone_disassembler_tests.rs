@@ -0,0 +1,76 @@
+extern crate core;
+
+use std::fs;
+use std::path::PathBuf;
+use yellowstone::assembler::assemble;
+use yellowstone::compiler::compile;
+use yellowstone::debug::disassemble;
+
+#[test]
+fn disassemble_snapshot_test() {
+    let source = "var count = 0;\nwhile (count < 2) {\n    count = count + 1;\n}\nprint count;\n";
+    let chunk = compile(&source.to_string()).unwrap();
+    let expected = r#"== count ==
+-- constants --
+0000 "count"
+0001 0
+0002 "count"
+0003 2
+0004 "count"
+0005 "count"
+0006 1
+0007 "count"
+-- code --
+0000    1 OP_CONSTANT 0
+0001    | OP_DEFINE_GLOBAL 0 "count"
+0002    2 OP_GET_GLOBAL 2 "count"
+0003    | OP_CONSTANT 2
+0004    | OP_LESS
+0005    | OP_JUMP_IF_FALSE -> 0014
+0007    | OP_POP
+0008    3 OP_GET_GLOBAL 5 "count"
+0009    | OP_CONSTANT 1
+0010    | OP_ADD
+0011    | OP_SET_GLOBAL 4 "count"
+0012    | OP_LOOP -> 0002
+0014    | OP_POP
+0015    5 OP_GET_GLOBAL 7 "count"
+0016    | OP_PRINT
+0017    | OP_RETURN
+"#;
+    assert_eq!(expected, disassemble(&chunk, "count"));
+}
+
+/// Every script in the repository disassembles to a listing that assembles back to the same code.
+#[test]
+fn disassemble_round_trip_test() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let mut scripts = vec![root.join("foo.ys")];
+    let mut dirs = vec![root.join("tests").join("modules")];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "ys") {
+                scripts.push(path);
+            }
+        }
+    }
+
+    for script in scripts {
+        let source = fs::read_to_string(&script).unwrap();
+        // Some of the modules are broken on purpose.
+        let Ok(chunk) = compile(&source) else {
+            continue;
+        };
+        let name = script.display().to_string();
+        let listing = disassemble(&chunk, &name);
+        let assembled = match assemble(&listing) {
+            Ok(assembled) => assembled,
+            Err(e) => panic!("{name}: {e:#}\n{listing}"),
+        };
+        assert_eq!(listing, disassemble(&assembled, &name));
+        assert_eq!(chunk.lines, assembled.lines, "{name}");
+    }
+}
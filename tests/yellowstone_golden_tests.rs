@@ -0,0 +1,159 @@
+//! Runs every script under `tests/golden` with the `yellowstone` binary and checks what it does
+//! against the comments in the script, like the Crafting Interpreters test suite:
+//!
+//! - `// expect: text` is a line the script prints, in order.
+//! - `// expect runtime error: message` is the error the script stops with.
+//! - `// expect compile error: Error at 'x': message` is a compile error on the comment's line.
+//!
+//! A script without errors to expect must exit cleanly and print exactly its `expect` lines.
+
+extern crate core;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const EXPECT: &str = "// expect: ";
+const EXPECT_RUNTIME_ERROR: &str = "// expect runtime error: ";
+const EXPECT_COMPILE_ERROR: &str = "// expect compile error: ";
+
+const EXIT_COMPILE_ERROR: i32 = 65;
+const EXIT_RUNTIME_ERROR: i32 = 70;
+
+/// What a script says it does, read from its comments.
+#[derive(Debug, Default)]
+struct Expectations {
+    output: Vec<String>,
+    runtime_error: Option<String>,
+    /// the errors with the line of their comment, as the compiler reports them
+    compile_errors: Vec<String>,
+}
+
+impl Expectations {
+    fn parse(source: &str) -> Expectations {
+        let mut expectations = Expectations::default();
+        for (index, line) in source.lines().enumerate() {
+            if let Some((_, text)) = line.split_once(EXPECT) {
+                expectations.output.push(text.to_string());
+            } else if let Some((_, message)) = line.split_once(EXPECT_RUNTIME_ERROR) {
+                expectations.runtime_error = Some(message.to_string());
+            } else if let Some((_, message)) = line.split_once(EXPECT_COMPILE_ERROR) {
+                let error = format!("[line {}] {message}", index + 1);
+                expectations.compile_errors.push(error);
+            }
+        }
+        expectations
+    }
+
+    fn exit_code(&self) -> i32 {
+        if !self.compile_errors.is_empty() {
+            EXIT_COMPILE_ERROR
+        } else if self.runtime_error.is_some() {
+            EXIT_RUNTIME_ERROR
+        } else {
+            0
+        }
+    }
+}
+
+/// The scripts under `dir` and its subdirectories, sorted.
+fn scripts(dir: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            found.extend(scripts(&path));
+        } else if path.extension().is_some_and(|extension| extension == "ys") {
+            found.push(path);
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Runs the script and returns how it differs from its comments, nothing when it does not.
+fn check(script: &Path) -> Vec<String> {
+    let source = fs::read_to_string(script).unwrap();
+    let expected = Expectations::parse(&source);
+    let output = Command::new(env!("CARGO_BIN_EXE_yellowstone"))
+        .arg(script)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let mut failures = Vec::new();
+    let printed: Vec<&str> = stdout.lines().collect();
+    for (index, expected) in expected.output.iter().enumerate() {
+        match printed.get(index) {
+            Some(line) if line == expected => {}
+            Some(line) => failures.push(format!("expected output `{expected}`, got `{line}`")),
+            None => failures.push(format!("expected output `{expected}`, got nothing")),
+        }
+    }
+    for line in printed.iter().skip(expected.output.len()) {
+        failures.push(format!("unexpected output `{line}`"));
+    }
+
+    if let Some(message) = &expected.runtime_error {
+        match stderr.lines().next() {
+            Some(line) if line == message => {}
+            line => failures.push(format!(
+                "expected runtime error `{message}`, got `{}`",
+                line.unwrap_or_default()
+            )),
+        }
+    }
+    for error in &expected.compile_errors {
+        if !stderr.lines().any(|line| line == error) {
+            failures.push(format!("expected compile error `{error}`"));
+        }
+    }
+
+    let code = output.status.code();
+    if code != Some(expected.exit_code()) {
+        failures.push(format!(
+            "expected exit code {}, got {code:?}\n{stderr}",
+            expected.exit_code()
+        ));
+    }
+    failures
+}
+
+#[test]
+fn golden_scripts_test() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden");
+    let scripts = scripts(&dir);
+    assert!(!scripts.is_empty(), "no scripts in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for script in &scripts {
+        for failure in check(script) {
+            failures.push(format!("{}: {failure}", script.display()));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn golden_expectations_test() {
+    let source = "\
+print 1; // expect: 1
+print \"a\" + \"b\"; // expect: ab
+var = 1; // expect compile error: Error at '=': Expect variable name.
+1 + nil; // expect runtime error: Operands must be two numbers or two strings.
+";
+    let expected = Expectations::parse(source);
+    assert_eq!(vec!["1", "ab"], expected.output);
+    assert_eq!(
+        vec!["[line 3] Error at '=': Expect variable name."],
+        expected.compile_errors
+    );
+    assert_eq!(
+        Some("Operands must be two numbers or two strings."),
+        expected.runtime_error.as_deref()
+    );
+    assert_eq!(EXIT_COMPILE_ERROR, expected.exit_code());
+}
@@ -0,0 +1,100 @@
+extern crate core;
+
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use yellowstone::error::InterpretError::*;
+use yellowstone::vm::VM;
+
+fn expect_interrupted<T>(result: anyhow::Result<T>) {
+    match result {
+        Err(e) => assert_eq!(
+            RUNTIME_INTERRUPT_ERROR.to_string(),
+            e.root_cause().to_string(),
+            "{e:#}"
+        ),
+        Ok(_) => panic!("expected the script to be interrupted"),
+    }
+}
+
+fn global(vm: &VM, name: &str) -> f32 {
+    vm.table.get(name).unwrap().as_number().unwrap()
+}
+
+#[test]
+fn interrupt_from_another_thread_test() {
+    let mut vm = VM::default();
+    let handle = vm.interrupt_handle();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        handle.interrupt();
+    });
+
+    let source = "var i = 0; while (true) { i = i + 1; }";
+    expect_interrupted(vm.interpret(&source.to_string()));
+    interrupter.join().unwrap();
+    assert!(vm.is_suspended());
+    assert!(!vm.interrupt_handle().is_interrupted());
+    let before = global(&vm, "i");
+    assert!(before > 0.);
+
+    // Resumed, the loop carries on counting from where it stopped.
+    vm.interrupt_handle().interrupt();
+    expect_interrupted(vm.resume());
+    assert!(vm.is_suspended());
+    assert!(global(&vm, "i") >= before);
+}
+
+#[test]
+fn resume_to_completion_test() {
+    let mut vm = VM::default();
+    vm.interrupt_handle().interrupt();
+    let source = "var i = 0; while (i < 100) { i = i + 1; } i;";
+    expect_interrupted(vm.interpret(&source.to_string()));
+    assert_eq!(1., global(&vm, "i"));
+
+    let result = vm.resume().unwrap();
+    assert_eq!(Some(100.), result.map(|value| value.as_number().unwrap()));
+    assert!(!vm.is_suspended());
+    assert!(vm.resume().is_err(), "nothing is left to resume");
+}
+
+#[test]
+fn interrupt_before_call_test() {
+    let mut vm = VM::default();
+    vm.interrupt_handle().interrupt();
+    let source = "var root = sqrt(16);";
+    expect_interrupted(vm.interpret(&source.to_string()));
+    assert!(vm.table.get("root").is_none());
+
+    // The call is made once the script is resumed.
+    assert!(vm.resume().is_ok());
+    assert_eq!(4., global(&vm, "root"));
+}
+
+#[test]
+fn interrupt_in_module_is_not_resumable_test() {
+    let mut vm = VM::default();
+    vm.interrupt_handle().interrupt();
+    let source = "import \"tests/modules/countdown.ys\";";
+    expect_interrupted(vm.interpret(&source.to_string()));
+    assert!(!vm.is_suspended());
+    assert!(vm.import_stack.is_empty());
+    assert!(vm.stack.is_empty());
+}
+
+#[test]
+fn interrupt_file_keeps_its_directory_test() {
+    let mut vm = VM::default();
+    vm.interrupt_handle().interrupt();
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("modules")
+        .join("countdown.ys");
+    expect_interrupted(vm.interpret_file(&path));
+    assert!(vm.import_stack.is_empty());
+
+    // The import after the loop is still found next to the script.
+    assert!(vm.resume().is_ok());
+    assert_eq!(4., global(&vm, "sides"));
+}
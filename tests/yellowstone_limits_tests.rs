@@ -0,0 +1,138 @@
+extern crate core;
+
+use std::thread;
+use std::time::{Duration, Instant};
+use yellowstone::error::InterpretError::{self, *};
+use yellowstone::limits::Limits;
+use yellowstone::vm::VM;
+
+fn limited(limits: Limits) -> VM {
+    VM {
+        limits,
+        ..VM::default()
+    }
+}
+
+fn expect_error(vm: &mut VM, source: &str, expect: InterpretError) {
+    match vm.interpret(&source.to_string()) {
+        Err(e) => assert_eq!(expect.to_string(), e.root_cause().to_string(), "{e:#}"),
+        Ok(_) => panic!("expected {expect}"),
+    }
+}
+
+/// After a script is stopped the VM still runs the next one.
+fn expect_reusable(vm: &mut VM) {
+    let result = vm.interpret(&"var answer = 40 + 2; answer;".to_string());
+    assert_eq!(
+        Some(42.),
+        result.unwrap().map(|value| value.as_number().unwrap())
+    );
+    assert_eq!(0, vm.stack.len());
+}
+
+#[test]
+fn limits_fuel_test() {
+    let mut vm = limited(Limits {
+        fuel: Some(10_000),
+        ..Limits::default()
+    });
+    expect_error(&mut vm, "while (true) {}", RUNTIME_FUEL_ERROR);
+    assert_eq!(10_001, vm.usage.instructions);
+    expect_reusable(&mut vm);
+}
+
+#[test]
+fn limits_stack_test() {
+    let mut vm = limited(Limits {
+        max_stack: Some(64),
+        ..Limits::default()
+    });
+    // An expression statement leaves its value on the stack.
+    expect_error(&mut vm, "while (true) 1;", RUNTIME_STACK_OVERFLOW_ERROR);
+    expect_reusable(&mut vm);
+}
+
+#[test]
+fn limits_heap_test() {
+    let mut vm = limited(Limits {
+        max_heap: Some(10_000),
+        ..Limits::default()
+    });
+    expect_error(
+        &mut vm,
+        "var text = \"a\"; while (true) { text = text + \"a\"; }",
+        RUNTIME_MEMORY_ERROR,
+    );
+    assert!(vm.usage.heap > 10_000);
+    expect_reusable(&mut vm);
+}
+
+#[test]
+fn limits_timeout_test() {
+    let mut vm = limited(Limits {
+        timeout: Some(Duration::from_millis(50)),
+        ..Limits::default()
+    });
+    let start = Instant::now();
+    expect_error(&mut vm, "while (true) {}", RUNTIME_TIMEOUT_ERROR);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    expect_reusable(&mut vm);
+}
+
+#[test]
+fn limits_timeout_leaves_out_suspension_test() {
+    let mut vm = limited(Limits {
+        timeout: Some(Duration::from_millis(200)),
+        ..Limits::default()
+    });
+    vm.interrupt_handle().interrupt();
+    let source = "var i = 0; while (i < 500) { i = i + 1; } i;";
+    assert!(vm.interpret(&source.to_string()).is_err());
+    assert!(vm.is_suspended());
+
+    // Longer than the timeout passes while suspended, the script still has its time to run.
+    thread::sleep(Duration::from_millis(300));
+    let result = vm.resume();
+    assert_eq!(
+        Some(500.),
+        result.unwrap().map(|value| value.as_number().unwrap())
+    );
+}
+
+#[test]
+fn limits_allow_within_bounds_test() {
+    let mut vm = limited(Limits {
+        fuel: Some(1_000),
+        max_stack: Some(16),
+        max_heap: Some(1_000),
+        timeout: Some(Duration::from_secs(5)),
+        ..Limits::default()
+    });
+    let source = "var i = 0; while (i < 10) { i = i + 1; } assert_eq(i, 10);";
+    assert!(vm.interpret(&source.to_string()).is_ok());
+}
+
+#[test]
+fn sandbox_print_test() {
+    let mut vm = limited(Limits {
+        allow_print: false,
+        ..Limits::default()
+    });
+    expect_error(&mut vm, "print 1;", RUNTIME_SANDBOX_ERROR);
+    expect_reusable(&mut vm);
+}
+
+#[test]
+fn sandbox_import_test() {
+    let mut vm = limited(Limits {
+        allow_imports: false,
+        ..Limits::default()
+    });
+    expect_error(
+        &mut vm,
+        "import \"tests/modules/geometry.ys\";",
+        RUNTIME_SANDBOX_ERROR,
+    );
+    assert!(vm.modules.is_empty());
+    expect_reusable(&mut vm);
+}
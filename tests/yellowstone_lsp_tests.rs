@@ -0,0 +1,295 @@
+extern crate core;
+
+use std::io::{Cursor, Write};
+use std::process::{Command, Stdio};
+use yellowstone::lsp::json::Json;
+use yellowstone::lsp::{read_message, run, MAX_MESSAGE_LENGTH};
+
+const URI: &str = "file:///main.ys";
+
+/// Runs the server over `messages`, framed the way an editor would send them, and returns
+/// everything it wrote back.
+fn session(messages: &[String]) -> Vec<Json> {
+    let mut input = String::new();
+    for message in messages {
+        input.push_str(&format!(
+            "Content-Length: {}\r\n\r\n{message}",
+            message.len()
+        ));
+    }
+
+    let mut output = Vec::new();
+    run(Cursor::new(input), &mut output).unwrap();
+
+    let mut output = Cursor::new(output);
+    let mut replies = Vec::new();
+    while let Some(body) = read_message(&mut output).unwrap() {
+        replies.push(Json::parse(&body).unwrap());
+    }
+    replies
+}
+
+fn open(text: &str) -> String {
+    let text = Json::from(text);
+    format!(
+        r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{URI}","languageId":"yellowstone","version":1,"text":{text}}}}}}}"#
+    )
+}
+
+fn request(id: usize, method: &str, line: usize, character: usize) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","id":{id},"method":"{method}","params":{{"textDocument":{{"uri":"{URI}"}},"position":{{"line":{line},"character":{character}}},"context":{{"includeDeclaration":true}}}}}}"#
+    )
+}
+
+/// The reply to the request with `id`.
+fn reply(replies: &[Json], id: usize) -> &Json {
+    replies
+        .iter()
+        .find(|reply| reply.get("id").and_then(Json::as_f64) == Some(id as f64))
+        .unwrap_or_else(|| panic!("no reply to request {id}"))
+}
+
+fn start(range: &Json) -> (f64, f64) {
+    let start = range.get("start").unwrap();
+    (
+        start.get("line").unwrap().as_f64().unwrap(),
+        start.get("character").unwrap().as_f64().unwrap(),
+    )
+}
+
+const SOURCE: &str =
+    "var total = 0;\n{\n    var step = 2;\n    total = total + step;\n}\nprint total;\n";
+
+#[test]
+fn lsp_initialize_and_shutdown_test() {
+    let replies = session(&[
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"capabilities":{}}}"#
+            .to_string(),
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#.to_string(),
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#.to_string(),
+        r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+        // Nothing after `exit` is answered.
+        r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#.to_string(),
+    ]);
+    assert_eq!(2, replies.len());
+    let capabilities = reply(&replies, 1)
+        .path(&["result", "capabilities"])
+        .unwrap();
+    assert_eq!(
+        Some(true),
+        capabilities
+            .get("definitionProvider")
+            .and_then(Json::as_bool)
+    );
+    assert_eq!(Some(&Json::Null), reply(&replies, 2).get("result"));
+}
+
+#[test]
+fn lsp_diagnostics_test() {
+    let replies = session(&[open("var = 1;\nprint ;"), open("{ var unused = 1; }")]);
+
+    let errors = replies[0]
+        .path(&["params", "diagnostics"])
+        .unwrap()
+        .as_array()
+        .unwrap();
+    assert_eq!(2, errors.len());
+    assert_eq!(
+        Some("Expect variable name."),
+        errors[0].get("message").and_then(Json::as_str)
+    );
+    assert_eq!(Some(1.0), errors[0].get("severity").and_then(Json::as_f64));
+    assert_eq!((1.0, 6.0), start(errors[1].get("range").unwrap()));
+
+    let warnings = replies[1]
+        .path(&["params", "diagnostics"])
+        .unwrap()
+        .as_array()
+        .unwrap();
+    assert_eq!(1, warnings.len());
+    assert_eq!(
+        Some("unused_local"),
+        warnings[0].get("code").and_then(Json::as_str)
+    );
+    assert_eq!(
+        Some(2.0),
+        warnings[0].get("severity").and_then(Json::as_f64)
+    );
+}
+
+#[test]
+fn lsp_definition_and_references_test() {
+    let replies = session(&[
+        open(SOURCE),
+        // `step` in `total + step`
+        request(1, "textDocument/definition", 3, 22),
+        // `total` on the last line
+        request(2, "textDocument/references", 5, 7),
+        // nothing at the opening brace
+        request(3, "textDocument/definition", 1, 0),
+    ]);
+
+    let definition = reply(&replies, 1).get("result").unwrap();
+    assert_eq!(Some(URI), definition.get("uri").and_then(Json::as_str));
+    assert_eq!((2.0, 8.0), start(definition.get("range").unwrap()));
+
+    let references = reply(&replies, 2)
+        .get("result")
+        .unwrap()
+        .as_array()
+        .unwrap();
+    let starts: Vec<(f64, f64)> = references
+        .iter()
+        .map(|location| start(location.get("range").unwrap()))
+        .collect();
+    assert_eq!(
+        vec![(0.0, 4.0), (3.0, 4.0), (3.0, 12.0), (5.0, 6.0)],
+        starts
+    );
+
+    assert_eq!(Some(&Json::Null), reply(&replies, 3).get("result"));
+}
+
+#[test]
+fn lsp_hover_test() {
+    let replies = session(&[
+        open("var name = \"yellow\" + 1;\nprint sqrt(name);"),
+        request(1, "textDocument/hover", 1, 12),
+        request(2, "textDocument/hover", 1, 7),
+    ]);
+    let hover = |id| {
+        reply(&replies, id)
+            .path(&["result", "contents", "value"])
+            .and_then(Json::as_str)
+            .unwrap()
+            .to_string()
+    };
+    assert!(
+        hover(1).contains("(global) var name: string"),
+        "{}",
+        hover(1)
+    );
+    assert!(hover(2).contains("(native) fn sqrt"), "{}", hover(2));
+}
+
+#[test]
+fn lsp_symbols_and_completion_test() {
+    let replies = session(&[
+        open("import shapes from \"lib/shapes.ys\";\n{ var hidden = 1; }\nvar count = 3;"),
+        request(1, "textDocument/documentSymbol", 0, 0),
+        request(2, "textDocument/completion", 2, 0),
+        r#"{"jsonrpc":"2.0","id":3,"method":"textDocument/formatting","params":{}}"#.to_string(),
+    ]);
+
+    let symbols = reply(&replies, 1)
+        .get("result")
+        .unwrap()
+        .as_array()
+        .unwrap();
+    let names: Vec<&str> = symbols
+        .iter()
+        .map(|symbol| symbol.get("name").and_then(Json::as_str).unwrap())
+        .collect();
+    assert_eq!(vec!["shapes", "count"], names);
+
+    let items = reply(&replies, 2)
+        .get("result")
+        .unwrap()
+        .as_array()
+        .unwrap();
+    let labels: Vec<&str> = items
+        .iter()
+        .map(|item| item.get("label").and_then(Json::as_str).unwrap())
+        .collect();
+    for label in [
+        "while",
+        "assert_eq",
+        "import",
+        "count",
+        "shapes",
+        "sqrt",
+        "pi",
+    ] {
+        assert!(labels.contains(&label), "missing completion {label}");
+    }
+    assert!(!labels.contains(&"hidden"));
+    assert!(!labels.contains(&"class"));
+
+    let error = reply(&replies, 3).get("error").unwrap();
+    assert_eq!(Some(-32601.0), error.get("code").and_then(Json::as_f64));
+}
+
+#[test]
+fn lsp_non_ascii_over_stdio_test() {
+    let messages = [
+        open("// café\nvar a = 1;\nprint é;\n"),
+        open("// café\nvar a = \"né\";\nprint a;\n"),
+        request(1, "textDocument/hover", 2, 6),
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#.to_string(),
+        r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+    ];
+    let mut input = String::new();
+    for message in &messages {
+        input.push_str(&format!(
+            "Content-Length: {}\r\n\r\n{message}",
+            message.len()
+        ));
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_yellowstone-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let mut stdout = Cursor::new(output.stdout);
+    let mut replies = Vec::new();
+    while let Some(body) = read_message(&mut stdout).unwrap() {
+        replies.push(Json::parse(&body).unwrap());
+    }
+    let errors = replies[0]
+        .path(&["params", "diagnostics"])
+        .unwrap()
+        .as_array()
+        .unwrap();
+    assert_eq!(1, errors.len());
+    assert_eq!(
+        Some("Unexpected character."),
+        errors[0].get("message").and_then(Json::as_str)
+    );
+    assert_eq!((2.0, 6.0), start(errors[0].get("range").unwrap()));
+    let diagnostics = replies[1].path(&["params", "diagnostics"]).unwrap();
+    assert_eq!(Some(0), diagnostics.as_array().map(Vec::len));
+    let hover = reply(&replies, 1)
+        .path(&["result", "contents", "value"])
+        .and_then(Json::as_str)
+        .unwrap();
+    assert!(hover.contains("(global) var a"), "{hover}");
+}
+
+#[test]
+fn lsp_message_too_large_test() {
+    let mut input = Cursor::new(format!(
+        "Content-Length: {}\r\n\r\n{{}}",
+        MAX_MESSAGE_LENGTH + 1
+    ));
+    let error = read_message(&mut input).unwrap_err();
+    assert!(
+        error.to_string().contains("larger than the limit"),
+        "{error}"
+    );
+}
@@ -0,0 +1,158 @@
+extern crate core;
+
+use anyhow::Result;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::rc::Rc;
+use yellowstone::error::InterpretError::{self, *};
+use yellowstone::value::Value;
+use yellowstone::vm::VM;
+
+#[test]
+fn module_import_file_test() {
+    let mut vm = VM::default();
+    let result = vm.interpret_file(&module_path("main.ys"));
+    if result.is_err() {
+        eprintln!("{result:?}");
+        panic!()
+    }
+}
+
+#[test]
+fn module_import_named_test() {
+    let mut vm = VM::default();
+    let source = "
+        import shapes from \"tests/modules/geometry.ys\";
+        assert_eq(shapes.area, 9);
+    ";
+    let result = run_code(&mut vm, source);
+    if result.is_err() {
+        eprintln!("{result:?}");
+        panic!()
+    }
+}
+
+#[test]
+fn module_globals_are_isolated_test() {
+    let mut vm = VM::default();
+    let source = "
+        var sides = 1;
+        import \"tests/modules/geometry.ys\";
+        assert_eq(sides, 1);
+        assert_eq(geometry.sides, 4);
+    ";
+    let result = run_code(&mut vm, source);
+    if result.is_err() {
+        eprintln!("{result:?}");
+        panic!()
+    }
+    // the module's globals do not leak into the importer
+    assert!(vm.table.get("unit").is_none());
+}
+
+#[test]
+fn module_is_cached_test() {
+    let mut vm = VM::default();
+    let source = "
+        import a from \"tests/modules/geometry.ys\";
+        import b from \"tests/modules/geometry.ys\";
+    ";
+    run_code(&mut vm, source).unwrap();
+
+    let a = vm.table.get("a").unwrap().as_obj().unwrap();
+    let b = vm.table.get("b").unwrap().as_obj().unwrap();
+    assert!(Rc::ptr_eq(&a, &b));
+    assert_eq!(vm.modules.len(), 1);
+}
+
+#[test]
+fn module_cyclic_import_test() {
+    let mut vm = VM::default();
+    let result = vm.interpret_file(&module_path("cycle_a.ys"));
+    let error = result.unwrap_err();
+    assert_eq!(
+        format!("{}", error.root_cause()),
+        RUNTIME_IMPORT_ERROR.to_string()
+    );
+    assert!(format!("{error:#}").contains("cyclic import"));
+    assert!(vm.import_stack.is_empty());
+}
+
+#[test]
+fn module_missing_file_test() {
+    let mut vm = VM::default();
+    run_code_expect_error(
+        &mut vm,
+        "import \"tests/modules/missing.ys\";",
+        RUNTIME_IMPORT_ERROR,
+    );
+}
+
+#[test]
+fn module_compile_error_test() {
+    let mut vm = VM::default();
+    run_code_expect_error(
+        &mut vm,
+        "import \"tests/modules/broken.ys\";",
+        COMPILE_ERROR,
+    );
+}
+
+#[test]
+fn module_undefined_member_test() {
+    let mut vm = VM::default();
+    run_code_expect_error(
+        &mut vm,
+        "import \"tests/modules/geometry.ys\"; geometry.volume",
+        RUNTIME_UNRECOGNIZED_VARIABLE_ERROR,
+    );
+}
+
+#[test]
+fn module_import_inside_block_test() {
+    let mut vm = VM::default();
+    run_code_expect_error(
+        &mut vm,
+        "{ import \"tests/modules/geometry.ys\"; }",
+        COMPILE_ERROR,
+    );
+}
+
+#[test]
+fn module_member_of_non_module_test() {
+    let mut vm = VM::default();
+    run_code_expect_error(&mut vm, "var foo = 1; foo.bar", RUNTIME_ERROR);
+}
+
+// ################################################################################
+// Helper Functions
+// ################################################################################
+
+pub fn module_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("modules")
+        .join(name)
+}
+
+pub fn run_code<T: ToString + Display>(vm: &mut VM, source: T) -> Result<Option<Value>> {
+    vm.interpret(&source.to_string())
+}
+
+pub fn run_code_expect_error<T: ToString + Display>(
+    vm: &mut VM,
+    source: T,
+    expect: InterpretError,
+) {
+    let result = run_code(vm, source);
+    match result {
+        Err(e) => {
+            let root = e.root_cause();
+            assert_eq!(format!("{root}"), expect.to_string());
+        }
+        _ => {
+            eprintln!("value returned when error expected");
+            panic!()
+        }
+    }
+}
@@ -0,0 +1,93 @@
+extern crate core;
+
+use yellowstone::vm::VM;
+
+/// Runs `source` on a fresh VM and describes what happened: the error's root cause, or the value
+/// left by the chunk followed by each of `globals`.
+fn outcome(source: &str, globals: &[&str], optimize: bool) -> String {
+    let mut vm = VM::default();
+    vm.compile_options.optimize = optimize;
+    match vm.interpret(&source.to_string()) {
+        Err(e) => format!("error: {}", e.root_cause()),
+        Ok(result) => {
+            let mut outcome = format!("result: {result:?}");
+            for name in globals {
+                outcome.push_str(&format!(", {name} = {:?}", vm.table.get(name)));
+            }
+            outcome
+        }
+    }
+}
+
+/// Checks the optimized and unoptimized chunks for `source` behave the same.
+fn assert_equivalent(source: &str, globals: &[&str]) {
+    assert_eq!(
+        outcome(source, globals, false),
+        outcome(source, globals, true),
+        "optimizing changed the behaviour of: {source}"
+    );
+}
+
+#[test]
+fn optimizer_arithmetic_equivalence_test() {
+    assert_equivalent("1 + 2 * 3 - 4 / 2", &[]);
+    assert_equivalent("2 ** 3 ** 2 % 7", &[]);
+    assert_equivalent("-(1 + 2) * -3", &[]);
+    assert_equivalent("1 / 0", &[]);
+    assert_equivalent("0 / 0 + 1", &[]);
+    assert_equivalent("var a = (1 + 2) * 3; var b = a - 10 / 4;", &["a", "b"]);
+}
+
+#[test]
+fn optimizer_comparison_equivalence_test() {
+    for op in ["==", "!=", "<", "<=", ">", ">="] {
+        for (a, b) in [("1", "2"), ("2", "2"), ("3", "2"), ("0 / 0", "1")] {
+            assert_equivalent(&format!("{a} {op} {b}"), &[]);
+            assert_equivalent(&format!("var a = {a}; var b = a {op} {b};"), &["b"]);
+        }
+    }
+    assert_equivalent("nil == false", &[]);
+    assert_equivalent("true != !nil", &[]);
+    assert_equivalent("!(1 < 2) == !false", &[]);
+}
+
+#[test]
+fn optimizer_control_flow_equivalence_test() {
+    assert_equivalent(
+        "var a = 0; if (!(a == 1)) { a = 2; } else { a = 3; }",
+        &["a"],
+    );
+    assert_equivalent("var a = 0; if (a != 0) { a = 2; }", &["a"]);
+    assert_equivalent("var a = 0; if (false) { a = 2; } else { a = 3; }", &["a"]);
+    assert_equivalent("var a = 0; if (1 < 2) { a = 2; }", &["a"]);
+    assert_equivalent("var a = 0; while (false) { a = a + 1; }", &["a"]);
+    assert_equivalent(
+        "var a = 0; var n = 0; while (!(a >= 10)) { a = a + 1; n = n + 2; }",
+        &["a", "n"],
+    );
+    assert_equivalent(
+        "var a = 1 < 2 and 2 <= 2; var b = false or 3 > 4;",
+        &["a", "b"],
+    );
+    assert_equivalent("var a = nil and 1; var b = nil or !nil;", &["a", "b"]);
+}
+
+#[test]
+fn optimizer_error_equivalence_test() {
+    assert_equivalent("1 + true", &[]);
+    assert_equivalent("!5", &[]);
+    assert_equivalent("var a = 5; if (!a) { a = 1; }", &["a"]);
+    assert_equivalent("-\"yellow\"", &[]);
+    assert_equivalent("1 < nil", &[]);
+    assert_equivalent("assert_eq(1 + 1, 3);", &[]);
+    assert_equivalent("if (false) { undefined; } missing;", &[]);
+}
+
+#[test]
+fn optimizer_locals_and_strings_equivalence_test() {
+    assert_equivalent(
+        "var s = \"\"; { var a = 1; a = a + 2 * 3; s = \"yellow\" + \"stone\"; }",
+        &["s"],
+    );
+    assert_equivalent("var s = \"a\" + \"b\" == \"ab\";", &["s"]);
+}
@@ -0,0 +1,124 @@
+extern crate core;
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+use yellowstone::error::InterpretError;
+use yellowstone::value::{allocate_object, Value};
+use yellowstone::vm::VM;
+
+/// Collects what the scripts print while the VM owns it.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+/// Refuses every write, like a closed pipe.
+struct Closed;
+
+impl Write for Closed {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn captured(output: &Output) -> VM {
+    VM {
+        output: Box::new(output.clone()),
+        ..VM::default()
+    }
+}
+
+#[test]
+fn output_print_test() {
+    let output = Output::default();
+    let mut vm = captured(&output);
+    let source = "print 1.5; print \"yellow\" + \"stone\"; print 1 < 2; print -3;";
+    vm.interpret(&source.to_string()).unwrap();
+    assert_eq!("1.5\nyellowstone\ntrue\n-3\n", output.text());
+}
+
+#[test]
+fn output_of_locals_and_modules_test() {
+    let output = Output::default();
+    let mut vm = captured(&output);
+    // Reading a local used to list the constants as well.
+    let source = "{ var side = 3; print side; } import \"tests/modules/nested/loud.ys\";";
+    vm.interpret(&source.to_string()).unwrap();
+    assert_eq!("3\nloading cm\n", output.text());
+}
+
+#[test]
+fn output_suppressed_test() {
+    let mut vm = VM {
+        output: Box::new(io::sink()),
+        ..VM::default()
+    };
+    let result = vm.interpret(&"print 1; 2 + 3;".to_string()).unwrap();
+    assert_eq!(Some(5.), result.map(|value| value.as_number().unwrap()));
+}
+
+#[test]
+fn output_write_error_test() {
+    let mut vm = VM {
+        output: Box::new(Closed),
+        ..VM::default()
+    };
+    let error = vm.interpret(&"print 1;".to_string()).unwrap_err();
+    assert_eq!(
+        InterpretError::RUNTIME_ERROR.to_string(),
+        error.root_cause().to_string()
+    );
+    assert!(error.to_string().starts_with("cannot print: "), "{error}");
+}
+
+#[test]
+fn value_display_and_repr_test() {
+    let mut vm = VM::default();
+    let text = vm
+        .interpret(&"var text = \"say \" + \"hi\"; text;".to_string())
+        .unwrap()
+        .unwrap();
+    assert_eq!("say hi", text.to_string());
+    assert_eq!("\"say hi\"", text.repr());
+
+    let cases = [
+        (Value::nil_value(), "nil", "nil"),
+        (Value::bool_val(false), "false", "false"),
+        (Value::number_value(4.), "4", "4"),
+        (Value::number_value(0.25), "0.25", "0.25"),
+        (
+            allocate_object("a\t\"b\"".to_string()),
+            "a\t\"b\"",
+            "\"a\\t\\\"b\\\"\"",
+        ),
+        (
+            vm.table.get("sqrt").unwrap().clone(),
+            "<native fn sqrt>",
+            "<native fn sqrt>",
+        ),
+    ];
+    for (value, display, repr) in cases {
+        assert_eq!(display, value.to_string());
+        assert_eq!(repr, value.repr());
+        assert_eq!(repr, format!("{value:?}"));
+    }
+}
@@ -45,6 +45,30 @@ fn tokenizer_divide_test() {
     assert_tokens_are!("1 / 1", TOKEN_NUMBER, TOKEN_SLASH, TOKEN_NUMBER, TOKEN_EOF);
 }
 
+#[test]
+fn tokenizer_modulo_test() {
+    assert_tokens_are!(
+        "7 % 2",
+        TOKEN_NUMBER,
+        TOKEN_PERCENT,
+        TOKEN_NUMBER,
+        TOKEN_EOF
+    );
+}
+
+#[test]
+fn tokenizer_power_test() {
+    assert_tokens_are!(
+        "2 ** 3 * 4",
+        TOKEN_NUMBER,
+        TOKEN_STAR_STAR,
+        TOKEN_NUMBER,
+        TOKEN_STAR,
+        TOKEN_NUMBER,
+        TOKEN_EOF
+    );
+}
+
 #[test]
 fn tokenizer_expression_test() {
     assert_tokens_are!(
@@ -143,6 +167,21 @@ fn peek_next_test() {
     assert_eq!(None, sc.peek_next());
 }
 
+#[test]
+fn peek_non_ascii_test() {
+    let source = String::from("é!ü");
+    let mut sc = Scanner::new(&source);
+    assert_eq!("é", sc.peek().unwrap());
+    assert_eq!("!", sc.peek_next().unwrap());
+    sc.advance();
+    assert_eq!("!", sc.peek().unwrap());
+    assert_eq!("ü", sc.peek_next().unwrap());
+    sc.advance();
+    assert_eq!("ü", sc.peek().unwrap());
+    sc.advance();
+    assert_eq!(None, sc.peek());
+}
+
 #[test]
 fn skip_whitespace_test() {
     let source = String::from("    Hi!");
@@ -409,6 +448,32 @@ fn tokenizer_block_test() {
     );
 }
 
+#[test]
+fn tokenizer_import_test() {
+    let source = String::from("import \"util.ys\";");
+    assert_tokens_are!(
+        source,
+        TOKEN_IMPORT,
+        TOKEN_STRING,
+        TOKEN_SEMICOLON,
+        TOKEN_EOF
+    );
+
+    let source = String::from("import util from \"lib/util.ys\"; util.pi");
+    assert_tokens_are!(
+        source,
+        TOKEN_IMPORT,
+        TOKEN_IDENTIFIER,
+        TOKEN_IDENTIFIER,
+        TOKEN_STRING,
+        TOKEN_SEMICOLON,
+        TOKEN_IDENTIFIER,
+        TOKEN_DOT,
+        TOKEN_IDENTIFIER,
+        TOKEN_EOF
+    );
+}
+
 #[test]
 fn tokenizer_assert_test() {
     let source = String::from("assert_eq(true, true)");
@@ -530,3 +595,36 @@ fn tokenizer_for_test() {
         TOKEN_EOF
     )
 }
+
+#[test]
+fn tokenizer_comments_test() {
+    assert_tokens_are!(
+        "// first\n// second\n    1 // trailing\n+ 1",
+        TOKEN_NUMBER,
+        TOKEN_PLUS,
+        TOKEN_NUMBER,
+        TOKEN_EOF
+    );
+}
+
+#[test]
+fn tokenizer_non_ascii_test() {
+    assert_tokens_are!(
+        "// café\nprint \"né\";",
+        TOKEN_PRINT,
+        TOKEN_STRING,
+        TOKEN_SEMICOLON,
+        TOKEN_EOF
+    );
+    assert_tokens_are!("1 é", TOKEN_NUMBER, TOKEN_ERROR, TOKEN_EOF);
+}
+
+#[test]
+fn tokenizer_keywords_test() {
+    for kind in TokenKind::KEYWORDS {
+        let keyword = kind.keyword().unwrap().to_string();
+        let mut scanner = Scanner::from(&keyword);
+        assert_eq!(kind, scanner.scan_token().kind, "{keyword}");
+    }
+    assert_eq!(None, TOKEN_IDENTIFIER.keyword());
+}
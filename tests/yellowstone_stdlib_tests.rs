@@ -0,0 +1,280 @@
+extern crate core;
+
+use anyhow::Result;
+use std::fmt::Display;
+use yellowstone::error::InterpretError::{self, *};
+use yellowstone::value::Value;
+use yellowstone::vm::VM;
+
+#[test]
+fn math_modulo_test() {
+    let mut vm = VM::default();
+    run_code_expect_number(&mut vm, "7 % 3", 1.);
+    run_code_expect_number(&mut vm, "-7 % 3", -1.);
+    run_code_expect_number(&mut vm, "7.5 % 2", 1.5);
+}
+
+#[test]
+fn math_power_test() {
+    let mut vm = VM::default();
+    run_code_expect_number(&mut vm, "2 ** 3", 8.);
+    // right associative
+    run_code_expect_number(&mut vm, "2 ** 3 ** 2", 512.);
+    // binds tighter than unary minus and multiplication
+    run_code_expect_number(&mut vm, "-2 ** 2", -4.);
+    run_code_expect_number(&mut vm, "3 * 2 ** 2", 12.);
+}
+
+#[test]
+fn math_functions_test() {
+    let mut vm = VM::default();
+    let source = "
+        assert_eq(sqrt(16), 4);
+        assert_eq(pow(2, 10), 1024);
+        assert_eq(abs(-3.5), 3.5);
+        assert_eq(floor(2.7), 2);
+        assert_eq(ceil(2.1), 3);
+        assert_eq(round(2.5), 3);
+        assert_eq(min(3, -1), -1);
+        assert_eq(max(3, -1), 3);
+        assert_eq(sin(0), 0);
+        assert_eq(cos(0), 1);
+        assert_eq(log(1), 0);
+        assert_eq(floor(pi * 100), 314);
+    ";
+    let result = run_code(&mut vm, source);
+    if result.is_err() {
+        eprintln!("{result:?}");
+        panic!()
+    }
+}
+
+#[test]
+fn math_nested_calls_test() {
+    let mut vm = VM::default();
+    run_code_expect_number(&mut vm, "max(abs(-4), sqrt(9)) + 1", 5.);
+    run_code_expect_number(&mut vm, "-abs(-2)", -2.);
+}
+
+#[test]
+fn math_random_is_in_range_test() {
+    let mut vm = VM::default();
+    let source = "
+        var i = 0;
+        var r = 0;
+        while (i < 100) {
+            r = random();
+            assert_eq(true, r >= 0 and r < 1);
+            i = i + 1;
+        }
+    ";
+    let result = run_code(&mut vm, source);
+    if result.is_err() {
+        eprintln!("{result:?}");
+        panic!()
+    }
+}
+
+#[test]
+fn math_random_is_reproducible_test() {
+    let source = "random_seed(42); var a = random(); var b = random();";
+
+    let mut first = VM::default();
+    run_code(&mut first, source).unwrap();
+    let mut second = VM::default();
+    run_code(&mut second, source).unwrap();
+
+    let a = first.table.get("a").unwrap().as_number().unwrap();
+    let b = first.table.get("b").unwrap().as_number().unwrap();
+    assert_ne!(a, b);
+    assert_eq!(a, second.table.get("a").unwrap().as_number().unwrap());
+    assert_eq!(b, second.table.get("b").unwrap().as_number().unwrap());
+}
+
+#[test]
+fn math_seed_random_from_host_test() {
+    let mut first = VM::default();
+    first.seed_random(7);
+    let mut second = VM::default();
+    second.seed_random(7);
+
+    let a = run_code(&mut first, "random()").unwrap().unwrap();
+    let b = run_code(&mut second, "random()").unwrap().unwrap();
+    assert_eq!(a.as_number().unwrap(), b.as_number().unwrap());
+}
+
+#[test]
+fn math_wrong_arity_test() {
+    let mut vm = VM::default();
+    run_code_expect_error(&mut vm, "sqrt(1, 2)", RUNTIME_ERROR);
+}
+
+#[test]
+fn math_wrong_argument_type_test() {
+    let mut vm = VM::default();
+    run_code_expect_error(&mut vm, "sqrt(\"four\")", RUNTIME_ERROR);
+}
+
+#[test]
+fn call_non_function_test() {
+    let mut vm = VM::default();
+    run_code_expect_error(&mut vm, "var foo = 1; foo();", RUNTIME_ERROR);
+}
+
+#[test]
+fn process_args_test() {
+    let mut vm = VM {
+        args: vec!["first".to_string(), "second".to_string()],
+        ..VM::default()
+    };
+    run_code_expect_number(&mut vm, "arg_count()", 2.);
+    let source = "assert_eq(arg(0), \"first\"); assert_eq(arg(1), \"second\");";
+    assert!(run_code(&mut vm, source).is_ok());
+    run_code_expect_error(&mut vm, "arg(2)", RUNTIME_ERROR);
+    run_code_expect_error(&mut vm, "arg(0.5)", RUNTIME_ERROR);
+    run_code_expect_number(&mut VM::default(), "arg_count()", 0.);
+}
+
+#[test]
+fn assert_test() {
+    let mut vm = VM::default();
+    let source = "
+        assert(1 < 2);
+        assert(0, \"zero is true\");
+        assert_ne(1, 2);
+        assert_ne(\"a\", \"b\");
+        assert_ne(1, \"1\");
+        assert_approx(0.1 + 0.2, 0.3, 0.0001);
+        assert_approx(10, 10.5, 0.5, \"close enough\");
+    ";
+    if let Err(e) = run_code(&mut vm, source) {
+        panic!("{e:#}");
+    }
+    run_code_expect_error(&mut vm, "assert(nil);", RUNTIME_ASSERT_ERROR);
+    run_code_expect_error(&mut vm, "assert_ne(2, 1 + 1);", RUNTIME_ASSERT_ERROR);
+    run_code_expect_error(
+        &mut vm,
+        "assert_approx(1, 1.1, 0.01);",
+        RUNTIME_ASSERT_ERROR,
+    );
+    run_code_expect_error(&mut vm, "assert_approx(1, 1, -1);", RUNTIME_ERROR);
+    run_code_expect_error(&mut vm, "assert(true, 1);", RUNTIME_ERROR);
+    run_code_expect_error(&mut vm, "assert(true, \"a\", \"b\");", RUNTIME_ERROR);
+}
+
+#[test]
+fn assert_messages_test() {
+    let mut vm = VM::default();
+    let message = |vm: &mut VM, source: &str| format!("{}", run_code(vm, source).unwrap_err());
+    assert_eq!("assert failed", message(&mut vm, "assert(false);"));
+    assert_eq!(
+        "positive, left: -1, right: 0, epsilon: 0.5",
+        message(&mut vm, "assert_approx(-1, 0, 0.5, \"positive\");")
+    );
+    assert_eq!(
+        "assert_ne failed, left: \"a\", right: \"a\"",
+        message(&mut vm, "assert_ne(\"a\", \"a\");")
+    );
+    assert_eq!(
+        "assert_error failed, no error from: \"1 + 1;\"",
+        message(&mut vm, "assert_error(\"1 + 1;\");")
+    );
+    assert_eq!(
+        "sums, left: 3, right: 4",
+        message(&mut vm, "assert_eq(1 + 2, 4, \"sums\");")
+    );
+}
+
+#[test]
+fn assert_eq_mixed_kinds_test() {
+    let mut vm = VM::default();
+    let message = |vm: &mut VM, source: &str| format!("{}", run_code(vm, source).unwrap_err());
+    run_code_expect_error(&mut vm, "assert_eq(1, \"1\");", RUNTIME_ASSERT_ERROR);
+    assert_eq!(
+        "assert_eq failed, left: 1, right: \"1\"",
+        message(&mut vm, "assert_eq(1, \"1\");")
+    );
+    assert_eq!(
+        "assert_eq failed, left: nil, right: false",
+        message(&mut vm, "assert_eq(nil, false);")
+    );
+    run_code_expect_error(&mut vm, "assert_eq(1, 2, \"msg\");", RUNTIME_ASSERT_ERROR);
+    if let Err(e) = run_code(&mut vm, "assert_eq(1, 1, \"msg\"); assert_ne(1, \"1\");") {
+        panic!("{e:#}");
+    }
+}
+
+#[test]
+fn assert_error_test() {
+    let mut vm = VM::default();
+    let source = "
+        var limit = 3;
+        var error = assert_error(\"limit + nil;\");
+        assert_eq(error, \"Operands must be two numbers or two strings.\");
+        assert_eq(assert_error(\"assert_eq(limit, 4);\"), \"assert_eq failed, left: 3, right: 4\");
+        assert_eq(assert_error(\"missing;\"), \"undefined variable: missing\");
+        assert_eq(limit, 3);
+    ";
+    if let Err(e) = run_code(&mut vm, source) {
+        panic!("{e:#}");
+    }
+    // Code that does not compile is an error of the test itself.
+    run_code_expect_error(&mut vm, "assert_error(\"limit +;\");", COMPILE_ERROR);
+    run_code_expect_error(&mut vm, "assert_error(1);", RUNTIME_ERROR);
+}
+
+#[test]
+fn assert_equality_test() {
+    let mut vm = VM::default();
+    let source = "
+        import \"tests/modules/geometry.ys\";
+        import \"tests/modules/square.ys\";
+        import \"tests/modules/nested/consts.ys\";
+        assert_ne(geometry, square);
+        assert_ne(geometry, consts);
+        assert_eq(consts.geometry, geometry);
+        assert_eq(sqrt, sqrt);
+        assert_ne(sqrt, abs);
+        assert_eq(\"ab\", \"a\" + \"b\");
+    ";
+    if let Err(e) = run_code(&mut vm, source) {
+        panic!("{e:#}");
+    }
+}
+
+// ################################################################################
+// Helper Functions
+// ################################################################################
+
+pub fn run_code<T: ToString + Display>(vm: &mut VM, source: T) -> Result<Option<Value>> {
+    vm.interpret(&source.to_string())
+}
+
+pub fn run_code_expect_number<T: ToString + Display>(vm: &mut VM, source: T, expect: f32) {
+    let result = run_code(vm, source);
+    match result {
+        Ok(Some(v)) => assert_eq!(expect, v.as_number().unwrap()),
+        _ => {
+            eprintln!("error returned when value expected: {result:?}");
+            panic!()
+        }
+    }
+}
+
+pub fn run_code_expect_error<T: ToString + Display>(
+    vm: &mut VM,
+    source: T,
+    expect: InterpretError,
+) {
+    let result = run_code(vm, source);
+    match result {
+        Err(e) => {
+            let root = e.root_cause();
+            assert_eq!(format!("{root}"), expect.to_string());
+        }
+        _ => {
+            eprintln!("value returned when error expected");
+            panic!()
+        }
+    }
+}
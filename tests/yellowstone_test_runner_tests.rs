@@ -0,0 +1,101 @@
+extern crate core;
+
+use std::path::PathBuf;
+use yellowstone::test_runner::{discover, run, run_file, Summary, TestOptions};
+
+fn runner_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("runner")
+}
+
+fn run_tests(paths: &[PathBuf], filter: Option<&str>) -> (Summary, String) {
+    let options = TestOptions {
+        filter: filter.map(str::to_string),
+        ..TestOptions::default()
+    };
+    let mut output = Vec::new();
+    let summary = run(paths, &options, &mut output).unwrap();
+    (summary, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn test_runner_discover_test() {
+    let files = discover(&[runner_dir()]).unwrap();
+    let names: Vec<String> = files
+        .iter()
+        .map(|file| file.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    // helper.ys does not end with `_test.ys`.
+    assert_eq!(vec!["math_test.ys", "plain_test.ys"], names);
+}
+
+#[test]
+fn test_runner_blocks_test() {
+    let (results, filtered) = run_file(&runner_dir().join("math_test.ys"), &TestOptions::default());
+    assert_eq!(0, filtered);
+    let names: Vec<&str> = results
+        .iter()
+        .map(|result| result.name.rsplit("::").next().unwrap())
+        .collect();
+    assert_eq!(vec!["adds", "multiplies", "reads imports"], names);
+
+    assert!(results[0].failure.is_none());
+    let failure = results[1].failure.as_ref().unwrap();
+    assert!(failure.starts_with("[line 10] "), "{failure}");
+    assert!(failure.contains("left: 6, right: 7"), "{failure}");
+    assert!(results[2].failure.is_none(), "{:?}", results[2].failure);
+}
+
+#[test]
+fn test_runner_summary_test() {
+    let (summary, output) = run_tests(&[runner_dir()], None);
+    assert_eq!(
+        Summary {
+            passed: 3,
+            failed: 1,
+            filtered: 0
+        },
+        summary
+    );
+    assert!(!summary.is_ok());
+    assert!(
+        output.contains("math_test.ys::multiplies ... FAILED\n"),
+        "{output}"
+    );
+    assert!(output.contains("plain_test.ys ... ok\n"), "{output}");
+    assert!(output.contains("\nfailures:\n"), "{output}");
+    assert!(
+        output.contains("test result: FAILED. 3 passed; 1 failed;"),
+        "{output}"
+    );
+}
+
+#[test]
+fn test_runner_filter_test() {
+    let (summary, output) = run_tests(&[runner_dir()], Some("imports"));
+    assert_eq!(
+        Summary {
+            passed: 1,
+            failed: 0,
+            filtered: 3
+        },
+        summary
+    );
+    assert!(
+        output.contains("math_test.ys::reads imports ... ok\n"),
+        "{output}"
+    );
+    assert!(!output.contains("failures:"), "{output}");
+}
+
+#[test]
+fn test_runner_parse_error_test() {
+    let broken = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("modules")
+        .join("broken.ys");
+    let (results, _) = run_file(&broken, &TestOptions::default());
+    assert_eq!(1, results.len());
+    assert!(results[0].failure.is_some());
+}
@@ -0,0 +1,152 @@
+extern crate core;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use yellowstone::error::InterpretError;
+use yellowstone::value::{allocate_object, Value, ValueKind};
+use yellowstone::vm::VM;
+
+fn hash(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn evaluate(vm: &mut VM, source: &str) -> Value {
+    vm.interpret(&source.to_string()).unwrap().unwrap()
+}
+
+#[test]
+fn value_number_equality_test() {
+    assert_eq!(Value::number_value(1.5), Value::number_value(1.5));
+    assert_ne!(Value::number_value(1.), Value::number_value(2.));
+    // IEEE semantics: NaN is not equal to itself, 0 and -0 are equal and hash the same.
+    let nan = Value::number_value(f32::NAN);
+    assert_ne!(nan, nan.clone());
+    assert_eq!(Value::number_value(0.), Value::number_value(-0.));
+    assert_eq!(
+        hash(&Value::number_value(0.)),
+        hash(&Value::number_value(-0.))
+    );
+}
+
+#[test]
+fn value_kinds_are_never_equal_test() {
+    let values = [
+        Value::nil_value(),
+        Value::bool_val(false),
+        Value::number_value(0.),
+        allocate_object("0".to_string()),
+    ];
+    for (i, a) in values.iter().enumerate() {
+        for (j, b) in values.iter().enumerate() {
+            assert_eq!(i == j, a == b, "{a:?} == {b:?}");
+        }
+    }
+    assert_ne!(Value::nil_value(), 0.);
+    assert_ne!(Value::number_value(1.), true);
+}
+
+#[test]
+fn value_strings_by_content_test() {
+    let a = allocate_object("stone".to_string());
+    let b = allocate_object("stone");
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+    assert_ne!(a, allocate_object("Stone".to_string()));
+    assert_eq!(a, *"stone");
+    assert_eq!(*"stone", a);
+}
+
+#[test]
+fn value_objects_by_identity_test() {
+    let mut vm = VM::default();
+    let sqrt = vm.table.get("sqrt").unwrap().clone();
+    assert_eq!(sqrt, vm.table.get("sqrt").unwrap().clone());
+    assert_eq!(hash(&sqrt), hash(&vm.table.get("sqrt").unwrap().clone()));
+    assert_ne!(sqrt, vm.table.get("abs").unwrap().clone());
+    assert_ne!(sqrt, VM::default().table.get("sqrt").unwrap().clone());
+
+    let geometry = evaluate(&mut vm, "import \"tests/modules/geometry.ys\"; geometry;");
+    let square = evaluate(&mut vm, "import \"tests/modules/square.ys\"; square;");
+    assert_eq!(geometry, evaluate(&mut vm, "geometry;"));
+    assert_ne!(geometry, square);
+    // Objects are not equal to the text they print as.
+    assert_ne!(geometry, allocate_object("<module geometry>".to_string()));
+}
+
+#[test]
+fn value_equality_in_scripts_test() {
+    let mut vm = VM::default();
+    let source = "
+        import \"tests/modules/geometry.ys\";
+        import \"tests/modules/square.ys\";
+        assert_eq(\"yellow\" + \"stone\" == \"yellowstone\", true);
+        assert_eq(sqrt == sqrt, true);
+        assert_eq(sqrt == abs, false);
+        assert_eq(geometry == square, false);
+        assert_eq(geometry == \"<module geometry>\", false);
+        assert_eq(nil == false, false);
+        assert_eq(0 == -0, true);
+        assert_eq(1 != \"1\", true);
+    ";
+    if let Err(e) = vm.interpret(&source.to_string()) {
+        panic!("{e:#}");
+    }
+}
+
+#[test]
+fn value_assert_eq_uses_equality_test() {
+    let mut vm = VM::default();
+    let source = "
+        import \"tests/modules/geometry.ys\";
+        import \"tests/modules/square.ys\";
+        assert_eq(geometry, geometry);
+        assert_eq(0, -0);
+        assert_eq(\"ab\", \"a\" + \"b\");
+        assert_eq(nil, nil);
+    ";
+    if let Err(e) = vm.interpret(&source.to_string()) {
+        panic!("{e:#}");
+    }
+    // Whatever `==` says about two values, `assert_eq` says the same.
+    for (left, right) in [
+        ("0 / 0", "0 / 0"),
+        ("1", "\"1\""),
+        ("nil", "false"),
+        ("geometry", "geometry"),
+        ("geometry", "square"),
+        ("sqrt", "sqrt"),
+        ("sqrt", "abs"),
+    ] {
+        let equal = evaluate(&mut vm, &format!("{left} == {right}"));
+        let assertion = vm.interpret(&format!("assert_eq({left}, {right});"));
+        assert_eq!(equal, Value::bool_val(assertion.is_ok()), "{left}, {right}");
+    }
+}
+
+#[test]
+fn value_kind_test() {
+    assert_eq!(ValueKind::ValNil, Value::nil_value().kind());
+    assert_eq!(ValueKind::ValBool, Value::bool_val(true).kind());
+    assert_eq!(ValueKind::ValNumber, Value::number_value(2.).kind());
+    let text = allocate_object("text".to_string());
+    assert_eq!(ValueKind::ValObj, text.kind());
+    assert!(text.is_obj() && Value::is_string(&text));
+    assert_eq!(ValueKind::ValObj, text.clone().kind());
+    assert!(Value::nil_value().as_number().is_err());
+}
+
+#[test]
+fn value_as_string_test() {
+    assert_eq!(
+        "text",
+        allocate_object("text".to_string()).as_string().unwrap()
+    );
+    let error = Value::number_value(2.).as_string().unwrap_err();
+    assert_eq!(
+        error.root_cause().downcast_ref::<InterpretError>(),
+        Some(&InterpretError::RUNTIME_ERROR)
+    );
+    assert!(format!("{error:#}").contains("cannot convert type to a string"));
+}